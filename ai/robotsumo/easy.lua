@@ -0,0 +1,9 @@
+-- Easy robot: doesn't look for the opponent at all. It drives round the
+-- ring and turns left whenever the edge gets close.
+
+function think()
+    if get_distance_to_edge() < 3 then
+        turn_left()
+    end
+    move_forward()
+end
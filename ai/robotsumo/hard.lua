@@ -0,0 +1,33 @@
+-- Hard robot: knows exactly which way it faces, so it turns to the
+-- opponent without overshooting and charges. It backs away from the edge
+-- when it is about to drive out on its own.
+
+local function angle_to(x, y)
+    return math.deg(math.atan(y, x))
+end
+
+-- Difference between two angles in degrees, in -180..180.
+local function angle_difference(a, b)
+    return (a - b + 540) % 360 - 180
+end
+
+function think()
+    local x, y = get_position()
+    local ox, oy = get_opponent_position()
+    local heading = observation.heading
+
+    local turn = angle_difference(angle_to(ox - x, oy - y), heading)
+    if turn > 3 then
+        turn_left()
+    elseif turn < -3 then
+        turn_right()
+    end
+
+    -- Facing away from the centre this close to the edge means driving out
+    local outwards = math.abs(angle_difference(angle_to(x, y), heading)) < 90
+    if get_distance_to_edge() < 1.5 and outwards and get_distance_to_opponent() > 3 then
+        move_backward()
+    else
+        move_forward()
+    end
+end
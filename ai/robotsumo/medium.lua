@@ -0,0 +1,24 @@
+-- Medium robot: drives at the opponent, but only knows which way it faces
+-- from how it moved since the last tick, so it aims late and overshoots.
+-- It never checks how close it is to the edge.
+
+local last_x, last_y
+
+function think()
+    local x, y = get_position()
+    local ox, oy = get_opponent_position()
+
+    if last_x then
+        local mx, my = x - last_x, y - last_y
+        -- Positive when the opponent is to the left of the way we move
+        local cross = mx * (oy - y) - my * (ox - x)
+        if cross > 0.05 then
+            turn_left()
+        elseif cross < -0.05 then
+            turn_right()
+        end
+    end
+    last_x, last_y = x, y
+
+    move_forward()
+end
@@ -0,0 +1,41 @@
+-- Easy snake: heads straight for the food and keeps off the walls, but
+-- never looks out for snakes, not even its own tail.
+
+local OFFSETS = { up = { 0, 1 }, right = { 1, 0 }, down = { 0, -1 }, left = { -1, 0 } }
+local LEFT_OF = { up = "left", left = "down", down = "right", right = "up" }
+local RIGHT_OF = { up = "right", right = "down", down = "left", left = "up" }
+
+local function inside(x, y)
+    return x >= 0 and y >= 0 and x < observation.width and y < observation.height
+end
+
+function think()
+    local x, y = get_head_position()
+    local fx, fy = get_food_position()
+    local direction = get_direction()
+
+    local moves = {
+        { turn = nil, direction = direction },
+        { turn = turn_left, direction = LEFT_OF[direction] },
+        { turn = turn_right, direction = RIGHT_OF[direction] },
+    }
+
+    local best, best_distance
+    for _, move in ipairs(moves) do
+        local offset = OFFSETS[move.direction]
+        local nx, ny = x + offset[1], y + offset[2]
+        if inside(nx, ny) then
+            local distance = 0
+            if fx then
+                distance = math.abs(fx - nx) + math.abs(fy - ny)
+            end
+            if best == nil or distance < best_distance then
+                best, best_distance = move, distance
+            end
+        end
+    end
+
+    if best and best.turn then
+        best.turn()
+    end
+end
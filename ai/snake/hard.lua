@@ -0,0 +1,106 @@
+-- Hard snake: counts the free space behind every move so it never walks
+-- into a dead end, stays away from the heads of snakes that could win a
+-- head-on crash, and only then heads for the food.
+
+local OFFSETS = { up = { 0, 1 }, right = { 1, 0 }, down = { 0, -1 }, left = { -1, 0 } }
+-- Walked in a fixed order so every decision is the same on replay
+local NEIGHBOURS = { OFFSETS.up, OFFSETS.right, OFFSETS.down, OFFSETS.left }
+local LEFT_OF = { up = "left", left = "down", down = "right", right = "up" }
+local RIGHT_OF = { up = "right", right = "down", down = "left", left = "up" }
+
+local function key(x, y)
+    return y * observation.width + x
+end
+
+local function inside(x, y)
+    return x >= 0 and y >= 0 and x < observation.width and y < observation.height
+end
+
+-- Cells that will still be taken after this tick. Our own tail moves away,
+-- other snakes' tails might not if they eat.
+local function blocked_cells()
+    local blocked = {}
+    local body = observation.body
+    for i = 1, #body - 1 do
+        blocked[key(body[i].x, body[i].y)] = true
+    end
+    for _, opponent in ipairs(observation.opponents) do
+        for _, cell in ipairs(opponent) do
+            blocked[key(cell.x, cell.y)] = true
+        end
+    end
+    return blocked
+end
+
+-- Cells next to the head of a snake at least as long as us: if both heads
+-- end up there, we don't survive the crash.
+local function dangerous_cells()
+    local danger = {}
+    for _, opponent in ipairs(observation.opponents) do
+        if #opponent >= observation.length then
+            local head = opponent[1]
+            for _, offset in ipairs(NEIGHBOURS) do
+                danger[key(head.x + offset[1], head.y + offset[2])] = true
+            end
+        end
+    end
+    return danger
+end
+
+-- Number of free cells reachable from (x, y), counting at most `limit`.
+local function space(x, y, blocked, limit)
+    local seen = { [key(x, y)] = true }
+    local queue = { { x, y } }
+    local head = 1
+    while head <= #queue and #queue < limit do
+        local cell = queue[head]
+        head = head + 1
+        for _, offset in ipairs(NEIGHBOURS) do
+            local nx, ny = cell[1] + offset[1], cell[2] + offset[2]
+            local k = key(nx, ny)
+            if inside(nx, ny) and not blocked[k] and not seen[k] then
+                seen[k] = true
+                queue[#queue + 1] = { nx, ny }
+            end
+        end
+    end
+    return math.min(#queue, limit)
+end
+
+function think()
+    local x, y = get_head_position()
+    local fx, fy = get_food_position()
+    local direction = get_direction()
+    local blocked = blocked_cells()
+    local danger = dangerous_cells()
+    local room = 2 * observation.length + 4
+
+    local moves = {
+        { turn = nil, direction = direction },
+        { turn = turn_left, direction = LEFT_OF[direction] },
+        { turn = turn_right, direction = RIGHT_OF[direction] },
+    }
+
+    local best, best_score
+    for _, move in ipairs(moves) do
+        local offset = OFFSETS[move.direction]
+        local nx, ny = x + offset[1], y + offset[2]
+        if inside(nx, ny) and not blocked[key(nx, ny)] then
+            -- Enough room to keep moving matters most, then safety, then food
+            local score = space(nx, ny, blocked, room) * 1000
+            if danger[key(nx, ny)] then
+                score = score - 5000
+            end
+            if fx then
+                score = score - math.abs(fx - nx) - math.abs(fy - ny)
+            end
+            if best == nil or score > best_score then
+                best, best_score = move, score
+            end
+        end
+    end
+
+    if best and best.turn then
+        best.turn()
+    end
+end
@@ -0,0 +1,63 @@
+-- Medium snake: heads for the food like the easy snake, but never moves
+-- into a wall or a snake if it can help it. It doesn't think ahead, so it
+-- can still trap itself.
+
+local OFFSETS = { up = { 0, 1 }, right = { 1, 0 }, down = { 0, -1 }, left = { -1, 0 } }
+local LEFT_OF = { up = "left", left = "down", down = "right", right = "up" }
+local RIGHT_OF = { up = "right", right = "down", down = "left", left = "up" }
+
+local function key(x, y)
+    return y * observation.width + x
+end
+
+local function inside(x, y)
+    return x >= 0 and y >= 0 and x < observation.width and y < observation.height
+end
+
+-- Cells that will still be taken after this tick. Our own tail moves away,
+-- other snakes' tails might not if they eat.
+local function blocked_cells()
+    local blocked = {}
+    local body = observation.body
+    for i = 1, #body - 1 do
+        blocked[key(body[i].x, body[i].y)] = true
+    end
+    for _, opponent in ipairs(observation.opponents) do
+        for _, cell in ipairs(opponent) do
+            blocked[key(cell.x, cell.y)] = true
+        end
+    end
+    return blocked
+end
+
+function think()
+    local x, y = get_head_position()
+    local fx, fy = get_food_position()
+    local direction = get_direction()
+    local blocked = blocked_cells()
+
+    local moves = {
+        { turn = nil, direction = direction },
+        { turn = turn_left, direction = LEFT_OF[direction] },
+        { turn = turn_right, direction = RIGHT_OF[direction] },
+    }
+
+    local best, best_distance
+    for _, move in ipairs(moves) do
+        local offset = OFFSETS[move.direction]
+        local nx, ny = x + offset[1], y + offset[2]
+        if inside(nx, ny) and not blocked[key(nx, ny)] then
+            local distance = 0
+            if fx then
+                distance = math.abs(fx - nx) + math.abs(fy - ny)
+            end
+            if best == nil or distance < best_distance then
+                best, best_distance = move, distance
+            end
+        end
+    end
+
+    if best and best.turn then
+        best.turn()
+    end
+end
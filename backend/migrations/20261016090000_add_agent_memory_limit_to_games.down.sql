@@ -0,0 +1 @@
+ALTER TABLE games DROP COLUMN agent_memory_limit;
@@ -0,0 +1,2 @@
+-- Memory limit (in bytes) for each agent's Lua VM, configured per game
+ALTER TABLE games ADD COLUMN agent_memory_limit INTEGER NOT NULL DEFAULT 16777216;
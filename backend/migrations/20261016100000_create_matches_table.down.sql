@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_match_agents_agent;
+DROP TABLE IF EXISTS match_agents;
+DROP TABLE IF EXISTS matches;
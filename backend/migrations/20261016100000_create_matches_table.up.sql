@@ -0,0 +1,32 @@
+-- Matches: a game played between two or more agents
+CREATE TABLE matches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    -- User who started the match
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    seed INTEGER NOT NULL,
+
+    -- Results, set once the match has been played
+    winner INTEGER,
+    ticks INTEGER,
+    termination TEXT,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    finished_at TEXT
+);
+
+-- Agents taking part in a match, one row per seat (player index in the game)
+CREATE TABLE match_agents (
+    match_id INTEGER NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    seat INTEGER NOT NULL,
+    -- Kept as NULL when the agent is deleted so the match history stays intact
+    agent_id INTEGER REFERENCES agents(id) ON DELETE SET NULL,
+    score INTEGER,
+    -- Error that stopped this agent, if any
+    error TEXT,
+
+    PRIMARY KEY (match_id, seat)
+);
+
+-- Index for listing the matches of an agent
+CREATE INDEX idx_match_agents_agent ON match_agents(agent_id);
@@ -0,0 +1,4 @@
+ALTER TABLE matches DROP COLUMN error;
+ALTER TABLE matches DROP COLUMN status;
+DROP INDEX IF EXISTS idx_jobs_status;
+DROP TABLE IF EXISTS jobs;
@@ -0,0 +1,24 @@
+-- Background jobs, processed by the worker pool. Kept in the database so
+-- queued work survives restarts.
+CREATE TABLE jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- What to do, e.g. 'match'
+    kind TEXT NOT NULL,
+    -- ID of the row the job works on, in the table for its kind
+    target_id INTEGER NOT NULL,
+    -- 'queued', 'running', 'finished' or 'failed'
+    status TEXT NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    started_at TEXT,
+    finished_at TEXT
+);
+
+-- Index for workers looking for the next queued job
+CREATE INDEX idx_jobs_status ON jobs(status, id);
+
+-- Matches follow the status of their job
+ALTER TABLE matches ADD COLUMN status TEXT NOT NULL DEFAULT 'queued';
+ALTER TABLE matches ADD COLUMN error TEXT;
+UPDATE matches SET status = 'finished' WHERE finished_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Recording of each played match, in the game_core::replay format
+CREATE TABLE match_replays (
+    match_id INTEGER PRIMARY KEY REFERENCES matches(id) ON DELETE CASCADE,
+    data BLOB NOT NULL
+);
@@ -0,0 +1,3 @@
+ALTER TABLE match_agents DROP COLUMN agent_version;
+ALTER TABLE agents DROP COLUMN version;
+DROP TABLE IF EXISTS agent_versions;
@@ -0,0 +1,20 @@
+-- Agent versions: every saved revision of an agent's code, never changed once written
+CREATE TABLE agent_versions (
+    agent_id INTEGER NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    -- Numbered from 1 for each agent
+    version INTEGER NOT NULL,
+    code TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    PRIMARY KEY (agent_id, version)
+);
+
+-- Latest version of each agent, the one whose code is in agents.code
+ALTER TABLE agents ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+-- Existing agents start out with their current code as version 1
+INSERT INTO agent_versions (agent_id, version, code, created_at)
+SELECT id, 1, code, updated_at FROM agents;
+
+-- Version of the agent that plays in each seat, NULL for matches from before versions
+ALTER TABLE match_agents ADD COLUMN agent_version INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE agents DROP COLUMN draft_updated_at;
+ALTER TABLE agents DROP COLUMN draft;
@@ -0,0 +1,3 @@
+-- Unpublished working copy of an agent's code, saved without validation
+ALTER TABLE agents ADD COLUMN draft TEXT;
+ALTER TABLE agents ADD COLUMN draft_updated_at TEXT;
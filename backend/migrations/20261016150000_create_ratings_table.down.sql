@@ -0,0 +1,4 @@
+ALTER TABLE matches DROP COLUMN rating_order;
+ALTER TABLE matches DROP COLUMN rated;
+DROP INDEX IF EXISTS idx_ratings_game;
+DROP TABLE IF EXISTS ratings;
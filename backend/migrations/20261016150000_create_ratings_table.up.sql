@@ -0,0 +1,23 @@
+-- Ratings: Glicko-2 skill rating of each agent version, updated after every rated match
+CREATE TABLE ratings (
+    agent_id INTEGER NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    agent_version INTEGER NOT NULL,
+    game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    rating REAL NOT NULL,
+    deviation REAL NOT NULL,
+    volatility REAL NOT NULL,
+    -- Number of rated matches played
+    matches INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    PRIMARY KEY (agent_id, agent_version)
+);
+
+-- Index for ranking the agents of a game
+CREATE INDEX idx_ratings_game ON ratings(game_id, rating);
+
+-- Whether a match counts towards ratings, only when asked for
+ALTER TABLE matches ADD COLUMN rated BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Order in which rated matches were applied to the ratings, NULL until applied
+ALTER TABLE matches ADD COLUMN rating_order INTEGER;
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_tournament_pairings_match;
+DROP INDEX IF EXISTS idx_tournament_pairings_tournament;
+DROP TABLE IF EXISTS tournament_pairings;
+DROP INDEX IF EXISTS idx_tournament_entrants_agent;
+DROP TABLE IF EXISTS tournament_entrants;
+DROP TABLE IF EXISTS tournaments;
@@ -0,0 +1,54 @@
+-- Tournaments: a competition between agents of one game, played in rounds of
+-- head-to-head matches
+CREATE TABLE tournaments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    -- User who runs the tournament, and starts its matches
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- 'round_robin', 'single_elimination', 'double_elimination' or 'swiss'
+    format TEXT NOT NULL,
+    -- Number of rounds of a Swiss tournament, NULL to pick one from the number of entrants
+    rounds INTEGER,
+    -- 'pending' while enrolling agents, then 'running' and 'finished'
+    status TEXT NOT NULL DEFAULT 'pending',
+    -- Round being played, 0 before the first one is scheduled
+    current_round INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    started_at TEXT,
+    finished_at TEXT
+);
+
+-- Agents enrolled in a tournament, each playing a fixed version
+CREATE TABLE tournament_entrants (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    -- Kept as NULL when the agent is deleted so the results stay intact
+    agent_id INTEGER REFERENCES agents(id) ON DELETE SET NULL,
+    agent_version INTEGER NOT NULL,
+    -- Position in the seeding, 1 for the favourite, set when the tournament starts
+    seed INTEGER,
+
+    UNIQUE (tournament_id, agent_id)
+);
+
+-- Index for finding the tournaments an agent plays in
+CREATE INDEX idx_tournament_entrants_agent ON tournament_entrants(agent_id);
+
+-- Pairings of entrants in each round. A pairing without a second entrant is a bye.
+CREATE TABLE tournament_pairings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    round INTEGER NOT NULL,
+    -- 'winners', 'losers' or 'final' in double elimination, NULL otherwise
+    bracket TEXT,
+    -- Entrants in seat order
+    first_entrant_id INTEGER NOT NULL REFERENCES tournament_entrants(id) ON DELETE CASCADE,
+    second_entrant_id INTEGER REFERENCES tournament_entrants(id) ON DELETE CASCADE,
+    -- Match between the entrants, NULL for a bye
+    match_id INTEGER REFERENCES matches(id) ON DELETE SET NULL
+);
+
+-- Indexes for showing a tournament's rounds and finding the pairing of a match
+CREATE INDEX idx_tournament_pairings_tournament ON tournament_pairings(tournament_id, round);
+CREATE INDEX idx_tournament_pairings_match ON tournament_pairings(match_id);
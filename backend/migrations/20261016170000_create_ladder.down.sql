@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS ladder_scheduler;
+DROP INDEX IF EXISTS idx_matches_ladder;
+ALTER TABLE matches DROP COLUMN ladder;
+ALTER TABLE agents DROP COLUMN ladder;
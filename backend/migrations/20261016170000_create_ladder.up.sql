@@ -0,0 +1,18 @@
+-- Whether an agent plays on its game's ladder, against opponents the server picks
+ALTER TABLE agents ADD COLUMN ladder BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Whether a match was queued by the ladder
+ALTER TABLE matches ADD COLUMN ladder BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Index for counting the ladder matches of the day
+CREATE INDEX idx_matches_ladder ON matches(ladder, created_at);
+
+-- State of the ladder scheduler, a single row
+CREATE TABLE ladder_scheduler (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    -- Paused by an admin, no ladder matches are queued
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    last_run_at TEXT
+);
+
+INSERT INTO ladder_scheduler (id) VALUES (1);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_agents_house_bot;
+ALTER TABLE agents DROP COLUMN difficulty;
+DELETE FROM users WHERE system;
+ALTER TABLE users DROP COLUMN system;
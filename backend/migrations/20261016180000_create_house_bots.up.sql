@@ -0,0 +1,11 @@
+-- System users own built-in content and can never log in
+ALTER TABLE users ADD COLUMN system BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Owner of the house bots. The empty password hash never verifies.
+INSERT INTO users (username, password_hash, system) VALUES ('House Bots', '', TRUE);
+
+-- Difficulty of a house bot, NULL for agents written by users
+ALTER TABLE agents ADD COLUMN difficulty TEXT;
+
+-- One house bot per difficulty and game
+CREATE UNIQUE INDEX idx_agents_house_bot ON agents(game_id, difficulty) WHERE difficulty IS NOT NULL;
@@ -0,0 +1,4 @@
+DELETE FROM jobs WHERE kind = 'evaluation_seed';
+DROP TABLE IF EXISTS evaluation_seeds;
+DROP INDEX IF EXISTS idx_evaluations_user;
+DROP TABLE IF EXISTS evaluations;
@@ -0,0 +1,51 @@
+-- A/B evaluations: two agent versions play the same seeds from both seats,
+-- one background job per seed, without creating matches
+CREATE TABLE evaluations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    -- User who asked for the evaluation
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Kept as NULL when an agent is deleted so the results stay intact
+    agent_a_id INTEGER REFERENCES agents(id) ON DELETE SET NULL,
+    agent_a_version INTEGER NOT NULL,
+    agent_b_id INTEGER REFERENCES agents(id) ON DELETE SET NULL,
+    agent_b_version INTEGER NOT NULL,
+    -- Seeds `seed` to `seed + seeds - 1` are each played twice
+    seed INTEGER NOT NULL,
+    seeds INTEGER NOT NULL,
+    -- 'queued' until its first seed starts, then 'running', and 'finished'
+    -- once every seed is played or 'failed' as soon as one of them fails
+    status TEXT NOT NULL DEFAULT 'queued',
+    error TEXT,
+    -- Results from agent A's point of view, set once finished
+    wins INTEGER,
+    draws INTEGER,
+    losses INTEGER,
+    mean_score_diff REAL,
+    ci_low REAL,
+    ci_high REAL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    finished_at TEXT
+);
+
+-- Index for listing a user's evaluations
+CREATE INDEX idx_evaluations_user ON evaluations(user_id, id);
+
+-- Seeds of an evaluation, each played by a job of its own so a long
+-- evaluation doesn't keep a worker busy for all of its matches
+CREATE TABLE evaluation_seeds (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    evaluation_id INTEGER NOT NULL REFERENCES evaluations(id) ON DELETE CASCADE,
+    seed INTEGER NOT NULL,
+    -- Follows its job: 'queued', 'running', 'finished' or 'failed'
+    status TEXT NOT NULL DEFAULT 'queued',
+    -- Results from agent A's point of view, first with A in seat 0 and then in
+    -- seat 1, set once finished. `a_won` is 1 if A won, 0 if B won and NULL
+    -- for a draw
+    first_a_won INTEGER,
+    first_score_diff REAL,
+    second_a_won INTEGER,
+    second_score_diff REAL,
+
+    UNIQUE (evaluation_id, seed)
+);
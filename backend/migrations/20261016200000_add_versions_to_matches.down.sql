@@ -0,0 +1,2 @@
+ALTER TABLE matches DROP COLUMN lua_version;
+ALTER TABLE matches DROP COLUMN game_version;
@@ -0,0 +1,4 @@
+-- Versions a match was played with, so it can be replayed exactly.
+-- NULL until the match is played, and for matches from before they were recorded.
+ALTER TABLE matches ADD COLUMN game_version TEXT;
+ALTER TABLE matches ADD COLUMN lua_version TEXT;
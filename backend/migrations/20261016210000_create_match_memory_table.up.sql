@@ -0,0 +1,11 @@
+-- What agents had in their `memory` table during a match, one row for
+-- every tick on which an agent's memory changed
+CREATE TABLE match_memory (
+    match_id INTEGER NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    seat INTEGER NOT NULL,
+    tick INTEGER NOT NULL,
+    -- The whole table, as JSON
+    memory TEXT NOT NULL,
+
+    PRIMARY KEY (match_id, seat, tick)
+);
@@ -0,0 +1,11 @@
+-- Lines agents printed during a match, tagged with the tick they were printed on
+CREATE TABLE match_logs (
+    match_id INTEGER NOT NULL REFERENCES matches(id) ON DELETE CASCADE,
+    -- Position of the line in the match's log, lines of a tick are in seat order
+    line INTEGER NOT NULL,
+    seat INTEGER NOT NULL,
+    tick INTEGER NOT NULL,
+    message TEXT NOT NULL,
+
+    PRIMARY KEY (match_id, line)
+);
@@ -0,0 +1,258 @@
+//! Built-in house bots, so there is always someone to play against.
+//!
+//! Every game ships with an easy, a medium and a hard bot, written in Lua in
+//! `ai/<game>/<difficulty>.lua` at the root of the repository. [`seed`] stores
+//! them as agents of the system user when the server starts and saves a new
+//! version whenever a bot's code has changed, so earlier matches keep the
+//! code they were played with. Nobody can log in as the system user, so the
+//! bots can't be edited through the API.
+
+use crate::models::Difficulty;
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, BotRepository, GameRepository};
+use sqlx::SqlitePool;
+use tracing::info;
+
+/// The code of one house bot.
+#[derive(Debug, Clone, Copy)]
+pub struct BotSource {
+    /// Name of the game, the same as `Game::name`
+    pub game: &'static str,
+    pub difficulty: Difficulty,
+    pub code: &'static str,
+}
+
+/// Every house bot, easiest first within each game.
+pub const BOTS: &[BotSource] = &[
+    BotSource {
+        game: "robotsumo",
+        difficulty: Difficulty::Easy,
+        code: include_str!("../../../ai/robotsumo/easy.lua"),
+    },
+    BotSource {
+        game: "robotsumo",
+        difficulty: Difficulty::Medium,
+        code: include_str!("../../../ai/robotsumo/medium.lua"),
+    },
+    BotSource {
+        game: "robotsumo",
+        difficulty: Difficulty::Hard,
+        code: include_str!("../../../ai/robotsumo/hard.lua"),
+    },
+    BotSource {
+        game: "snake",
+        difficulty: Difficulty::Easy,
+        code: include_str!("../../../ai/snake/easy.lua"),
+    },
+    BotSource {
+        game: "snake",
+        difficulty: Difficulty::Medium,
+        code: include_str!("../../../ai/snake/medium.lua"),
+    },
+    BotSource {
+        game: "snake",
+        difficulty: Difficulty::Hard,
+        code: include_str!("../../../ai/snake/hard.lua"),
+    },
+];
+
+/// Code of a game's house bot.
+pub fn code(game: &str, difficulty: Difficulty) -> Option<&'static str> {
+    BOTS.iter()
+        .find(|bot| bot.game == game && bot.difficulty == difficulty)
+        .map(|bot| bot.code)
+}
+
+/// Agent name of a house bot, e.g. "Hard Bot".
+pub fn name(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "Easy Bot",
+        Difficulty::Medium => "Medium Bot",
+        Difficulty::Hard => "Hard Bot",
+    }
+}
+
+/// Store every house bot in the database, or update the ones whose code has
+/// changed. Bots of games missing from the database are skipped.
+pub async fn seed(db: &SqlitePool) -> Result<()> {
+    let bots = BotRepository::new(db);
+    let agents = AgentRepository::new(db);
+    let games = GameRepository::new(db);
+    let owner_id = bots.owner_id().await?;
+
+    for bot in BOTS {
+        let Some(game) = games.find_by_name(bot.game).await? else {
+            continue;
+        };
+
+        match bots.find_by_difficulty(game.id, bot.difficulty).await? {
+            Some(existing) if existing.code == bot.code => {}
+            Some(existing) => {
+                agents
+                    .update(existing.agent_id, owner_id, None, Some(bot.code))
+                    .await?;
+                info!("Updated {} {} house bot", bot.game, bot.difficulty);
+            }
+            None => {
+                let agent = agents
+                    .create(owner_id, game.id, name(bot.difficulty), bot.code)
+                    .await?;
+                bots.set_difficulty(agent.id, bot.difficulty).await?;
+                info!("Added {} {} house bot", bot.game, bot.difficulty);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::GameRegistry;
+    use crate::runner::Entrant;
+    use crate::sandbox::Limits;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    /// Seeds every bot plays on, from both seats.
+    const SEEDS: &[u64] = &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 42, 1234, 99999];
+
+    fn entrant(game: &str, difficulty: Difficulty) -> Entrant {
+        Entrant {
+            id: None,
+            name: name(difficulty).to_string(),
+            code: code(game, difficulty).unwrap().to_string(),
+        }
+    }
+
+    /// Play `first` against `second` on every seed from both seats and
+    /// return how many matches `first` won.
+    fn wins(game: &str, first: Difficulty, second: Difficulty) -> usize {
+        let registry = GameRegistry::new();
+        let rules = registry.get(game).unwrap();
+        let (a, b) = (entrant(game, first), entrant(game, second));
+
+        let mut wins = 0;
+        for &seed in SEEDS {
+            let outcome = rules.run(seed, &[a.clone(), b.clone()], Limits::default(), None, None);
+            assert!(
+                outcome.errors.iter().all(Option::is_none),
+                "{:?}",
+                outcome.errors
+            );
+            wins += usize::from(outcome.winner == Some(0));
+
+            let outcome = rules.run(seed, &[b.clone(), a.clone()], Limits::default(), None, None);
+            assert!(
+                outcome.errors.iter().all(Option::is_none),
+                "{:?}",
+                outcome.errors
+            );
+            wins += usize::from(outcome.winner == Some(1));
+        }
+        wins
+    }
+
+    #[test]
+    fn every_game_has_every_difficulty() {
+        let registry = GameRegistry::new();
+        for game in registry.names() {
+            for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+                assert!(code(game, difficulty).is_some(), "{game} {difficulty}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_bot_is_valid_agent_code() {
+        for bot in BOTS {
+            crate::models::validate_agent_code(bot.code).unwrap();
+        }
+    }
+
+    #[test]
+    fn hard_snake_beats_easy_snake() {
+        let matches = 2 * SEEDS.len();
+        assert_eq!(wins("snake", Difficulty::Hard, Difficulty::Easy), matches);
+    }
+
+    #[test]
+    fn hard_robot_beats_easy_robot() {
+        let matches = 2 * SEEDS.len();
+        assert_eq!(
+            wins("robotsumo", Difficulty::Hard, Difficulty::Easy),
+            matches
+        );
+    }
+
+    #[tokio::test]
+    async fn seed_is_idempotent() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create test database");
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        seed(&pool).await.unwrap();
+        seed(&pool).await.unwrap();
+
+        let repo = BotRepository::new(&pool);
+        let game = GameRepository::new(&pool)
+            .find_by_name("snake")
+            .await
+            .unwrap()
+            .unwrap();
+        let bots = repo.find_by_game(game.id).await.unwrap();
+        let difficulties: Vec<Difficulty> = bots.iter().map(|bot| bot.difficulty).collect();
+        assert_eq!(
+            difficulties,
+            vec![Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+        );
+        assert!(bots.iter().all(|bot| bot.version == 1));
+    }
+
+    #[tokio::test]
+    async fn seed_saves_changed_code_as_new_version() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create test database");
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+        seed(&pool).await.unwrap();
+
+        let repo = BotRepository::new(&pool);
+        let game = GameRepository::new(&pool)
+            .find_by_name("snake")
+            .await
+            .unwrap()
+            .unwrap();
+        let bot = repo
+            .find_by_difficulty(game.id, Difficulty::Easy)
+            .await
+            .unwrap()
+            .unwrap();
+        let owner_id = repo.owner_id().await.unwrap();
+        AgentRepository::new(&pool)
+            .update(bot.agent_id, owner_id, None, Some("function think() end"))
+            .await
+            .unwrap();
+
+        seed(&pool).await.unwrap();
+
+        let bot = repo
+            .find_by_difficulty(game.id, Difficulty::Easy)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bot.version, 3);
+        assert_eq!(bot.code, code("snake", Difficulty::Easy).unwrap());
+    }
+}
@@ -0,0 +1,229 @@
+//! Line-based diffs between two versions of agent code.
+//!
+//! [`diff`] groups the changed lines into [`Hunk`]s with a few lines of
+//! unchanged context around them, like `diff -u`. The hunks are returned as
+//! is for the UI to show, and [`unified`] turns them into the familiar text
+//! format.
+//!
+//! Finding the changes takes time and memory proportional to the product of
+//! the line counts, so code with more than [`MAX_LINES`] lines isn't diffed.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Number of unchanged lines shown around each change.
+pub const CONTEXT_LINES: usize = 3;
+
+/// Most lines either version may have to be diffed.
+pub const MAX_LINES: usize = 2000;
+
+/// A line in a [`Hunk`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "text", rename_all = "snake_case")]
+pub enum Line {
+    /// In both versions.
+    Context(String),
+    /// Only in the old version.
+    Removed(String),
+    /// Only in the new version.
+    Added(String),
+}
+
+/// A group of changed lines and the context around them. Line numbers start
+/// at 1. A count of 0 means the hunk has no lines in that version, and the
+/// start is then the line after which lines were added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<Line>,
+}
+
+/// Find the changes between `old` and `new`, an empty list if they are the
+/// same. Differences in the final newline are ignored. `None` if either
+/// version has more than [`MAX_LINES`] lines.
+pub fn diff(old: &str, new: &str) -> Option<Vec<Hunk>> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    if old_lines.len() > MAX_LINES || new_lines.len() > MAX_LINES {
+        return None;
+    }
+    let lines: Vec<Line> = diff::slice(&old_lines, &new_lines)
+        .into_iter()
+        .map(|line| match line {
+            diff::Result::Both(text, _) => Line::Context(text.to_string()),
+            diff::Result::Left(text) => Line::Removed(text.to_string()),
+            diff::Result::Right(text) => Line::Added(text.to_string()),
+        })
+        .collect();
+
+    // Line numbers in each version before every line of the diff
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match line {
+            Line::Context(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Line::Removed(_) => old_line += 1,
+            Line::Added(_) => new_line += 1,
+        }
+    }
+
+    // Ranges of the diff to show, merged when their context overlaps
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if matches!(line, Line::Context(_)) {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let hunks = ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let lines = lines[start..end].to_vec();
+            let old_lines = lines
+                .iter()
+                .filter(|line| !matches!(line, Line::Added(_)))
+                .count();
+            let new_lines = lines
+                .iter()
+                .filter(|line| !matches!(line, Line::Removed(_)))
+                .count();
+            let (old_before, new_before) = positions[start];
+            Hunk {
+                old_start: old_before + usize::from(old_lines > 0),
+                old_lines,
+                new_start: new_before + usize::from(new_lines > 0),
+                new_lines,
+                lines,
+            }
+        })
+        .collect();
+    Some(hunks)
+}
+
+/// Format hunks as a unified diff, with `old_name` and `new_name` in the
+/// header. Returns an empty string when there are no hunks.
+pub fn unified(hunks: &[Hunk], old_name: &str, new_name: &str) -> String {
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut text = format!("--- {old_name}\n+++ {new_name}\n");
+    for hunk in hunks {
+        let _ = writeln!(
+            text,
+            "@@ -{},{} +{},{} @@",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        );
+        for line in &hunk.lines {
+            let (prefix, line) = match line {
+                Line::Context(line) => (' ', line),
+                Line::Removed(line) => ('-', line),
+                Line::Added(line) => ('+', line),
+            };
+            let _ = writeln!(text, "{prefix}{line}");
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines "1" to "n", one per line.
+    fn numbered(n: usize) -> String {
+        (1..=n).map(|i| format!("{i}\n")).collect()
+    }
+
+    #[test]
+    fn same_code_has_no_hunks() {
+        let code = numbered(5);
+        assert!(diff(&code, &code).unwrap().is_empty());
+        assert_eq!(unified(&[], "a", "b"), "");
+    }
+
+    #[test]
+    fn changed_line_has_context() {
+        let old = numbered(10);
+        let new = old.replace("5\n", "five\n");
+
+        let hunks = diff(&old, &new).unwrap();
+
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (2, 7));
+        assert_eq!((hunk.new_start, hunk.new_lines), (2, 7));
+        assert_eq!(hunk.lines[0], Line::Context("2".to_string()));
+        assert_eq!(hunk.lines[3], Line::Removed("5".to_string()));
+        assert_eq!(hunk.lines[4], Line::Added("five".to_string()));
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let old = numbered(20);
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replace("18\n", "eighteen\n");
+
+        let hunks = diff(&old, &new).unwrap();
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].old_start, 1);
+        assert_eq!(hunks[1].old_start, 15);
+    }
+
+    #[test]
+    fn nearby_changes_share_a_hunk() {
+        let old = numbered(20);
+        let new = old.replacen("5\n", "five\n", 1).replace("10\n", "ten\n");
+
+        assert_eq!(diff(&old, &new).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn added_to_empty_code() {
+        let hunks = diff("", "a\nb\n").unwrap();
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (0, 0));
+        assert_eq!((hunks[0].new_start, hunks[0].new_lines), (1, 2));
+    }
+
+    #[test]
+    fn too_many_lines_are_not_diffed() {
+        let small = numbered(3);
+        let large = numbered(MAX_LINES + 1);
+        assert!(diff(&small, &large).is_none());
+        assert!(diff(&large, &small).is_none());
+        assert!(diff(&small, &numbered(MAX_LINES)).is_some());
+    }
+
+    #[test]
+    fn unified_format() {
+        let hunks = diff("a\nb\nc\n", "a\nc\nd\n").unwrap();
+
+        assert_eq!(
+            unified(&hunks, "version 1", "version 2"),
+            "--- version 1\n+++ version 2\n@@ -1,3 +1,3 @@\n a\n-b\n c\n+d\n"
+        );
+    }
+
+    #[test]
+    fn line_serializes_with_kind() {
+        let json = serde_json::to_value(Line::Added("x".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "added", "text": "x" }));
+    }
+}
@@ -0,0 +1,217 @@
+//! A/B evaluations between two agent versions.
+//!
+//! A single match says little about which of two agents is better, luck with
+//! the seed or the seat can decide it. An evaluation plays agent A against
+//! agent B on a run of consecutive seeds, each seed twice with the seats
+//! swapped, and reports A's wins, draws and losses along with the mean score
+//! difference and its 95% confidence interval. If the interval is above 0,
+//! A is better than B and not just lucky.
+//!
+//! Each seed is a job of its own on the match queue, so a long evaluation
+//! shares the workers with other matches instead of holding one for all of
+//! its matches. The matches are played like any other but never show up in
+//! the match history. Once the last seed is played, the evaluation is summed
+//! up.
+
+use crate::models::{EvaluationError, EvaluationSummary, JobStatus, MatchOutcome};
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, EvaluationRepository, GameRepository};
+use crate::runner::Entrant;
+
+/// Number of seeds played unless asked for otherwise.
+pub const DEFAULT_SEEDS: i64 = 20;
+
+/// Fewest seeds an evaluation plays, a confidence interval needs two.
+pub const MIN_SEEDS: i64 = 2;
+
+/// Most seeds an evaluation plays.
+pub const MAX_SEEDS: i64 = 100;
+
+/// Most evaluations a user can have queued or running at the same time.
+pub const MAX_PENDING: i64 = 3;
+
+/// z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// One match from agent A's point of view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Played {
+    /// `Some(true)` if A won, `Some(false)` if B won, `None` for a draw
+    pub a_won: Option<bool>,
+    /// A's score minus B's score
+    pub score_diff: f64,
+}
+
+impl Played {
+    /// Read a two-player match in which A sat in seat `a_seat`.
+    pub fn new(outcome: &MatchOutcome, a_seat: usize) -> Self {
+        let b_seat = 1 - a_seat;
+        Self {
+            a_won: outcome.winner.map(|seat| seat == a_seat),
+            score_diff: f64::from(outcome.scores[a_seat]) - f64::from(outcome.scores[b_seat]),
+        }
+    }
+}
+
+/// Sum up the matches of an evaluation, two per seed. The confidence
+/// interval is taken over the seeds, each counting with the mean score
+/// difference of its two matches, so the advantage of a seat cancels out.
+pub fn summarize(pairs: &[[Played; 2]]) -> EvaluationSummary {
+    let played = || pairs.iter().flatten();
+    let wins = played().filter(|p| p.a_won == Some(true)).count() as i64;
+    let losses = played().filter(|p| p.a_won == Some(false)).count() as i64;
+    let draws = played().filter(|p| p.a_won.is_none()).count() as i64;
+
+    let diffs: Vec<f64> = pairs
+        .iter()
+        .map(|[first, second]| (first.score_diff + second.score_diff) / 2.0)
+        .collect();
+    let n = diffs.len() as f64;
+    let mean = if diffs.is_empty() {
+        0.0
+    } else {
+        diffs.iter().sum::<f64>() / n
+    };
+    let margin = if diffs.len() < 2 {
+        0.0
+    } else {
+        let variance = diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Z_95 * (variance / n).sqrt()
+    };
+
+    EvaluationSummary {
+        wins,
+        draws,
+        losses,
+        mean_score_diff: mean,
+        ci_low: mean - margin,
+        ci_high: mean + margin,
+    }
+}
+
+/// Play both matches of a queued evaluation seed, and sum up the evaluation
+/// once all of its seeds are played. A seed that was already stored, e.g.
+/// before a crash, is not played again.
+pub async fn play_seed(state: &AppState, seed_id: i64) -> Result<()> {
+    let repo = EvaluationRepository::new(&state.db);
+    let queued = repo.find_seed(seed_id).await?.ok_or(Error::NotFound)?;
+    let evaluation = repo
+        .find_by_id(queued.evaluation_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    // Another seed failed, so there are no results to play for
+    if evaluation.status == JobStatus::Failed {
+        return Ok(());
+    }
+
+    if queued.status != JobStatus::Finished {
+        repo.start(evaluation.id).await?;
+        repo.set_seed_status(seed_id, JobStatus::Running, None)
+            .await?;
+
+        let game = GameRepository::new(&state.db)
+            .find_by_id(evaluation.game_id)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let a = entrant(state, evaluation.agent_a_id, evaluation.agent_a_version).await?;
+        let b = entrant(state, evaluation.agent_b_id, evaluation.agent_b_version).await?;
+
+        let limits = state.config.sandbox_limits(&game);
+        let games = state.games.clone();
+        let seed = queued.seed as u64;
+        let pair = tokio::task::spawn_blocking(move || {
+            let rules = games.get(&game.name).ok_or(Error::NotFound)?;
+            let first = rules.run(seed, &[a.clone(), b.clone()], limits, None, None);
+            let second = rules.run(seed, &[b, a], limits, None, None);
+            Ok::<_, Error>([Played::new(&first, 0), Played::new(&second, 1)])
+        })
+        .await??;
+        repo.finish_seed(seed_id, pair).await?;
+    }
+
+    let pairs = repo.find_played(evaluation.id).await?;
+    if pairs.len() as i64 == evaluation.seeds {
+        repo.finish(evaluation.id, &summarize(&pairs)).await?;
+    }
+
+    Ok(())
+}
+
+/// The code of an agent version in an evaluation.
+async fn entrant(state: &AppState, agent_id: Option<i64>, version: i64) -> Result<Entrant> {
+    let agent_repo = AgentRepository::new(&state.db);
+    let agent = match agent_id {
+        Some(agent_id) => agent_repo.find_by_id_any_owner(agent_id).await?,
+        None => None,
+    };
+    let agent = agent.ok_or(EvaluationError::MissingAgent)?;
+    let code = agent_repo
+        .find_version(agent.id, version)
+        .await?
+        .ok_or(EvaluationError::MissingAgent)?
+        .code;
+
+    Ok(Entrant {
+        id: Some(agent.id),
+        name: agent.name,
+        code,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(a_won: Option<bool>, score_diff: f64) -> Played {
+        Played { a_won, score_diff }
+    }
+
+    #[test]
+    fn summarize_counts_results_from_a() {
+        let summary = summarize(&[
+            [played(Some(true), 1.0), played(None, 0.0)],
+            [played(Some(false), -1.0), played(Some(true), 1.0)],
+        ]);
+
+        assert_eq!(summary.wins, 2);
+        assert_eq!(summary.draws, 1);
+        assert_eq!(summary.losses, 1);
+        assert_eq!(summary.mean_score_diff, 0.25);
+    }
+
+    #[test]
+    fn summarize_gives_interval_around_mean() {
+        let summary = summarize(&[
+            [played(Some(true), 4.0), played(Some(true), 2.0)],
+            [played(Some(true), 1.0), played(Some(true), 1.0)],
+            [played(Some(true), 2.0), played(Some(true), 2.0)],
+        ]);
+
+        // Seed means are 3, 1 and 2: sample standard deviation 1
+        assert_eq!(summary.mean_score_diff, 2.0);
+        let margin = 1.96 / 3f64.sqrt();
+        assert!((summary.ci_low - (2.0 - margin)).abs() < 1e-9);
+        assert!((summary.ci_high - (2.0 + margin)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn summarize_identical_results_has_no_spread() {
+        let pair = [played(None, 0.0), played(None, 0.0)];
+        let summary = summarize(&[pair, pair, pair]);
+
+        assert_eq!(summary.draws, 6);
+        assert_eq!(summary.ci_low, 0.0);
+        assert_eq!(summary.ci_high, 0.0);
+    }
+
+    #[test]
+    fn seat_advantage_cancels_out() {
+        // The agent in seat 0 always wins by 1, whoever it is
+        let pair = [played(Some(true), 1.0), played(Some(false), -1.0)];
+        let summary = summarize(&[pair, pair]);
+
+        assert_eq!(summary.wins, 2);
+        assert_eq!(summary.losses, 2);
+        assert_eq!(summary.mean_score_diff, 0.0);
+    }
+}
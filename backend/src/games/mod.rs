@@ -0,0 +1,331 @@
+//! Rules of every game the backend can run, keyed by `Game::name`.
+//!
+//! A game is a core crate implementing [`GameRules`] plus a Lua file with the
+//! functions agents call. Those functions read the `observation` global and
+//! write the `action` global, and [`decide`] converts both to and from the
+//! game's own types. Adding a game only means registering it in
+//! [`GameRegistry::new`]. Its reasons for ending a game are its own too, and
+//! matches store them as they are.
+//!
+//! Matches are always played with the game's default `Settings`. Nothing in
+//! the backend stores or accepts other settings, so they only change what the
+//! WASM games and the cores' own tests play.
+
+use crate::live::LiveMatch;
+use crate::models::{AgentError, MatchOutcome};
+use crate::runner::{self, Entrant};
+use crate::sandbox::{Limits, Sandbox};
+use game_core::GameRules;
+use game_core::replay::{self, Replay};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+type Result<T> = std::result::Result<T, AgentError>;
+
+/// Function every agent defines. It is called once per tick.
+pub const THINK_FUNCTION: &str = "think";
+
+/// A registered game with its types erased, so all games can be stored together.
+pub trait Rules: Send + Sync {
+    /// Name of the game, the same as `Game::name`.
+    fn name(&self) -> &'static str;
+
+    /// Fewest agents a match can have.
+    fn min_players(&self) -> usize;
+
+    /// Most agents a match can have.
+    fn max_players(&self) -> usize;
+
+    /// Lua source defining the game's functions for agents.
+    fn lua_api(&self) -> &'static str;
+
+    /// Play a match with default settings between `entrants`, one per seat,
+    /// stopping after `max_ticks` if given and streaming it to `live` if
+    /// given. Blocks until the match is over.
+    fn run(
+        &self,
+        seed: u64,
+        entrants: &[Entrant],
+        limits: Limits,
+        max_ticks: Option<u32>,
+        live: Option<&LiveMatch>,
+    ) -> MatchOutcome;
+
+    /// Agents recorded in an encoded replay of this game, `None` if it
+    /// can't be decoded.
+    fn replay_agents(&self, replay: &[u8]) -> Option<Vec<replay::Agent>>;
+}
+
+/// [`Rules`] for a game core type.
+struct Registered<G> {
+    lua_api: &'static str,
+    game: PhantomData<fn() -> G>,
+}
+
+impl<G: GameRules> Rules for Registered<G> {
+    fn name(&self) -> &'static str {
+        G::NAME
+    }
+
+    fn min_players(&self) -> usize {
+        G::MIN_PLAYERS
+    }
+
+    fn max_players(&self) -> usize {
+        G::MAX_PLAYERS
+    }
+
+    fn lua_api(&self) -> &'static str {
+        self.lua_api
+    }
+
+    fn run(
+        &self,
+        seed: u64,
+        entrants: &[Entrant],
+        limits: Limits,
+        max_ticks: Option<u32>,
+        live: Option<&LiveMatch>,
+    ) -> MatchOutcome {
+        runner::run::<G>(self.lua_api, seed, entrants, limits, max_ticks, live)
+    }
+
+    fn replay_agents(&self, replay: &[u8]) -> Option<Vec<replay::Agent>> {
+        Replay::<G>::decode(replay)
+            .ok()
+            .map(|replay| replay.header.agents)
+    }
+}
+
+/// All games the backend knows how to run.
+pub struct GameRegistry {
+    games: BTreeMap<&'static str, Box<dyn Rules>>,
+}
+
+impl GameRegistry {
+    /// Create a registry with every built-in game.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            games: BTreeMap::new(),
+        };
+        registry.register::<robotsumo_core::Game>(include_str!("robotsumo.lua"));
+        registry.register::<snake_core::Game>(include_str!("snake.lua"));
+        registry
+    }
+
+    /// Add a game, replacing any game with the same name.
+    pub fn register<G: GameRules + 'static>(&mut self, lua_api: &'static str) {
+        let rules = Registered::<G> {
+            lua_api,
+            game: PhantomData,
+        };
+        self.games.insert(G::NAME, Box::new(rules));
+    }
+
+    /// Look up a game by its name.
+    pub fn get(&self, name: &str) -> Option<&dyn Rules> {
+        self.games.get(name).map(|rules| rules.as_ref())
+    }
+
+    /// Names of all registered games, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.games.keys().copied()
+    }
+}
+
+impl Default for GameRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for GameRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Load a game's API and then the agent's code into a fresh sandbox.
+pub fn load_agent(sandbox: &Sandbox, lua_api: &str, code: &str) -> Result<()> {
+    sandbox.load(lua_api)?;
+    sandbox.load(code)
+}
+
+/// Ask an agent for its action on this tick. The observation is read-only,
+/// and the action starts out as the game's default, so an agent that calls
+/// none of the API functions does nothing.
+pub fn decide<G: GameRules>(sandbox: &Sandbox, observation: &G::Observation) -> Result<G::Action> {
+    sandbox.set_readonly_global("observation", observation)?;
+    sandbox.set_global("action", &G::Action::default())?;
+    sandbox.call::<()>(THINK_FUNCTION, ())?;
+    sandbox.global("action").map_err(|error| match error {
+        AgentError::Runtime(message) => AgentError::InvalidAction(message),
+        error => error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::GameRepository;
+    use crate::sandbox::Limits;
+    use game_core::GameRules;
+    use robotsumo_core::{Controls, Steering, Throttle};
+    use snake_core::Turn;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    fn agent(game: &str, code: &str) -> Sandbox {
+        let registry = GameRegistry::new();
+        let sandbox = Sandbox::new(Limits::default()).unwrap();
+        load_agent(&sandbox, registry.get(game).unwrap().lua_api(), code).unwrap();
+        sandbox
+    }
+
+    #[tokio::test]
+    async fn every_game_in_the_database_is_registered() {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to create test database");
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        let registry = GameRegistry::new();
+        let games = GameRepository::new(&pool).find_all().await.unwrap();
+        for game in games {
+            let rules = registry.get(&game.name).expect("game is not registered");
+            assert_eq!(rules.name(), game.name);
+        }
+    }
+
+    #[test]
+    fn unknown_game_is_not_found() {
+        assert!(GameRegistry::new().get("chess").is_none());
+    }
+
+    #[test]
+    fn snake_agent_turns() {
+        let game = snake_core::Game::new_game(0, Default::default(), 1);
+        let observation = game.observe(0);
+        let sandbox = agent(
+            "snake",
+            r#"
+            function think()
+                local x, y = get_head_position()
+                if get_direction() == "up" and get_length() == 3 and x == 10 then
+                    turn_left()
+                end
+            end
+            "#,
+        );
+
+        let turn = decide::<snake_core::Game>(&sandbox, &observation).unwrap();
+        assert_eq!(turn, Turn::Left);
+    }
+
+    #[test]
+    fn snake_agent_sees_food() {
+        let game = snake_core::Game::new_game(0, Default::default(), 1);
+        let food = game.food.unwrap();
+        let sandbox = agent("snake", "function think() fx, fy = get_food_position() end");
+
+        decide::<snake_core::Game>(&sandbox, &game.observe(0)).unwrap();
+        assert_eq!(sandbox.global::<i32>("fx").unwrap(), food.x);
+        assert_eq!(sandbox.global::<i32>("fy").unwrap(), food.y);
+    }
+
+    #[test]
+    fn robotsumo_agent_drives() {
+        let game = robotsumo_core::Game::new_game(0, Default::default(), 2);
+        let sandbox = agent(
+            "robotsumo",
+            r#"
+            function think()
+                local x, y = get_position()
+                local ox, oy = get_opponent_position()
+                if x < ox and get_distance_to_opponent() == 10 and get_distance_to_edge() == 5 then
+                    move_forward()
+                    turn_right()
+                end
+            end
+            "#,
+        );
+
+        let controls = decide::<robotsumo_core::Game>(&sandbox, &game.observe(0)).unwrap();
+        assert_eq!(
+            controls,
+            Controls {
+                throttle: Throttle::Forward,
+                steering: Steering::Right,
+            }
+        );
+    }
+
+    #[test]
+    fn action_is_reset_every_tick() {
+        let game = robotsumo_core::Game::new_game(0, Default::default(), 2);
+        let sandbox = agent(
+            "robotsumo",
+            r#"
+            calls = 0
+            function think()
+                calls = calls + 1
+                if calls == 1 then move_backward() end
+            end
+            "#,
+        );
+
+        let first = decide::<robotsumo_core::Game>(&sandbox, &game.observe(0)).unwrap();
+        let second = decide::<robotsumo_core::Game>(&sandbox, &game.observe(0)).unwrap();
+        assert_eq!(first.throttle, Throttle::Backward);
+        assert_eq!(second, Controls::default());
+    }
+
+    #[test]
+    fn agent_without_think_fails() {
+        let game = snake_core::Game::new_game(0, Default::default(), 1);
+        let sandbox = agent("snake", "local x = 1");
+
+        assert!(matches!(
+            decide::<snake_core::Game>(&sandbox, &game.observe(0)),
+            Err(AgentError::MissingFunction(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_action_fails() {
+        let game = snake_core::Game::new_game(0, Default::default(), 1);
+        let sandbox = agent("snake", "function think() action = 'jump' end");
+
+        assert!(matches!(
+            decide::<snake_core::Game>(&sandbox, &game.observe(0)),
+            Err(AgentError::InvalidAction(_))
+        ));
+    }
+
+    #[test]
+    fn replay_agents_reads_recorded_names() {
+        let registry = GameRegistry::new();
+        let rules = registry.get("snake").unwrap();
+        let entrants = ["Ada", "Linus"].map(|name| Entrant {
+            id: None,
+            name: name.to_string(),
+            code: "function think() end".to_string(),
+        });
+        let outcome = rules.run(1, &entrants, Limits::default(), Some(3), None);
+
+        let agents = rules.replay_agents(&outcome.replay).unwrap();
+        let names: Vec<_> = agents.into_iter().map(|agent| agent.name).collect();
+        assert_eq!(names, ["Ada", "Linus"]);
+        assert!(rules.replay_agents(b"not a replay").is_none());
+        assert!(
+            registry
+                .get("robotsumo")
+                .unwrap()
+                .replay_agents(&outcome.replay)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,36 @@
+-- Robot Sumo API for agents, documented in frontend/src/components/GameDocs.tsx.
+-- `observation` is set before every call to `think()` and `action` is read
+-- back afterwards. Agents keep their own state in the `memory` table, which
+-- the sandbox provides and keeps between ticks.
+
+function move_forward()
+    action.throttle = "forward"
+end
+
+function move_backward()
+    action.throttle = "backward"
+end
+
+function turn_left()
+    action.steering = "left"
+end
+
+function turn_right()
+    action.steering = "right"
+end
+
+function get_position()
+    return observation.position.x, observation.position.y
+end
+
+function get_opponent_position()
+    return observation.opponent_position.x, observation.opponent_position.y
+end
+
+function get_distance_to_opponent()
+    return observation.distance_to_opponent
+end
+
+function get_distance_to_edge()
+    return observation.distance_to_edge
+end
@@ -0,0 +1,30 @@
+-- Snake API for agents, documented in frontend/src/components/GameDocs.tsx.
+-- `observation` is set before every call to `think()` and `action` is read
+-- back afterwards. Agents keep their own state in the `memory` table, which
+-- the sandbox provides and keeps between ticks.
+
+function turn_left()
+    action = "left"
+end
+
+function turn_right()
+    action = "right"
+end
+
+function get_head_position()
+    return observation.head.x, observation.head.y
+end
+
+function get_food_position()
+    if observation.food then
+        return observation.food.x, observation.food.y
+    end
+end
+
+function get_direction()
+    return observation.direction
+end
+
+function get_length()
+    return observation.length
+end
@@ -0,0 +1,153 @@
+//! Continuous ladder matchmaking.
+//!
+//! Users put agents on their game's ladder, and every
+//! [`Config::ladder_interval_secs`] the scheduler pairs them with opponents
+//! of similar rating and queues rated matches between their current
+//! versions. An agent plays one ladder match at a time, and at most
+//! [`Config::ladder_daily_match_cap`] a day, so the ladder can't crowd out
+//! matches users start themselves. Admins can pause the scheduler.
+
+use crate::prelude::*;
+use crate::rating::DEFAULT_RATING;
+use crate::repositories::{LadderCandidate, LadderRepository};
+use std::hash::{BuildHasher, RandomState};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Default number of seconds between ladder pairings.
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+/// Default largest rating difference between ladder opponents.
+pub const DEFAULT_RATING_WINDOW: f64 = 200.0;
+
+/// Default number of ladder matches an agent plays per day.
+pub const DEFAULT_DAILY_MATCH_CAP: i64 = 20;
+
+/// Start the scheduler, unless the configured interval is 0.
+pub fn start(state: &AppState) {
+    let secs = state.config.ladder_interval_secs;
+    if secs == 0 {
+        info!("Ladder scheduler disabled");
+        return;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = run(&state).await {
+                error!("Ladder run failed: {}", e);
+            }
+        }
+    });
+    info!("Started ladder scheduler, pairing every {}s", secs);
+}
+
+/// Pair the agents on the ladder that can play and queue their matches.
+/// Returns the number of matches queued, 0 while the scheduler is paused.
+pub async fn run(state: &AppState) -> Result<usize> {
+    let repo = LadderRepository::new(&state.db);
+    if repo.find_status(&state.config).await?.paused {
+        return Ok(0);
+    }
+
+    let candidates = repo
+        .find_candidates(state.config.ladder_daily_match_cap)
+        .await?;
+
+    let hasher = RandomState::new();
+    let mut queued = 0;
+    for game in candidates.chunk_by(|a, b| a.game_id == b.game_id) {
+        let head_to_head = state
+            .games
+            .get(&game[0].game_name)
+            .is_some_and(|rules| (rules.min_players()..=rules.max_players()).contains(&2));
+        if !head_to_head {
+            continue;
+        }
+
+        let ratings: Vec<f64> = game.iter().map(rating).collect();
+        let owners: Vec<i64> = game.iter().map(|candidate| candidate.user_id).collect();
+        for (a, b) in pair(&ratings, &owners, state.config.ladder_rating_window) {
+            let (a, b) = (&game[a], &game[b]);
+            // Only the lower 63 bits, so the seed fits in an SQLite integer
+            let seed = (hasher.hash_one((a.agent_id, b.agent_id)) >> 1) as i64;
+            // Neither agent always gets the first seat
+            let (first, second) = if seed & 1 == 0 { (a, b) } else { (b, a) };
+            repo.queue_match(first, second, seed).await?;
+            queued += 1;
+        }
+    }
+
+    repo.finish_run().await?;
+    if queued > 0 {
+        state.queue.notify();
+        info!("Queued {} ladder matches", queued);
+    }
+    Ok(queued)
+}
+
+/// Rating of a candidate, the default before its first rated match.
+fn rating(candidate: &LadderCandidate) -> f64 {
+    candidate.rating.unwrap_or(DEFAULT_RATING)
+}
+
+/// Pair players by rating, from the lowest up, each with the next one if
+/// they are at most `window` apart and have different owners, so nobody can
+/// feed rating to their own agents. Returns pairs of indices into `ratings`
+/// and `owners`, and leaves out players without an opponent.
+pub fn pair(ratings: &[f64], owners: &[i64], window: f64) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..ratings.len()).collect();
+    order.sort_by(|&a, &b| ratings[a].total_cmp(&ratings[b]));
+
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i + 1 < order.len() {
+        let (a, b) = (order[i], order[i + 1]);
+        if ratings[b] - ratings[a] <= window && owners[a] != owners[b] {
+            pairs.push((a, b));
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_neighbours_by_rating() {
+        let pairs = pair(&[1500.0, 1800.0, 1550.0, 1750.0], &[1, 2, 3, 4], 100.0);
+        assert_eq!(pairs, vec![(0, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn test_pair_respects_window() {
+        assert!(pair(&[1200.0, 1500.0, 1800.0], &[1, 2, 3], 200.0).is_empty());
+        // Paired from the lowest rating up, so the highest waits
+        assert_eq!(
+            pair(&[1500.0, 1650.0, 1600.0], &[1, 2, 3], 200.0),
+            vec![(0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_pair_odd_player_out() {
+        assert_eq!(pair(&[1500.0], &[1], 200.0), vec![]);
+        assert_eq!(pair(&[1500.0, 1500.0, 1500.0], &[1, 2, 3], 0.0).len(), 1);
+    }
+
+    #[test]
+    fn test_pair_skips_agents_of_the_same_owner() {
+        assert!(pair(&[1500.0, 1510.0], &[1, 1], 200.0).is_empty());
+        // The lower agent waits, and its owner's other agent plays on
+        assert_eq!(
+            pair(&[1500.0, 1510.0, 1520.0], &[1, 1, 2], 200.0),
+            vec![(1, 2)]
+        );
+    }
+}
@@ -3,9 +3,22 @@
 //! This module exposes the backend components for use in integration tests
 //! and as a library.
 
+pub mod bots;
+pub mod diff;
+pub mod evaluation;
+pub mod games;
+pub mod ladder;
+pub mod live;
 pub mod models;
+pub mod playground;
 pub mod prelude;
+pub mod queue;
+pub mod rating;
 pub mod repositories;
 pub mod routes;
+pub mod runner;
+pub mod sandbox;
+pub mod tournament;
+pub mod verify;
 
 pub use routes::routes;
@@ -0,0 +1,288 @@
+//! Live streams of matches while they are played.
+//!
+//! The worker playing a match sends every [`Frame`] to a [`LiveMatch`], which
+//! passes it on to all spectators through a bounded broadcast channel. The
+//! worker never waits for spectators: one that falls more than
+//! [`SPECTATOR_BUFFER`] frames behind is caught up again from the latest
+//! snapshot, the same way as a spectator that joins late. The channel closes
+//! once the result of the match is stored, so spectators know when to stop.
+
+use axum::body::Bytes;
+use game_core::{Frame, GameRules};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Number of frames a spectator can fall behind before skipping ahead.
+pub const SPECTATOR_BUFFER: usize = 256;
+
+/// Matches currently being played, by match ID.
+#[derive(Debug, Default)]
+pub struct LiveMatches {
+    matches: Mutex<HashMap<i64, Arc<LiveMatch>>>,
+}
+
+impl LiveMatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start streaming a match, replacing any earlier stream of it.
+    pub fn start(&self, match_id: i64) -> Arc<LiveMatch> {
+        let live = Arc::new(LiveMatch::new());
+        self.matches.lock().unwrap().insert(match_id, live.clone());
+        live
+    }
+
+    pub fn get(&self, match_id: i64) -> Option<Arc<LiveMatch>> {
+        self.matches.lock().unwrap().get(&match_id).cloned()
+    }
+
+    /// Stop streaming a match, even if it never sent [`Frame::End`].
+    /// Spectators still get the frames already sent.
+    pub fn finish(&self, match_id: i64) {
+        if let Some(live) = self.matches.lock().unwrap().remove(&match_id) {
+            live.close();
+        }
+    }
+}
+
+/// The stream of one match.
+#[derive(Debug)]
+pub struct LiveMatch {
+    catch_up: Mutex<CatchUp>,
+}
+
+/// The frames a new spectator needs to show the current tick.
+#[derive(Debug, Default)]
+struct CatchUp {
+    /// `None` once the stream is closed.
+    sender: Option<broadcast::Sender<Bytes>>,
+    header: Option<Bytes>,
+    snapshot: Option<Bytes>,
+    /// Ticks since the snapshot
+    ticks: Vec<Bytes>,
+    end: Option<Bytes>,
+}
+
+impl Default for LiveMatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveMatch {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(SPECTATOR_BUFFER);
+        Self {
+            catch_up: Mutex::new(CatchUp {
+                sender: Some(sender),
+                ..CatchUp::default()
+            }),
+        }
+    }
+
+    /// Send a frame to every spectator, without waiting for any of them.
+    pub fn send<G: GameRules>(&self, frame: &Frame<G>) {
+        let bytes = Bytes::from(frame.encode());
+
+        // Spectators subscribe while holding the lock, so they never miss or
+        // repeat a frame between catching up and receiving
+        let mut catch_up = self.catch_up.lock().unwrap();
+        match frame {
+            Frame::Header(_) => catch_up.header = Some(bytes.clone()),
+            Frame::Snapshot(_) => {
+                catch_up.snapshot = Some(bytes.clone());
+                catch_up.ticks.clear();
+            }
+            Frame::Tick { .. } => catch_up.ticks.push(bytes.clone()),
+            Frame::End => catch_up.end = Some(bytes.clone()),
+        }
+
+        if let Some(sender) = &catch_up.sender {
+            // Only fails when nobody is watching
+            let _ = sender.send(bytes);
+        }
+    }
+
+    /// Close the stream. Nothing sent after this reaches spectators.
+    pub fn close(&self) {
+        self.catch_up.lock().unwrap().sender = None;
+    }
+
+    /// Start watching. Returns the frames to show the current tick, starting
+    /// with the header, and a receiver for every frame after those.
+    pub fn subscribe(&self) -> (Vec<Bytes>, broadcast::Receiver<Bytes>) {
+        let catch_up = self.catch_up.lock().unwrap();
+        let frames = catch_up.header.iter().chain(catch_up.frames()).cloned();
+        (frames.collect(), catch_up.receiver())
+    }
+
+    /// Like [`LiveMatch::subscribe`] without the header, for spectators that
+    /// fell behind.
+    pub fn resubscribe(&self) -> (Vec<Bytes>, broadcast::Receiver<Bytes>) {
+        let catch_up = self.catch_up.lock().unwrap();
+        (catch_up.frames().cloned().collect(), catch_up.receiver())
+    }
+}
+
+impl CatchUp {
+    /// A receiver for the next frame on, already closed if the stream is.
+    fn receiver(&self) -> broadcast::Receiver<Bytes> {
+        match &self.sender {
+            Some(sender) => sender.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    /// The latest snapshot, the ticks since then and the end.
+    fn frames(&self) -> impl Iterator<Item = &Bytes> {
+        self.snapshot
+            .iter()
+            .chain(&self.ticks)
+            .chain(self.end.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_core::replay::Snapshot;
+    use game_core::{LiveView, Recorder};
+    use robotsumo_core::{Controls, Game, Settings};
+
+    /// Stream a game of `ticks` ticks with a snapshot every 4 ticks.
+    fn stream(live: &LiveMatch, ticks: u32) {
+        let mut game = Game::new(1, Settings::default());
+        let recorder =
+            Recorder::<Game>::with_snapshot_interval(&game, 1, Settings::default(), Vec::new(), 4);
+        live.send(&Frame::<Game>::Header(recorder.replay().header.clone()));
+        live.send(&Frame::Snapshot(recorder.replay().snapshots[0].clone()));
+        for _ in 0..ticks {
+            let tick = game.tick;
+            game.step([Controls::default(); 2]);
+            live.send(&Frame::<Game>::Tick {
+                tick,
+                actions: vec![Controls::default(); 2],
+            });
+            if game.tick.is_multiple_of(4) {
+                live.send(&Frame::Snapshot(Snapshot {
+                    tick: game.tick,
+                    state: game.clone(),
+                }));
+            }
+        }
+    }
+
+    fn decode(frames: &[Bytes]) -> Vec<Frame<Game>> {
+        frames
+            .iter()
+            .map(|bytes| Frame::decode(bytes).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn late_spectator_starts_from_latest_snapshot() {
+        let live = LiveMatch::new();
+        stream(&live, 6);
+
+        let (frames, _) = live.subscribe();
+        let frames = decode(&frames);
+
+        assert_eq!(frames.len(), 4);
+        assert!(matches!(frames[0], Frame::Header(_)));
+        assert!(matches!(&frames[1], Frame::Snapshot(s) if s.tick == 4));
+        assert!(matches!(frames[2], Frame::Tick { tick: 4, .. }));
+        assert!(matches!(frames[3], Frame::Tick { tick: 5, .. }));
+    }
+
+    #[test]
+    fn spectator_receives_frames_after_catch_up() {
+        let live = LiveMatch::new();
+        stream(&live, 2);
+        let (frames, mut receiver) = live.subscribe();
+        assert_eq!(frames.len(), 4);
+
+        live.send(&Frame::<Game>::End);
+
+        let frame = receiver.try_recv().unwrap();
+        assert!(matches!(Frame::<Game>::decode(&frame), Ok(Frame::End)));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn slow_spectator_lags_and_resubscribes() {
+        let live = LiveMatch::new();
+        let (_, mut receiver) = live.subscribe();
+        stream(&live, SPECTATOR_BUFFER as u32);
+
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(_))
+        ));
+
+        let (frames, _) = live.resubscribe();
+        let frames = decode(&frames);
+        assert!(matches!(frames[0], Frame::Snapshot(_)));
+        assert!(frames.len() <= 4);
+    }
+
+    #[test]
+    fn closed_stream_includes_end() {
+        let live = LiveMatch::new();
+        stream(&live, 1);
+        let (_, mut watching) = live.subscribe();
+        live.send(&Frame::<Game>::End);
+        live.close();
+
+        let (frames, mut late) = live.subscribe();
+        let frames = decode(&frames);
+        assert!(matches!(frames.last(), Some(Frame::End)));
+        assert!(matches!(
+            late.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+
+        // Spectators already watching get the end before the stream closes
+        let frame = watching.try_recv().unwrap();
+        assert!(matches!(Frame::<Game>::decode(&frame), Ok(Frame::End)));
+        assert!(matches!(
+            watching.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn registry_tracks_running_matches() {
+        let matches = LiveMatches::new();
+        assert!(matches.get(1).is_none());
+
+        let live = matches.start(1);
+        assert!(Arc::ptr_eq(&live, &matches.get(1).unwrap()));
+        let (_, mut receiver) = live.subscribe();
+
+        matches.finish(1);
+        assert!(matches.get(1).is_none());
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn frames_rebuild_the_game() {
+        let live = LiveMatch::new();
+        stream(&live, 10);
+        let (frames, _) = live.subscribe();
+
+        let mut view = LiveView::<Game>::new();
+        for frame in decode(&frames) {
+            view.receive(frame);
+        }
+        let mut expected = Game::new(1, Settings::default());
+        for _ in 0..10 {
+            expected.step([Controls::default(); 2]);
+        }
+        assert_eq!(view.game(), Some(&expected));
+    }
+}
@@ -1,9 +1,6 @@
-mod models;
-mod prelude;
-mod repositories;
-mod routes;
-
-use prelude::*;
+use backend::prelude::*;
+use backend::repositories::RatingRepository;
+use backend::{bots, ladder, queue, routes};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::net::Ipv4Addr;
 use std::str::FromStr;
@@ -11,8 +8,6 @@ use tower_http::trace::TraceLayer;
 use tracing::{debug, error, info};
 use tracing_subscriber::{EnvFilter, fmt};
 
-use crate::routes::routes;
-
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
@@ -41,7 +36,18 @@ async fn app() -> Result<()> {
     sqlx::migrate!().run(&db).await?;
     info!("Migrations completed successfully");
 
+    bots::seed(&db).await?;
+
+    // `backend recompute-ratings` rebuilds all ratings from the match history
+    if std::env::args().nth(1).as_deref() == Some("recompute-ratings") {
+        let matches = RatingRepository::new(&db).recompute().await?;
+        info!("Recomputed ratings from {} matches", matches);
+        return Ok(());
+    }
+
     let state = AppState::new(config.clone(), db);
+    queue::start(&state).await?;
+    ladder::start(&state);
 
     let app = routes().with_state(state).layer(TraceLayer::new_for_http());
 
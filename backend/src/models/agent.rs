@@ -1,4 +1,5 @@
-use mlua::Lua;
+use crate::diff::Hunk;
+use crate::sandbox;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use thiserror::Error;
@@ -17,12 +18,76 @@ pub enum AgentError {
     #[error("Agent code is required.")]
     CodeEmpty,
 
+    #[error("Agent code can be at most {} bytes.", MAX_CODE_BYTES)]
+    CodeTooLarge,
+
+    #[error("Drafts can be at most {} bytes.", MAX_DRAFT_BYTES)]
+    DraftTooLarge,
+
+    #[error(
+        "Versions with more than {} lines can't be compared.",
+        crate::diff::MAX_LINES
+    )]
+    DiffTooLarge,
+
+    #[error("This agent has no draft.")]
+    NoDraft,
+
+    #[error("This agent plays in a tournament that hasn't finished.")]
+    InTournament,
+
     #[error("Invalid Lua syntax: {0}")]
     InvalidLuaSyntax(String),
+
+    #[error("Lua error: {0}")]
+    Runtime(String),
+
+    #[error("Agent must define a function called '{0}'.")]
+    MissingFunction(String),
+
+    #[error("Agent chose an invalid action: {0}")]
+    InvalidAction(String),
+
+    #[error("Agent was stopped on tick {tick} because it {budget}.")]
+    BudgetExceeded { tick: u64, budget: Budget },
+
+    #[error("Agent ran out of memory on tick {tick} (the limit is {limit} bytes).")]
+    OutOfMemory { tick: u64, limit: usize },
+
+    #[error("Agent remembered more than {limit} bytes on tick {tick}.")]
+    MemoryTooLarge { tick: u64, limit: usize },
+
+    #[error("Agent memory can't be saved: {0}")]
+    InvalidMemory(String),
+}
+
+/// A per-call resource budget an agent can run out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Budget {
+    /// Maximum number of Lua instructions.
+    Instructions(u64),
+    /// Maximum wall-clock time in milliseconds.
+    Time(u64),
+}
+
+impl std::fmt::Display for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Budget::Instructions(limit) => write!(f, "used more than {} instructions", limit),
+            Budget::Time(limit_ms) => write!(f, "took longer than {} ms", limit_ms),
+        }
+    }
 }
 
 type Result<T> = std::result::Result<T, AgentError>;
 
+/// Largest agent code that can be saved, in bytes.
+pub const MAX_CODE_BYTES: usize = 64 * 1024;
+
+/// Largest draft that can be saved, in bytes. The same as for saved code, so
+/// every draft can become a version.
+pub const MAX_DRAFT_BYTES: usize = MAX_CODE_BYTES;
+
 /// Validates an agent name.
 pub fn validate_agent_name(name: &str) -> Result<()> {
     let name = name.trim();
@@ -46,24 +111,27 @@ pub fn validate_agent_name(name: &str) -> Result<()> {
     Ok(())
 }
 
-/// Validates agent code - must not be empty and must be valid Lua syntax.
+/// Validates agent code - must not be empty, at most [`MAX_CODE_BYTES`] and
+/// valid Lua syntax.
+///
+/// The code is only compiled inside the sandbox, never executed.
 pub fn validate_agent_code(code: &str) -> Result<()> {
     if code.trim().is_empty() {
         return Err(AgentError::CodeEmpty);
     }
 
-    // Validate Lua syntax by attempting to load the code
-    let lua = Lua::new();
-    lua.load(code).exec().map_err(|e| {
-        // Extract the error message, removing the "[string \"...\"]:line:" prefix if present
-        let msg = e.to_string();
-        let clean_msg = if let Some(pos) = msg.find("]:") {
-            msg[pos + 2..].trim().to_string()
-        } else {
-            msg
-        };
-        AgentError::InvalidLuaSyntax(clean_msg)
-    })?;
+    if code.len() > MAX_CODE_BYTES {
+        return Err(AgentError::CodeTooLarge);
+    }
+
+    sandbox::check_syntax(code)
+}
+
+/// Validates a draft. Drafts may be unfinished, so only the size is checked.
+pub fn validate_agent_draft(code: &str) -> Result<()> {
+    if code.len() > MAX_DRAFT_BYTES {
+        return Err(AgentError::DraftTooLarge);
+    }
 
     Ok(())
 }
@@ -76,10 +144,51 @@ pub struct Agent {
     pub game_id: i64,
     pub name: String,
     pub code: String,
+    /// Number of the latest version, the one in `code`
+    pub version: i64,
+    /// Whether there is a draft that differs from `code`
+    pub has_draft: bool,
+    /// Whether the agent plays on its game's ladder
+    pub ladder: bool,
+    /// Rating of the latest version, `None` until it has played a rated match
+    pub rating: Option<f64>,
+    /// Uncertainty of `rating`, lower is more certain
+    pub rating_deviation: Option<f64>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Unpublished changes to an agent's code, which may not even be valid Lua.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AgentDraft {
+    pub agent_id: i64,
+    pub code: String,
+    pub updated_at: String,
+}
+
+/// A saved revision of an agent's code. Versions are numbered from 1 and
+/// never change once saved.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AgentVersion {
+    pub agent_id: i64,
+    pub version: i64,
+    pub code: String,
+    pub created_at: String,
+}
+
+/// The changes between two versions of an agent.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentDiff {
+    pub agent_id: i64,
+    /// Version changed from
+    pub from: i64,
+    /// Version changed to
+    pub to: i64,
+    /// The changes in unified diff format, empty if there are none
+    pub unified: String,
+    pub hunks: Vec<Hunk>,
+}
+
 /// Request payload for creating a new agent.
 #[derive(Debug, Deserialize)]
 pub struct CreateAgentRequest {
@@ -89,6 +198,12 @@ pub struct CreateAgentRequest {
     pub code: String,
 }
 
+/// Request payload for saving a draft.
+#[derive(Debug, Deserialize)]
+pub struct SaveDraftRequest {
+    pub code: String,
+}
+
 /// Request payload for updating an existing agent.
 #[derive(Debug, Deserialize)]
 pub struct UpdateAgentRequest {
@@ -237,6 +352,37 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn validate_code_does_not_execute_code() {
+        // Both would misbehave if executed: one never ends, the other touches the disk
+        assert!(validate_agent_code("while true do end").is_ok());
+        assert!(validate_agent_code("io.open('/tmp/agent.txt', 'w')").is_ok());
+    }
+
+    #[test]
+    fn validate_draft_accepts_broken_code() {
+        assert!(validate_agent_draft("function think(").is_ok());
+        assert!(validate_agent_draft("").is_ok());
+    }
+
+    #[test]
+    fn validate_draft_rejects_large_code() {
+        let code = "-".repeat(MAX_DRAFT_BYTES + 1);
+        assert!(matches!(
+            validate_agent_draft(&code),
+            Err(AgentError::DraftTooLarge)
+        ));
+    }
+
+    #[test]
+    fn validate_code_rejects_large_code() {
+        let code = "-".repeat(MAX_CODE_BYTES + 1);
+        assert!(matches!(
+            validate_agent_code(&code),
+            Err(AgentError::CodeTooLarge)
+        ));
+    }
+
     #[test]
     fn validate_code_rejects_malformed_expression() {
         let code = "local x = 5 +"; // incomplete expression
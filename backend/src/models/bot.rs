@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// How strong a house bot plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Lowercase name, as used in the API and in `ai/<game>/<name>.lua`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A built-in agent anyone can play against. House bots are owned by a
+/// system user, so nobody can edit them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HouseBot {
+    /// ID of the bot's agent, to use in `CreateMatchRequest::agent_ids`
+    pub agent_id: i64,
+    pub game_id: i64,
+    pub name: String,
+    pub difficulty: Difficulty,
+    /// Reference code of the bot, kids may learn from it
+    pub code: String,
+    pub version: i64,
+    /// Rating of the latest version, `None` until it has played a rated match
+    pub rating: Option<f64>,
+    /// Uncertainty of `rating`, lower is more certain
+    pub rating_deviation: Option<f64>,
+}
@@ -0,0 +1,113 @@
+use crate::models::JobStatus;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EvaluationError {
+    #[error("Evaluations are only played in games for two agents.")]
+    NotHeadToHead,
+
+    #[error("An evaluation needs at least {0} seeds.")]
+    TooFewSeeds(i64),
+
+    #[error("An evaluation plays at most {0} seeds.")]
+    TooManySeeds(i64),
+
+    #[error("Seed must not be negative.")]
+    NegativeSeed,
+
+    #[error("Seed is too large to play {0} seeds after it.")]
+    SeedTooLarge(i64),
+
+    #[error("Agent {0} does not exist.")]
+    InvalidAgent(i64),
+
+    #[error("Agent {0} has no version {1}.")]
+    InvalidVersion(i64, i64),
+
+    #[error("Agent A must be one of your own agents.")]
+    NotOwner,
+
+    #[error("Only the current version of agent {0} can be played against.")]
+    PrivateVersion(i64),
+
+    #[error("Both agents must play the same game.")]
+    DifferentGames,
+
+    #[error("An agent of this evaluation no longer exists.")]
+    MissingAgent,
+
+    #[error("You can have at most {0} evaluations waiting to be played.")]
+    TooManyPending(i64),
+}
+
+/// Two agent versions played against each other over many seeds, each seed
+/// from both seats, to tell whether A is really better than B. The results
+/// are only set once `status` is `finished`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub id: i64,
+    pub game_id: i64,
+    /// User who asked for the evaluation
+    pub user_id: i64,
+    /// `None` if the agent has since been deleted
+    pub agent_a_id: Option<i64>,
+    pub agent_a_version: i64,
+    pub agent_b_id: Option<i64>,
+    pub agent_b_version: i64,
+    /// First seed played, the others follow it
+    pub seed: i64,
+    /// Number of seeds, each played twice with the seats swapped
+    pub seeds: i64,
+    pub status: JobStatus,
+    /// Why the evaluation could not be played, when it failed
+    pub error: Option<String>,
+    /// Matches won by A
+    pub wins: Option<i64>,
+    pub draws: Option<i64>,
+    /// Matches won by B
+    pub losses: Option<i64>,
+    /// Mean of A's score minus B's score per match
+    pub mean_score_diff: Option<f64>,
+    /// Lower end of the 95% confidence interval of `mean_score_diff`
+    pub ci_low: Option<f64>,
+    /// Upper end of the 95% confidence interval of `mean_score_diff`
+    pub ci_high: Option<f64>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// One seed of an evaluation, played twice by a job of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationSeed {
+    pub id: i64,
+    pub evaluation_id: i64,
+    pub seed: i64,
+    pub status: JobStatus,
+}
+
+/// The results of a played evaluation, before they are stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationSummary {
+    pub wins: i64,
+    pub draws: i64,
+    pub losses: i64,
+    pub mean_score_diff: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateEvaluationRequest {
+    pub agent_a_id: i64,
+    /// Version of agent A to play, its latest if left out
+    pub agent_a_version: Option<i64>,
+    /// May be the same agent as A, to compare two of its versions
+    pub agent_b_id: i64,
+    /// Version of agent B to play, its latest if left out
+    pub agent_b_version: Option<i64>,
+    /// Number of seeds to play, a default if left out
+    pub seeds: Option<i64>,
+    /// First seed, picked by the server if left out
+    pub seed: Option<i64>,
+}
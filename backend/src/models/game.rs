@@ -9,4 +9,6 @@ pub struct Game {
     pub name: String,
     /// Human-friendly display name
     pub display_name: String,
+    /// Memory limit for each agent's Lua VM, in bytes
+    pub agent_memory_limit: i64,
 }
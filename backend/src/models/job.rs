@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// What a background job does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Play the match with ID `target_id`.
+    Match,
+    /// Play both matches of the evaluation seed with ID `target_id`.
+    EvaluationSeed,
+}
+
+/// Where a job, and the match it plays, is in its life cycle:
+/// `queued → running → finished/failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// A unit of background work, processed by the worker pool.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub kind: JobKind,
+    /// ID of the row the job works on, in the table for its kind
+    pub target_id: i64,
+    pub status: JobStatus,
+    /// Number of times a worker has started the job
+    pub attempts: i64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// State and settings of the ladder, which keeps pairing agents on it with
+/// opponents of similar rating.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LadderStatus {
+    /// Paused by an admin, no ladder matches are queued
+    pub paused: bool,
+    /// When agents were last paired, `None` if never
+    pub last_run_at: Option<String>,
+    /// Seconds between pairings, 0 if the ladder never runs
+    pub interval_secs: u64,
+    /// Largest rating difference between opponents
+    pub rating_window: f64,
+    /// Most ladder matches an agent plays per day
+    pub daily_match_cap: i64,
+}
@@ -0,0 +1,275 @@
+use crate::models::{AgentError, Difficulty, JobStatus};
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use thiserror::Error;
+
+/// Fewest seats a match has, even in games that can be played alone.
+pub const MIN_MATCH_PLAYERS: usize = 2;
+
+#[derive(Debug, Error)]
+pub enum MatchError {
+    #[error("Unknown game.")]
+    UnknownGame,
+
+    #[error("A match in this game needs at least {0} agents.")]
+    TooFewAgents(usize),
+
+    #[error("This game allows at most {0} agents.")]
+    TooManyAgents(usize),
+
+    #[error("Agent {0} does not exist or does not play this game.")]
+    InvalidAgent(i64),
+
+    #[error("This game has no {0} house bot.")]
+    MissingBot(Difficulty),
+
+    #[error("Only matches between your own agents can be rated.")]
+    RatedNotAllowed,
+
+    #[error("Seed must not be negative.")]
+    NegativeSeed,
+
+    #[error("The agent in seat {0} no longer exists.")]
+    MissingAgent(usize),
+
+    #[error("The version of the agent in seat {0} no longer exists.")]
+    MissingAgentVersion(usize),
+
+    #[error("This match is not being played right now.")]
+    NotLive,
+
+    #[error("This match has not been played yet.")]
+    NotPlayed,
+}
+
+/// Why a match ended. Stored and sent as a `snake_case` name: `timeout`,
+/// `agent_error`, or the game's own reason when its rules ended the match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Termination {
+    /// The game's rules ended the match, for the reason the game gives, like
+    /// `ring_out` in robot sumo.
+    Finished(String),
+    /// The tick limit was reached.
+    Timeout,
+    /// An agent's code failed, see [`MatchAgent::error`].
+    AgentError,
+}
+
+impl Termination {
+    /// Name the termination is stored and sent as.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Termination::Finished(reason) => reason,
+            Termination::Timeout => "timeout",
+            Termination::AgentError => "agent_error",
+        }
+    }
+}
+
+impl From<String> for Termination {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "timeout" => Termination::Timeout,
+            "agent_error" => Termination::AgentError,
+            _ => Termination::Finished(name),
+        }
+    }
+}
+
+impl From<Termination> for String {
+    fn from(termination: Termination) -> Self {
+        match termination {
+            Termination::Finished(reason) => reason,
+            other => other.as_str().to_string(),
+        }
+    }
+}
+
+impl From<game_core::Termination> for Termination {
+    fn from(termination: game_core::Termination) -> Self {
+        match termination {
+            game_core::Termination::Finished(reason) => Termination::Finished(reason.to_string()),
+            game_core::Termination::Timeout => Termination::Timeout,
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for Termination {
+    fn type_info() -> SqliteTypeInfo {
+        <String as sqlx::Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as sqlx::Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for Termination {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        <String as sqlx::Encode<Sqlite>>::encode(self.as_str().to_string(), buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Sqlite> for Termination {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(<String as sqlx::Decode<Sqlite>>::decode(value)?.into())
+    }
+}
+
+/// A game played between two or more agents. Matches are played in the
+/// background, so results are only set once `status` is `finished`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Match {
+    pub id: i64,
+    pub game_id: i64,
+    /// User who started the match
+    pub user_id: i64,
+    pub seed: i64,
+    /// Version of the game core the match was played with, `None` while
+    /// unplayed and for matches from before versions were recorded
+    pub game_version: Option<String>,
+    /// Version of the Lua runtime the agents ran on, like `game_version`
+    pub lua_version: Option<String>,
+    /// Whether the match counts towards the ratings of the agents
+    pub rated: bool,
+    /// Whether the ladder queued the match, rather than a user
+    pub ladder: bool,
+    pub status: JobStatus,
+    /// Why the match could not be played, when it failed
+    pub error: Option<String>,
+    /// Seat of the winning agent, `None` for a draw or while unplayed
+    pub winner: Option<i64>,
+    /// Number of ticks played
+    pub ticks: Option<i64>,
+    pub termination: Option<Termination>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+    /// Participants, ordered by seat
+    pub agents: Vec<MatchAgent>,
+}
+
+/// Progress of a match, for clients polling until it has been played.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchStatus {
+    pub id: i64,
+    pub status: JobStatus,
+    pub error: Option<String>,
+}
+
+/// An agent's seat in a match and how it did.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchAgent {
+    /// Player index in the game, starting at 0
+    pub seat: i64,
+    /// `None` if the agent has since been deleted
+    pub agent_id: Option<i64>,
+    /// Version of the agent that plays, `None` for matches from before
+    /// agents had versions
+    pub agent_version: Option<i64>,
+    pub score: Option<i64>,
+    /// Error that stopped the agent, if any
+    pub error: Option<String>,
+}
+
+/// The result of playing a match, before it is stored.
+#[derive(Debug)]
+pub struct MatchOutcome {
+    /// Seat of the winning agent, `None` for a draw
+    pub winner: Option<usize>,
+    /// Score of every seat
+    pub scores: Vec<u32>,
+    pub ticks: u32,
+    pub termination: Termination,
+    /// Error that stopped each seat's agent, if any
+    pub errors: Vec<Option<AgentError>>,
+    /// The match in the `game_core::replay` format
+    pub replay: Vec<u8>,
+    /// Lines the agents printed, by tick
+    pub logs: Vec<LogLine>,
+    /// What the agents had in their `memory` table, by tick
+    pub memory: Vec<MemorySnapshot>,
+    /// `GameRules::VERSION` of the game core that played the match
+    pub game_version: &'static str,
+    /// Version of the Lua runtime the agents ran on
+    pub lua_version: &'static str,
+}
+
+/// A line an agent printed with `print` during a match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLine {
+    /// Seat of the agent that printed it
+    pub seat: i64,
+    pub tick: i64,
+    pub message: String,
+}
+
+/// What an agent had in its `memory` table after a tick on which it changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemorySnapshot {
+    /// Seat of the agent that remembered it
+    pub seat: i64,
+    pub tick: i64,
+    /// The whole table, as JSON
+    pub memory: serde_json::Value,
+}
+
+/// A finished match played again, compared with how it was first played.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatchVerification {
+    pub match_id: i64,
+    /// Whether the new replay is byte for byte the stored one
+    pub identical: bool,
+    /// Offset of the first byte where the replays differ, if they do
+    pub first_difference: Option<i64>,
+    /// Size of the stored replay in bytes
+    pub stored_size: i64,
+    /// Size of the new replay in bytes
+    pub replayed_size: i64,
+    /// Versions the match was first played with, `None` for matches from
+    /// before they were recorded
+    pub game_version: Option<String>,
+    pub lua_version: Option<String>,
+    /// Versions the match was played with again
+    pub replayed_game_version: String,
+    pub replayed_lua_version: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMatchRequest {
+    pub game_id: i64,
+    /// Agents in seat order. The same agent may take several seats.
+    pub agent_ids: Vec<i64>,
+    /// House bots taking the seats after the agents
+    #[serde(default)]
+    pub bots: Vec<Difficulty>,
+    /// Random seed for the game, picked by the server if left out
+    pub seed: Option<i64>,
+    /// Whether the match counts towards ratings, `false` if left out. Only
+    /// allowed when the caller owns every seat, or is an admin.
+    pub rated: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_termination_names() {
+        for (termination, name) in [
+            (Termination::Finished("ring_out".to_string()), "ring_out"),
+            (Termination::Timeout, "timeout"),
+            (Termination::AgentError, "agent_error"),
+        ] {
+            assert_eq!(termination.as_str(), name);
+            assert_eq!(serde_json::to_value(&termination).unwrap(), name);
+            assert_eq!(Termination::from(name.to_string()), termination);
+        }
+        assert_eq!(
+            Termination::from(game_core::Termination::Finished("ring_out")),
+            Termination::Finished("ring_out".to_string())
+        );
+    }
+}
@@ -1,7 +1,23 @@
 mod agent;
+mod bot;
+mod evaluation;
 mod game;
+mod job;
+mod ladder;
+mod r#match;
+mod playground;
+mod rating;
+mod tournament;
 mod user;
 
 pub use agent::*;
+pub use bot::*;
+pub use evaluation::*;
 pub use game::*;
+pub use job::*;
+pub use ladder::*;
+pub use r#match::*;
+pub use playground::*;
+pub use rating::*;
+pub use tournament::*;
 pub use user::*;
@@ -0,0 +1,42 @@
+use crate::models::{Difficulty, LogLine, MemorySnapshot, Termination};
+use serde::{Deserialize, Serialize};
+
+/// Who the code under test plays against in the playground.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Opponent {
+    /// The game's house bot of this difficulty
+    Bot(Difficulty),
+    /// The latest version of one of the user's own agents
+    Agent(i64),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaygroundRequest {
+    /// Lua code to try, which is not saved anywhere
+    pub code: String,
+    pub opponent: Opponent,
+    /// Random seed for the game, picked by the server if left out
+    pub seed: Option<i64>,
+}
+
+/// The result of a playground match. The code under test plays in seat 0,
+/// the opponent in seat 1.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlaygroundResult {
+    pub seed: i64,
+    /// Seat of the winner, `None` for a draw
+    pub winner: Option<i64>,
+    pub ticks: i64,
+    pub termination: Termination,
+    /// Score of every seat
+    pub scores: Vec<i64>,
+    /// Error that stopped each seat's agent, if any
+    pub errors: Vec<Option<String>>,
+    /// The match in the `game_core::replay` format, base64 encoded
+    pub replay: String,
+    /// Lines both agents printed, by tick
+    pub logs: Vec<LogLine>,
+    /// What both agents had in their `memory` table, by tick
+    pub memory: Vec<MemorySnapshot>,
+}
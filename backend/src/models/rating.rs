@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// The Glicko-2 rating of one version of an agent.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentRating {
+    pub agent_id: i64,
+    pub agent_version: i64,
+    pub game_id: i64,
+    pub rating: f64,
+    /// Uncertainty of the rating, lower is more certain
+    pub deviation: f64,
+    pub volatility: f64,
+    /// Number of rated matches played
+    pub matches: i64,
+    pub updated_at: String,
+}
+
+/// An agent's place on the leaderboard of a game.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    /// Position on the leaderboard, starting at 1
+    pub rank: i64,
+    pub agent_id: i64,
+    pub agent_name: String,
+    /// Username of the agent's owner
+    pub owner: String,
+    /// Newest version of the agent that has a rating
+    pub agent_version: i64,
+    pub rating: f64,
+    pub deviation: f64,
+    pub wins: i64,
+    pub losses: i64,
+    pub draws: i64,
+    /// Number of rated matches played
+    pub games: i64,
+}
+
+/// A page of a game's leaderboard, best rated first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub game: String,
+    pub page: i64,
+    pub per_page: i64,
+    /// Number of agents on the whole leaderboard
+    pub total: i64,
+    pub entries: Vec<LeaderboardEntry>,
+}
@@ -0,0 +1,217 @@
+use crate::models::JobStatus;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TournamentError {
+    #[error("Tournament name is required.")]
+    NameEmpty,
+
+    #[error("Tournament name must be at most 100 characters.")]
+    NameTooLong,
+
+    #[error("Unknown game.")]
+    UnknownGame,
+
+    #[error("Tournaments are only played in games for two agents.")]
+    NotHeadToHead,
+
+    #[error("Only Swiss tournaments have a number of rounds, which must be at least 1.")]
+    InvalidRounds,
+
+    #[error("Agent {0} does not exist or does not play this game.")]
+    InvalidAgent(i64),
+
+    #[error("Agent {0} has no version {1}.")]
+    InvalidVersion(i64, i64),
+
+    #[error("The tournament has already started.")]
+    AlreadyStarted,
+
+    #[error("A tournament needs at least 2 agents.")]
+    TooFewEntrants,
+}
+
+type Result<T> = std::result::Result<T, TournamentError>;
+
+/// Validates a tournament name.
+pub fn validate_tournament_name(name: &str) -> Result<()> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(TournamentError::NameEmpty);
+    }
+
+    if name.len() > 100 {
+        return Err(TournamentError::NameTooLong);
+    }
+
+    Ok(())
+}
+
+/// How the entrants of a tournament are paired and ranked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TournamentFormat {
+    /// Every entrant plays every other entrant once.
+    RoundRobin,
+    /// Entrants are knocked out by their first loss.
+    SingleElimination,
+    /// Entrants are knocked out by their second loss.
+    DoubleElimination,
+    /// A fixed number of rounds, pairing entrants with similar scores.
+    Swiss,
+}
+
+/// Where a tournament is in its life cycle: `pending → running → finished`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TournamentStatus {
+    /// Agents can be enrolled.
+    Pending,
+    Running,
+    Finished,
+}
+
+/// The bracket of a double elimination pairing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TournamentBracket {
+    /// Entrants without a loss.
+    Winners,
+    /// Entrants with one loss.
+    Losers,
+    /// The last two entrants.
+    Final,
+}
+
+/// A competition between agents of one game.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tournament {
+    pub id: i64,
+    pub game_id: i64,
+    /// User who runs the tournament
+    pub user_id: i64,
+    pub name: String,
+    pub format: TournamentFormat,
+    /// Number of rounds of a Swiss tournament, picked from the number of
+    /// entrants if `None`
+    pub rounds: Option<i64>,
+    pub status: TournamentStatus,
+    /// Round being played, 0 before the tournament starts
+    pub current_round: i64,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// An agent enrolled in a tournament.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentEntrant {
+    pub id: i64,
+    /// `None` if the agent has since been deleted
+    pub agent_id: Option<i64>,
+    pub agent_name: Option<String>,
+    /// Version of the agent that plays every match
+    pub agent_version: i64,
+    /// Position in the seeding, 1 for the favourite, `None` until the
+    /// tournament starts
+    pub seed: Option<i64>,
+}
+
+/// Two entrants of a round and the match between them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentPairing {
+    pub id: i64,
+    pub round: i64,
+    pub bracket: Option<TournamentBracket>,
+    /// Entrant in seat 0
+    pub first_entrant_id: i64,
+    /// Entrant in seat 1, `None` for a bye
+    pub second_entrant_id: Option<i64>,
+    /// `None` for a bye
+    pub match_id: Option<i64>,
+    /// Status of the match, `None` for a bye
+    pub status: Option<JobStatus>,
+    /// `None` for byes, draws and matches that haven't been played
+    pub winner_entrant_id: Option<i64>,
+}
+
+/// The pairings of one round.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentRound {
+    pub round: i64,
+    pub pairings: Vec<TournamentPairing>,
+}
+
+/// An entrant's place in a tournament.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentStanding {
+    /// Position in the standings, starting at 1
+    pub rank: i64,
+    pub entrant_id: i64,
+    pub agent_id: Option<i64>,
+    pub agent_name: Option<String>,
+    /// 1 for a win and 0.5 for a draw. Byes count as wins in Swiss tournaments.
+    pub points: f64,
+    pub wins: i64,
+    pub draws: i64,
+    pub losses: i64,
+    pub byes: i64,
+    /// Sum of the points of every opponent played, the first tie-breaker
+    pub buchholz: f64,
+    /// Round the entrant was knocked out in, for elimination formats
+    pub eliminated_in: Option<i64>,
+}
+
+/// A tournament with its entrants and how far it has come.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentDetails {
+    #[serde(flatten)]
+    pub tournament: Tournament,
+    /// Entrants in seeding order, or enrollment order before the start
+    pub entrants: Vec<TournamentEntrant>,
+    /// Best first
+    pub standings: Vec<TournamentStanding>,
+    /// Matches scheduled so far
+    pub matches_total: i64,
+    /// Scheduled matches that are finished or failed
+    pub matches_played: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTournamentRequest {
+    pub game_id: i64,
+    pub name: String,
+    pub format: TournamentFormat,
+    /// Number of rounds, only for Swiss tournaments
+    pub rounds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollAgentRequest {
+    pub agent_id: i64,
+    /// Version of the agent to play, its latest if left out
+    pub version: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_tournament_name() {
+        assert!(validate_tournament_name("End of term").is_ok());
+        assert!(matches!(
+            validate_tournament_name("   "),
+            Err(TournamentError::NameEmpty)
+        ));
+        assert!(matches!(
+            validate_tournament_name(&"a".repeat(101)),
+            Err(TournamentError::NameTooLong)
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Quick matches for code that hasn't been saved as an agent.
+//!
+//! The playground lets users try code from the editor against a house bot or
+//! one of their own agents. The match is played while the request waits, so
+//! it is cut short after [`MAX_TICKS`], the code runs on tighter budgets
+//! than in queued matches, and every user may only start
+//! [`Config::playground_runs_per_minute`] matches a minute. Matches are
+//! played on the blocking thread pool the job queue also uses, so at most
+//! [`Config::playground_max_running`] of them run at once, across all users.
+//! Nothing about the match is stored.
+
+use crate::models::{
+    Game, MatchError, Opponent, PlaygroundRequest, PlaygroundResult, validate_agent_code,
+};
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, BotRepository};
+use crate::runner::Entrant;
+use crate::sandbox::Limits;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default number of playground matches a user may start per minute.
+pub const DEFAULT_RUNS_PER_MINUTE: usize = 10;
+
+/// Default number of playground matches played at the same time.
+pub const DEFAULT_MAX_RUNNING: usize = 4;
+
+/// Ticks after which a playground match ends in a draw.
+pub const MAX_TICKS: u32 = 300;
+
+/// Most Lua instructions per agent call in the playground.
+pub const INSTRUCTION_LIMIT: u64 = 250_000;
+
+/// Most wall-clock time per agent call in the playground.
+pub const TIME_LIMIT: Duration = Duration::from_millis(10);
+
+/// Name of the code under test in the replay.
+const PLAYGROUND_NAME: &str = "Playground";
+
+/// Counts the runs every user started in the last minute.
+#[derive(Debug)]
+pub struct RateLimiter {
+    runs_per_minute: usize,
+    runs: Mutex<HashMap<i64, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(runs_per_minute: usize) -> Self {
+        Self {
+            runs_per_minute,
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a run for the user, unless they already used up their runs.
+    pub fn try_start(&self, user_id: i64) -> bool {
+        let now = Instant::now();
+        let mut runs = self.runs.lock().unwrap();
+        // Forget users whose runs have all expired, so the map stays small
+        runs.retain(|_, started| {
+            while started
+                .front()
+                .is_some_and(|&time| now.duration_since(time) >= Duration::from_secs(60))
+            {
+                started.pop_front();
+            }
+            !started.is_empty()
+        });
+
+        let started = runs.entry(user_id).or_default();
+        if started.len() >= self.runs_per_minute {
+            return false;
+        }
+        started.push_back(now);
+        true
+    }
+}
+
+/// Play `request.code` against its opponent on behalf of a user.
+pub async fn play(
+    state: &AppState,
+    user_id: i64,
+    game: Game,
+    request: PlaygroundRequest,
+) -> Result<PlaygroundResult> {
+    validate_agent_code(&request.code)?;
+
+    let opponent = match request.opponent {
+        Opponent::Bot(difficulty) => {
+            let bot = BotRepository::new(&state.db)
+                .find_by_difficulty(game.id, difficulty)
+                .await?
+                .ok_or(MatchError::MissingBot(difficulty))?;
+            Entrant {
+                id: Some(bot.agent_id),
+                name: bot.name,
+                code: bot.code,
+            }
+        }
+        Opponent::Agent(agent_id) => {
+            let agent = AgentRepository::new(&state.db)
+                .find_by_id(agent_id, user_id)
+                .await?
+                .filter(|agent| agent.game_id == game.id)
+                .ok_or(MatchError::InvalidAgent(agent_id))?;
+            Entrant {
+                id: Some(agent.id),
+                name: agent.name,
+                code: agent.code,
+            }
+        }
+    };
+
+    // Only the lower 63 bits, so the seed fits in an SQLite integer
+    let seed = request
+        .seed
+        .unwrap_or_else(|| (RandomState::new().hash_one(()) >> 1) as i64);
+    if seed < 0 {
+        return Err(MatchError::NegativeSeed.into());
+    }
+
+    // Taken before counting the run, so a busy server doesn't use up the
+    // user's runs, and held until the match is over even if the request is
+    // dropped
+    let permit = state
+        .playground_runs
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| Error::TooManyRequests)?;
+    if !state.playground.try_start(user_id) {
+        return Err(Error::TooManyRequests);
+    }
+
+    let entrants = vec![
+        Entrant {
+            id: None,
+            name: PLAYGROUND_NAME.to_string(),
+            code: request.code,
+        },
+        opponent,
+    ];
+    let limits = limits(state.config.sandbox_limits(&game));
+    let games = state.games.clone();
+    let outcome = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let rules = games.get(&game.name).ok_or(MatchError::UnknownGame)?;
+        Ok::<_, MatchError>(rules.run(seed as u64, &entrants, limits, Some(MAX_TICKS), None))
+    })
+    .await??;
+
+    Ok(PlaygroundResult {
+        seed,
+        winner: outcome.winner.map(|seat| seat as i64),
+        ticks: i64::from(outcome.ticks),
+        termination: outcome.termination,
+        scores: outcome.scores.into_iter().map(i64::from).collect(),
+        errors: outcome
+            .errors
+            .iter()
+            .map(|error| error.as_ref().map(ToString::to_string))
+            .collect(),
+        replay: STANDARD.encode(&outcome.replay),
+        logs: outcome.logs,
+        memory: outcome.memory,
+    })
+}
+
+/// Tighten the limits of queued matches for the playground.
+fn limits(limits: Limits) -> Limits {
+    Limits {
+        instructions: limits.instructions.min(INSTRUCTION_LIMIT),
+        time: limits.time.min(TIME_LIMIT),
+        memory: limits.memory,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_counts_runs_per_user() {
+        let limiter = RateLimiter::new(2);
+
+        assert!(limiter.try_start(1));
+        assert!(limiter.try_start(1));
+        assert!(!limiter.try_start(1));
+        assert!(limiter.try_start(2));
+    }
+
+    #[test]
+    fn limits_are_never_looser_than_queued_matches() {
+        let strict = Limits {
+            instructions: 1000,
+            time: Duration::from_millis(1),
+            memory: 1024,
+        };
+        let tightened = limits(strict);
+        assert_eq!(tightened.instructions, 1000);
+        assert_eq!(tightened.time, Duration::from_millis(1));
+
+        let tightened = limits(Limits::default());
+        assert_eq!(tightened.instructions, INSTRUCTION_LIMIT);
+        assert_eq!(tightened.time, TIME_LIMIT);
+    }
+}
@@ -88,6 +88,8 @@ impl FromRequestParts<AppState> for Claims {
 mod tests {
     use super::*;
     use crate::prelude::{AppState, Config};
+    use crate::sandbox;
+    use crate::{ladder, playground};
     use axum::http::Request;
 
     fn test_state(secret: &str) -> AppState {
@@ -98,6 +100,14 @@ mod tests {
                 database_url,
                 server_port: 3000,
                 jwt_secret: secret.to_string(),
+                agent_instruction_limit: sandbox::DEFAULT_INSTRUCTION_LIMIT,
+                agent_time_limit_ms: sandbox::DEFAULT_TIME_LIMIT_MS,
+                job_workers: 1,
+                ladder_interval_secs: 0,
+                ladder_rating_window: ladder::DEFAULT_RATING_WINDOW,
+                ladder_daily_match_cap: ladder::DEFAULT_DAILY_MATCH_CAP,
+                playground_runs_per_minute: playground::DEFAULT_RUNS_PER_MINUTE,
+                playground_max_running: playground::DEFAULT_MAX_RUNNING,
             },
             db,
         )
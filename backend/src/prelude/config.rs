@@ -1,13 +1,23 @@
 use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::Duration;
 
 use thiserror::Error;
 
+use crate::ladder;
+use crate::models::Game;
+use crate::playground;
+use crate::queue;
+use crate::sandbox::{self, Limits};
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Missing environment variable: {0}")]
     MissingEnvVar(String),
     #[error("Invalid port: {0}")]
     InvalidPort(#[from] ParseIntError),
+    #[error("Invalid value for environment variable: {0}")]
+    InvalidValue(String),
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +25,22 @@ pub struct Config {
     pub database_url: String,
     pub server_port: u16,
     pub jwt_secret: String,
+    /// Maximum number of Lua instructions per agent call.
+    pub agent_instruction_limit: u64,
+    /// Maximum wall-clock time per agent call, in milliseconds.
+    pub agent_time_limit_ms: u64,
+    /// Number of background workers playing queued matches.
+    pub job_workers: usize,
+    /// Seconds between ladder pairings, 0 to never pair.
+    pub ladder_interval_secs: u64,
+    /// Largest rating difference between ladder opponents.
+    pub ladder_rating_window: f64,
+    /// Most ladder matches an agent plays per day.
+    pub ladder_daily_match_cap: i64,
+    /// Most playground matches a user may start per minute.
+    pub playground_runs_per_minute: usize,
+    /// Most playground matches played at the same time, by all users.
+    pub playground_max_running: usize,
 }
 
 impl Config {
@@ -29,10 +55,64 @@ impl Config {
         let jwt_secret = std::env::var("JWT_SECRET")
             .map_err(|_| ConfigError::MissingEnvVar("JWT_SECRET".to_string()))?;
 
+        let agent_instruction_limit = optional_env(
+            "AGENT_INSTRUCTION_LIMIT",
+            sandbox::DEFAULT_INSTRUCTION_LIMIT,
+        )?;
+
+        let agent_time_limit_ms =
+            optional_env("AGENT_TIME_LIMIT_MS", sandbox::DEFAULT_TIME_LIMIT_MS)?;
+
+        let job_workers = optional_env("JOB_WORKERS", queue::DEFAULT_WORKERS)?;
+
+        let ladder_interval_secs =
+            optional_env("LADDER_INTERVAL_SECS", ladder::DEFAULT_INTERVAL_SECS)?;
+
+        let ladder_rating_window =
+            optional_env("LADDER_RATING_WINDOW", ladder::DEFAULT_RATING_WINDOW)?;
+
+        let ladder_daily_match_cap =
+            optional_env("LADDER_DAILY_MATCH_CAP", ladder::DEFAULT_DAILY_MATCH_CAP)?;
+
+        let playground_runs_per_minute = optional_env(
+            "PLAYGROUND_RUNS_PER_MINUTE",
+            playground::DEFAULT_RUNS_PER_MINUTE,
+        )?;
+
+        let playground_max_running =
+            optional_env("PLAYGROUND_MAX_RUNNING", playground::DEFAULT_MAX_RUNNING)?;
+
         Ok(Config {
             database_url,
             server_port,
             jwt_secret,
+            agent_instruction_limit,
+            agent_time_limit_ms,
+            job_workers,
+            ladder_interval_secs,
+            ladder_rating_window,
+            ladder_daily_match_cap,
+            playground_runs_per_minute,
+            playground_max_running,
         })
     }
+
+    /// Limits for agent code running in the given game.
+    pub fn sandbox_limits(&self, game: &Game) -> Limits {
+        Limits {
+            instructions: self.agent_instruction_limit,
+            time: Duration::from_millis(self.agent_time_limit_ms),
+            memory: game.agent_memory_limit.max(0) as usize,
+        }
+    }
+}
+
+/// Read an optional environment variable, falling back to a default when unset.
+fn optional_env<T: FromStr>(name: &str, default: T) -> Result<T, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| ConfigError::InvalidValue(name.to_string())),
+        Err(_) => Ok(default),
+    }
 }
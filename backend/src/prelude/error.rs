@@ -22,11 +22,26 @@ pub enum Error {
     #[error("Agent error: {0}")]
     Agent(#[from] crate::models::AgentError),
 
+    #[error("Match error: {0}")]
+    Match(#[from] crate::models::MatchError),
+
+    #[error("Tournament error: {0}")]
+    Tournament(#[from] crate::models::TournamentError),
+
+    #[error("Evaluation error: {0}")]
+    Evaluation(#[from] crate::models::EvaluationError),
+
+    #[error("Task error: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
     #[error("Not found")]
     NotFound,
 
     #[error("Conflict: {0}")]
     Conflict(String),
+
+    #[error("Too many requests, try again in a minute.")]
+    TooManyRequests,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -43,7 +58,12 @@ impl IntoResponse for Error {
             Error::Claims(_) => StatusCode::UNAUTHORIZED,
             Error::NotFound => StatusCode::NOT_FOUND,
             Error::Conflict(_) => StatusCode::CONFLICT,
-            Error::User(_) | Error::Agent(_) => StatusCode::BAD_REQUEST,
+            Error::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Error::User(_)
+            | Error::Agent(_)
+            | Error::Match(_)
+            | Error::Tournament(_)
+            | Error::Evaluation(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
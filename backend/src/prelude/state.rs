@@ -1,19 +1,38 @@
 use sqlx::SqlitePool;
 
+use crate::games::GameRegistry;
+use crate::live::LiveMatches;
+use crate::playground::RateLimiter;
 use crate::prelude::Config;
+use crate::queue::JobQueue;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
     pub db: SqlitePool,
+    pub games: Arc<GameRegistry>,
+    pub queue: Arc<JobQueue>,
+    pub live: Arc<LiveMatches>,
+    /// Playground runs each user started recently
+    pub playground: Arc<RateLimiter>,
+    /// Permits for playground matches being played right now
+    pub playground_runs: Arc<Semaphore>,
 }
 
 impl AppState {
     pub fn new(config: Config, db: SqlitePool) -> Self {
+        let playground = RateLimiter::new(config.playground_runs_per_minute);
+        let playground_runs = Semaphore::new(config.playground_max_running);
         Self {
             config: Arc::new(config),
             db,
+            games: Arc::new(GameRegistry::new()),
+            queue: Arc::new(JobQueue::new()),
+            live: Arc::new(LiveMatches::new()),
+            playground: Arc::new(playground),
+            playground_runs: Arc::new(playground_runs),
         }
     }
 }
@@ -0,0 +1,431 @@
+//! Background job queue.
+//!
+//! Jobs are rows in the `jobs` table, so queued work survives a restart. A
+//! pool of workers takes jobs oldest first and runs them to completion. A job
+//! moves through `queued → running → finished/failed`, and the match or
+//! evaluation seed it plays follows along so clients can poll it.
+//!
+//! Jobs still marked as running when the server starts were interrupted by a
+//! crash or restart. [`start`] puts them back in the queue, unless they have
+//! already been tried [`MAX_ATTEMPTS`] times, and tournaments whose round
+//! ended while the server was down move on to the next one.
+
+use crate::models::{Job, JobKind, JobStatus, Match, MatchError};
+use crate::prelude::*;
+use crate::repositories::{
+    AgentRepository, EvaluationRepository, GameRepository, JobRepository, MatchRepository,
+};
+use crate::runner::Entrant;
+use crate::{evaluation, tournament};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// Default number of workers processing jobs at the same time.
+pub const DEFAULT_WORKERS: usize = 2;
+
+/// Number of times a job is started before it is given up on.
+pub const MAX_ATTEMPTS: i64 = 3;
+
+/// How often idle workers look for new jobs when they are not woken up.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wakes idle workers when new jobs are queued.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    notify: Notify,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tell the workers a job was queued.
+    pub fn notify(&self) {
+        self.notify.notify_one();
+    }
+}
+
+/// Recover interrupted jobs and start the worker pool.
+pub async fn start(state: &AppState) -> Result<()> {
+    recover(state).await?;
+
+    let workers = state.config.job_workers.max(1);
+    for _ in 0..workers {
+        tokio::spawn(work(state.clone()));
+    }
+    info!("Started {} job workers", workers);
+
+    Ok(())
+}
+
+/// Requeue jobs left running by a previous run of the server.
+async fn recover(state: &AppState) -> Result<()> {
+    let jobs = JobRepository::new(&state.db);
+    for job in jobs.find_running().await? {
+        if job.attempts < MAX_ATTEMPTS {
+            warn!("Requeueing interrupted job {}", job.id);
+            jobs.requeue(job.id).await?;
+            set_target_status(state, &job, JobStatus::Queued, None).await?;
+        } else {
+            warn!(
+                "Giving up on job {} after {} attempts",
+                job.id, job.attempts
+            );
+            fail(state, &job, "Interrupted too many times.").await?;
+        }
+    }
+    tournament::resume(state).await
+}
+
+/// Process jobs until the server shuts down.
+async fn work(state: AppState) {
+    loop {
+        match JobRepository::new(&state.db).claim_next().await {
+            Ok(Some(job)) => process(&state, job).await,
+            Ok(None) => {
+                tokio::select! {
+                    _ = state.queue.notify.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+            Err(e) => {
+                error!("Failed to claim job: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Run a claimed job and record how it went.
+async fn process(state: &AppState, job: Job) {
+    let result = match job.kind {
+        JobKind::Match => play_match(state, job.target_id).await,
+        JobKind::EvaluationSeed => evaluation::play_seed(state, job.target_id).await,
+    };
+
+    let saved = match result {
+        Ok(()) => JobRepository::new(&state.db).finish(job.id).await,
+        Err(e) => {
+            warn!("Job {} failed: {}", job.id, e);
+            fail(state, &job, &e.to_string()).await
+        }
+    };
+    if let Err(e) = saved {
+        error!("Failed to save result of job {}: {}", job.id, e);
+    }
+
+    // Played or not, the match may have been the last of a tournament round
+    let advanced = match job.kind {
+        JobKind::Match => tournament::match_done(state, job.target_id).await,
+        JobKind::EvaluationSeed => Ok(()),
+    };
+    if let Err(e) = advanced {
+        error!("Failed to advance tournament after job {}: {}", job.id, e);
+    }
+}
+
+/// Mark a job, and what it works on, as failed.
+async fn fail(state: &AppState, job: &Job, error: &str) -> Result<()> {
+    JobRepository::new(&state.db).fail(job.id, error).await?;
+    set_target_status(state, job, JobStatus::Failed, Some(error)).await
+}
+
+/// Keep the status of what a job works on in step with the job.
+async fn set_target_status(
+    state: &AppState,
+    job: &Job,
+    status: JobStatus,
+    error: Option<&str>,
+) -> Result<()> {
+    match job.kind {
+        JobKind::Match => {
+            MatchRepository::new(&state.db)
+                .set_status(job.target_id, status, error)
+                .await
+        }
+        JobKind::EvaluationSeed => {
+            EvaluationRepository::new(&state.db)
+                .set_seed_status(job.target_id, status, error)
+                .await
+        }
+    }
+}
+
+/// Play a queued match, then store its result and update the ratings in
+/// one go. A match that was already stored, e.g. before a crash, is not
+/// played again.
+async fn play_match(state: &AppState, match_id: i64) -> Result<()> {
+    let matches = MatchRepository::new(&state.db);
+    let queued = matches.find_by_id(match_id).await?.ok_or(Error::NotFound)?;
+    if queued.status == JobStatus::Finished {
+        return Ok(());
+    }
+    matches
+        .set_status(match_id, JobStatus::Running, None)
+        .await?;
+
+    let game = GameRepository::new(&state.db)
+        .find_by_id(queued.game_id)
+        .await?
+        .ok_or(MatchError::UnknownGame)?;
+
+    let entrants = load_entrants(state, &queued).await?;
+
+    let limits = state.config.sandbox_limits(&game);
+    let games = state.games.clone();
+    let seed = queued.seed as u64;
+    let live = state.live.start(match_id);
+    let played = tokio::task::spawn_blocking(move || {
+        let rules = games.get(&game.name).ok_or(MatchError::UnknownGame)?;
+        Ok::<_, MatchError>(rules.run(seed, &entrants, limits, None, Some(&live)))
+    })
+    .await;
+
+    // Store the result before closing the stream, so spectators who see it
+    // end can fetch the replay
+    let result = match played {
+        Ok(Ok(outcome)) => matches.finish(match_id, &outcome).await,
+        Ok(Err(e)) => Err(e.into()),
+        Err(e) => Err(e.into()),
+    };
+    state.live.finish(match_id);
+    result
+}
+
+/// Load the code of every seat of a match, in seat order. Each agent plays
+/// the version the match was created with, even if it was edited since.
+pub(crate) async fn load_entrants(state: &AppState, queued: &Match) -> Result<Vec<Entrant>> {
+    let agent_repo = AgentRepository::new(&state.db);
+    let mut entrants = Vec::with_capacity(queued.agents.len());
+    for (seat, seated) in queued.agents.iter().enumerate() {
+        let agent = match seated.agent_id {
+            Some(agent_id) => agent_repo.find_by_id_any_owner(agent_id).await?,
+            None => None,
+        };
+        let agent = agent.ok_or(MatchError::MissingAgent(seat))?;
+
+        let code = match seated.agent_version {
+            Some(version) => {
+                agent_repo
+                    .find_version(agent.id, version)
+                    .await?
+                    .ok_or(MatchError::MissingAgentVersion(seat))?
+                    .code
+            }
+            None => agent.code,
+        };
+        entrants.push(Entrant {
+            id: Some(agent.id),
+            name: agent.name,
+            code,
+        });
+    }
+
+    Ok(entrants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::Config;
+    use crate::repositories::UserRepository;
+    use crate::sandbox;
+    use crate::{ladder, playground};
+    use sqlx::SqlitePool;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const IDLE: &str = "function think() end";
+
+    async fn setup_state() -> AppState {
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(":memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&db).await.unwrap();
+        let config = Config {
+            database_url: ":memory:".to_string(),
+            server_port: 0,
+            jwt_secret: "secret".to_string(),
+            agent_instruction_limit: sandbox::DEFAULT_INSTRUCTION_LIMIT,
+            agent_time_limit_ms: sandbox::DEFAULT_TIME_LIMIT_MS,
+            job_workers: 1,
+            ladder_interval_secs: 0,
+            ladder_rating_window: ladder::DEFAULT_RATING_WINDOW,
+            ladder_daily_match_cap: ladder::DEFAULT_DAILY_MATCH_CAP,
+            playground_runs_per_minute: playground::DEFAULT_RUNS_PER_MINUTE,
+            playground_max_running: playground::DEFAULT_MAX_RUNNING,
+        };
+        AppState::new(config, db)
+    }
+
+    /// Create a queued snake match between two idle agents.
+    async fn create_match(db: &SqlitePool) -> i64 {
+        let user = UserRepository::new(db)
+            .create("testuser", "TestPass123!", false)
+            .await
+            .unwrap();
+        let game = GameRepository::new(db)
+            .find_by_name("snake")
+            .await
+            .unwrap()
+            .unwrap();
+        let agent = AgentRepository::new(db)
+            .create(user.id, game.id, "Idle", IDLE)
+            .await
+            .unwrap();
+        let created = MatchRepository::new(db)
+            .create(game.id, user.id, 1, &[agent.id, agent.id], true)
+            .await
+            .unwrap();
+        created.id
+    }
+
+    /// Wait until a match is no longer queued or running.
+    async fn wait_for(db: &SqlitePool, match_id: i64) -> crate::models::Match {
+        for _ in 0..500 {
+            let found = MatchRepository::new(db)
+                .find_by_id(match_id)
+                .await
+                .unwrap()
+                .unwrap();
+            if matches!(found.status, JobStatus::Finished | JobStatus::Failed) {
+                return found;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("match {} was not played in time", match_id);
+    }
+
+    #[tokio::test]
+    async fn workers_play_queued_matches() {
+        let state = setup_state().await;
+        let match_id = create_match(&state.db).await;
+
+        start(&state).await.unwrap();
+        state.queue.notify();
+
+        let played = wait_for(&state.db, match_id).await;
+        assert_eq!(played.status, JobStatus::Finished);
+        assert!(played.termination.is_some());
+        let replay = MatchRepository::new(&state.db)
+            .find_replay(match_id)
+            .await
+            .unwrap();
+        assert!(replay.is_some());
+
+        let job = JobRepository::new(&state.db)
+            .find_by_id(1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Finished);
+        assert_eq!(job.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn queued_match_plays_version_it_was_created_with() {
+        let state = setup_state().await;
+        let match_id = create_match(&state.db).await;
+        let repo = AgentRepository::new(&state.db);
+        let agent = repo.find_by_id_any_owner(1).await.unwrap().unwrap();
+        repo.update(
+            agent.id,
+            agent.user_id,
+            None,
+            Some("function think() error('edited') end"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        start(&state).await.unwrap();
+
+        let played = wait_for(&state.db, match_id).await;
+        assert_eq!(played.status, JobStatus::Finished);
+        assert!(played.agents.iter().all(|seat| seat.error.is_none()));
+        assert!(
+            played
+                .agents
+                .iter()
+                .all(|seat| seat.agent_version == Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_agent_fails_match() {
+        let state = setup_state().await;
+        let match_id = create_match(&state.db).await;
+        sqlx::query!("DELETE FROM agents")
+            .execute(&state.db)
+            .await
+            .unwrap();
+
+        start(&state).await.unwrap();
+
+        let failed = wait_for(&state.db, match_id).await;
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert!(failed.error.unwrap().contains("seat 0"));
+
+        let job = JobRepository::new(&state.db)
+            .find_by_id(1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn interrupted_job_is_requeued() {
+        let state = setup_state().await;
+        let match_id = create_match(&state.db).await;
+        let jobs = JobRepository::new(&state.db);
+        let job = jobs.claim_next().await.unwrap().unwrap();
+        MatchRepository::new(&state.db)
+            .set_status(match_id, JobStatus::Running, None)
+            .await
+            .unwrap();
+
+        recover(&state).await.unwrap();
+
+        let job = jobs.find_by_id(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        let found = MatchRepository::new(&state.db)
+            .find_by_id(match_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.status, JobStatus::Queued);
+
+        start(&state).await.unwrap();
+        let played = wait_for(&state.db, match_id).await;
+        assert_eq!(played.status, JobStatus::Finished);
+    }
+
+    #[tokio::test]
+    async fn job_interrupted_too_often_fails() {
+        let state = setup_state().await;
+        let match_id = create_match(&state.db).await;
+        let jobs = JobRepository::new(&state.db);
+        for _ in 1..MAX_ATTEMPTS {
+            let job = jobs.claim_next().await.unwrap().unwrap();
+            jobs.requeue(job.id).await.unwrap();
+        }
+        let job = jobs.claim_next().await.unwrap().unwrap();
+        assert_eq!(job.attempts, MAX_ATTEMPTS);
+
+        recover(&state).await.unwrap();
+
+        let job = jobs.find_by_id(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        let found = MatchRepository::new(&state.db)
+            .find_by_id(match_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.status, JobStatus::Failed);
+        assert!(found.error.is_some());
+    }
+}
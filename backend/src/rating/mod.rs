@@ -0,0 +1,252 @@
+//! Glicko-2 skill ratings.
+//!
+//! Every agent version has a [`Rating`]: an estimate of its skill, how
+//! uncertain that estimate is (the deviation), and how consistently it plays
+//! (the volatility). Each rated match is one Glicko-2 rating period in which
+//! every participant played every other participant, see [`pairwise_scores`].
+//!
+//! The maths follows Mark Glickman's "Example of the Glicko-2 system".
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Rating of an agent version that hasn't played a rated match yet.
+pub const DEFAULT_RATING: f64 = 1500.0;
+
+/// Deviation of an agent version that hasn't played a rated match yet.
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+
+/// Volatility of an agent version that hasn't played a rated match yet.
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+
+/// How much the volatility can change between matches.
+const TAU: f64 = 0.5;
+
+/// Converts between the Glicko and Glicko-2 scales.
+const SCALE: f64 = 173.7178;
+
+/// Precision of the volatility iteration.
+const EPSILON: f64 = 0.000001;
+
+/// A Glicko-2 rating, on the Glicko scale where new players start at 1500.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+impl Rating {
+    /// The rating after one rating period against `results`, pairs of the
+    /// opponent's rating before the period and the score against them: 1 for
+    /// a win, 0.5 for a draw and 0 for a loss.
+    pub fn update(&self, results: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - DEFAULT_RATING) / SCALE;
+        let phi = self.deviation / SCALE;
+
+        // Without games only the uncertainty grows
+        if results.is_empty() {
+            return Rating {
+                deviation: (phi * phi + self.volatility * self.volatility).sqrt() * SCALE,
+                ..*self
+            };
+        }
+
+        let mut inverse_variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in results {
+            let opponent_mu = (opponent.rating - DEFAULT_RATING) / SCALE;
+            let g = g(opponent.deviation / SCALE);
+            let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+            inverse_variance += g * g * expected * (1.0 - expected);
+            improvement += g * (score - expected);
+        }
+        let variance = 1.0 / inverse_variance;
+        let delta = variance * improvement;
+
+        let volatility = self.new_volatility(phi, variance, delta);
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / variance).sqrt();
+        let new_mu = mu + new_phi * new_phi * improvement;
+
+        Rating {
+            rating: new_mu * SCALE + DEFAULT_RATING,
+            deviation: new_phi * SCALE,
+            volatility,
+        }
+    }
+
+    /// Find the new volatility with the Illinois algorithm.
+    fn new_volatility(&self, phi: f64, variance: f64, delta: f64) -> f64 {
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + variance + ex;
+            ex * (delta * delta - phi * phi - variance - ex) / (2.0 * d * d) - (x - a) / (TAU * TAU)
+        };
+
+        let mut low = a;
+        let mut high = if delta * delta > phi * phi + variance {
+            (delta * delta - phi * phi - variance).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+
+        let mut f_low = f(low);
+        let mut f_high = f(high);
+        while (high - low).abs() > EPSILON {
+            let mid = low + (low - high) * f_low / (f_high - f_low);
+            let f_mid = f(mid);
+            if f_mid * f_high <= 0.0 {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.0;
+            }
+            high = mid;
+            f_high = f_mid;
+        }
+
+        (low / 2.0).exp()
+    }
+}
+
+/// Reduces the impact of games against opponents with uncertain ratings.
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+/// How a seat did in a match, compared to the other seats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Standing {
+    /// Whether the seat won the match. Comes first, a winner beats everyone.
+    pub won: bool,
+    /// Whether the agent played without errors, beating any that failed.
+    pub finished: bool,
+    pub score: i64,
+}
+
+/// The score of every seat against every other seat: 1 for a better
+/// standing, 0.5 for the same and 0 for a worse one. `scores[i]` holds pairs
+/// of the other seat and the score of `i` against it.
+pub fn pairwise_scores(standings: &[Standing]) -> Vec<Vec<(usize, f64)>> {
+    standings
+        .iter()
+        .enumerate()
+        .map(|(seat, standing)| {
+            standings
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != seat)
+                .map(|(other, other_standing)| {
+                    let score = match standing.cmp(other_standing) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Less => 0.0,
+                    };
+                    (other, score)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+
+    #[test]
+    fn matches_glickman_example() {
+        let player = rating(1500.0, 200.0);
+        let results = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+
+        let updated = player.update(&results);
+
+        assert!((updated.rating - 1464.06).abs() < 0.01, "{updated:?}");
+        assert!((updated.deviation - 151.52).abs() < 0.01, "{updated:?}");
+        assert!(
+            (updated.volatility - 0.05999).abs() < 0.00001,
+            "{updated:?}"
+        );
+    }
+
+    #[test]
+    fn winning_raises_rating() {
+        let player = Rating::default();
+        let won = player.update(&[(Rating::default(), 1.0)]);
+        let lost = player.update(&[(Rating::default(), 0.0)]);
+        let drew = player.update(&[(Rating::default(), 0.5)]);
+
+        assert!(won.rating > DEFAULT_RATING);
+        assert!(lost.rating < DEFAULT_RATING);
+        assert!((drew.rating - DEFAULT_RATING).abs() < 1e-9);
+        assert!(won.deviation < DEFAULT_DEVIATION);
+    }
+
+    #[test]
+    fn no_games_increases_deviation() {
+        let player = rating(1600.0, 50.0);
+        let updated = player.update(&[]);
+
+        assert_eq!(updated.rating, 1600.0);
+        assert!(updated.deviation > 50.0);
+    }
+
+    #[test]
+    fn pairwise_scores_rank_standings() {
+        let standings = [
+            Standing {
+                won: false,
+                finished: true,
+                score: 3,
+            },
+            Standing {
+                won: true,
+                finished: true,
+                score: 1,
+            },
+            Standing {
+                won: false,
+                finished: false,
+                score: 5,
+            },
+            Standing {
+                won: false,
+                finished: true,
+                score: 3,
+            },
+        ];
+
+        let scores = pairwise_scores(&standings);
+
+        assert_eq!(scores[0], vec![(1, 0.0), (2, 1.0), (3, 0.5)]);
+        assert_eq!(scores[1], vec![(0, 1.0), (2, 1.0), (3, 1.0)]);
+        assert_eq!(scores[2], vec![(0, 0.0), (1, 0.0), (3, 0.0)]);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::models::{Agent, validate_agent_code, validate_agent_name};
+use crate::models::{
+    Agent, AgentDraft, AgentError, AgentVersion, validate_agent_code, validate_agent_draft,
+    validate_agent_name,
+};
 use crate::prelude::*;
 use sqlx::SqlitePool;
 
@@ -13,7 +16,7 @@ impl<'a> AgentRepository<'a> {
         Self { db }
     }
 
-    /// Create a new agent for a user.
+    /// Create a new agent for a user, with its code as version 1.
     pub async fn create(
         &self,
         user_id: i64,
@@ -25,26 +28,19 @@ impl<'a> AgentRepository<'a> {
         validate_agent_name(name)?;
         validate_agent_code(code)?;
 
-        let agent = sqlx::query_as!(
-            Agent,
+        let mut tx = self.db.begin().await?;
+        let id = sqlx::query_scalar!(
             r#"
             INSERT INTO agents (user_id, game_id, name, code)
             VALUES (?, ?, ?, ?)
-            RETURNING 
-                id as "id!",
-                user_id as "user_id!",
-                game_id as "game_id!",
-                name,
-                code,
-                created_at,
-                updated_at
+            RETURNING id as "id!"
             "#,
             user_id,
             game_id,
             name,
             code,
         )
-        .fetch_one(self.db)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| {
             if let sqlx::Error::Database(ref db_err) = e
@@ -58,7 +54,19 @@ impl<'a> AgentRepository<'a> {
             Error::Database(e)
         })?;
 
-        Ok(agent)
+        sqlx::query!(
+            r#"
+            INSERT INTO agent_versions (agent_id, version, code)
+            VALUES (?, 1, ?)
+            "#,
+            id,
+            code,
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.find_by_id(id, user_id).await?.ok_or(Error::NotFound)
     }
 
     /// Find an agent by ID, only if it belongs to the specified user.
@@ -66,16 +74,22 @@ impl<'a> AgentRepository<'a> {
         let agent = sqlx::query_as!(
             Agent,
             r#"
-            SELECT 
-                id as "id!",
-                user_id as "user_id!",
-                game_id as "game_id!",
-                name,
-                code,
-                created_at,
-                updated_at
-            FROM agents
-            WHERE id = ? AND user_id = ?
+            SELECT
+                a.id as "id!",
+                a.user_id as "user_id!",
+                a.game_id as "game_id!",
+                a.name,
+                a.code,
+                a.version,
+                a.draft IS NOT NULL AND a.draft <> a.code as "has_draft!: bool",
+                a.ladder as "ladder: bool",
+                r.rating as "rating?: f64",
+                r.deviation as "rating_deviation?: f64",
+                a.created_at,
+                a.updated_at
+            FROM agents a
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.id = ? AND a.user_id = ?
             "#,
             id,
             user_id,
@@ -86,22 +100,59 @@ impl<'a> AgentRepository<'a> {
         Ok(agent)
     }
 
+    /// Find an agent by ID no matter who owns it, e.g. to play it in a match.
+    /// Never return the result to anyone but the owner.
+    pub async fn find_by_id_any_owner(&self, id: i64) -> Result<Option<Agent>> {
+        let agent = sqlx::query_as!(
+            Agent,
+            r#"
+            SELECT
+                a.id as "id!",
+                a.user_id as "user_id!",
+                a.game_id as "game_id!",
+                a.name,
+                a.code,
+                a.version,
+                a.draft IS NOT NULL AND a.draft <> a.code as "has_draft!: bool",
+                a.ladder as "ladder: bool",
+                r.rating as "rating?: f64",
+                r.deviation as "rating_deviation?: f64",
+                a.created_at,
+                a.updated_at
+            FROM agents a
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(agent)
+    }
+
     /// Find all agents for a user in a specific game.
     pub async fn find_by_user_and_game(&self, user_id: i64, game_id: i64) -> Result<Vec<Agent>> {
         let agents = sqlx::query_as!(
             Agent,
             r#"
-            SELECT 
-                id as "id!",
-                user_id as "user_id!",
-                game_id as "game_id!",
-                name,
-                code,
-                created_at,
-                updated_at
-            FROM agents
-            WHERE user_id = ? AND game_id = ?
-            ORDER BY name
+            SELECT
+                a.id as "id!",
+                a.user_id as "user_id!",
+                a.game_id as "game_id!",
+                a.name,
+                a.code,
+                a.version,
+                a.draft IS NOT NULL AND a.draft <> a.code as "has_draft!: bool",
+                a.ladder as "ladder: bool",
+                r.rating as "rating?: f64",
+                r.deviation as "rating_deviation?: f64",
+                a.created_at,
+                a.updated_at
+            FROM agents a
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.user_id = ? AND a.game_id = ?
+            ORDER BY a.name
             "#,
             user_id,
             game_id,
@@ -112,7 +163,8 @@ impl<'a> AgentRepository<'a> {
         Ok(agents)
     }
 
-    /// Update an agent's name and/or code.
+    /// Update an agent's name and/or code. Changed code is saved as a new
+    /// version.
     pub async fn update(
         &self,
         id: i64,
@@ -130,38 +182,25 @@ impl<'a> AgentRepository<'a> {
             validate_agent_code(code)?;
         }
 
-        // First check the agent exists and belongs to user
-        let existing = self.find_by_id(id, user_id).await?;
-        if existing.is_none() {
-            return Ok(None);
-        }
-        let existing = existing.unwrap();
-
-        // Determine new values
-        let new_name = name.unwrap_or(&existing.name);
-        let new_code = code.unwrap_or(&existing.code);
-
-        let agent = sqlx::query_as!(
-            Agent,
+        // Updating first takes the write lock, so the version is read and
+        // raised by one save at a time, even when saves run concurrently
+        let mut tx = self.db.begin().await?;
+        let updated = sqlx::query!(
             r#"
             UPDATE agents
-            SET name = ?, code = ?, updated_at = datetime('now')
+            SET name = COALESCE(?, name),
+                code = COALESCE(?, code),
+                version = version + (COALESCE(?, code) <> code),
+                updated_at = datetime('now')
             WHERE id = ? AND user_id = ?
-            RETURNING 
-                id as "id!",
-                user_id as "user_id!",
-                game_id as "game_id!",
-                name,
-                code,
-                created_at,
-                updated_at
             "#,
-            new_name,
-            new_code,
+            name,
+            code,
+            code,
             id,
             user_id,
         )
-        .fetch_one(self.db)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             if let sqlx::Error::Database(ref db_err) = e
@@ -169,13 +208,204 @@ impl<'a> AgentRepository<'a> {
             {
                 return Error::Conflict(format!(
                     "An agent with name '{}' already exists for this game",
-                    new_name
+                    name.unwrap_or_default()
                 ));
             }
             Error::Database(e)
         })?;
+        if updated.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        // Only adds a row when the code changed, the current version is
+        // stored already otherwise
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO agent_versions (agent_id, version, code)
+            SELECT id, version, code
+            FROM agents
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.find_by_id(id, user_id).await
+    }
+
+    /// Find the draft of an agent, only if it belongs to the specified user.
+    pub async fn find_draft(&self, id: i64, user_id: i64) -> Result<Option<AgentDraft>> {
+        let draft = sqlx::query_as!(
+            AgentDraft,
+            r#"
+            SELECT
+                id as "agent_id!",
+                draft as "code!",
+                draft_updated_at as "updated_at!"
+            FROM agents
+            WHERE id = ? AND user_id = ? AND draft IS NOT NULL
+            "#,
+            id,
+            user_id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(draft)
+    }
+
+    /// Save a draft of an agent's code. Drafts are not checked for errors, so
+    /// unfinished code can be saved. Returns `None` if the agent doesn't
+    /// exist or belongs to someone else.
+    pub async fn save_draft(
+        &self,
+        id: i64,
+        user_id: i64,
+        code: &str,
+    ) -> Result<Option<AgentDraft>> {
+        validate_agent_draft(code)?;
+
+        let draft = sqlx::query_as!(
+            AgentDraft,
+            r#"
+            UPDATE agents
+            SET draft = ?, draft_updated_at = datetime('now')
+            WHERE id = ? AND user_id = ?
+            RETURNING
+                id as "agent_id!",
+                draft as "code!",
+                draft_updated_at as "updated_at!"
+            "#,
+            code,
+            id,
+            user_id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(draft)
+    }
+
+    /// Throw away the draft of an agent. Returns whether there was one.
+    pub async fn discard_draft(&self, id: i64, user_id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE agents
+            SET draft = NULL, draft_updated_at = NULL
+            WHERE id = ? AND user_id = ? AND draft IS NOT NULL
+            "#,
+            id,
+            user_id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Check the draft of an agent and save it as the agent's code, which
+    /// makes it a new version. The draft is removed once published.
+    pub async fn publish_draft(&self, id: i64, user_id: i64) -> Result<Option<Agent>> {
+        let Some(draft) = self.find_draft(id, user_id).await? else {
+            return match self.find_by_id(id, user_id).await? {
+                Some(_) => Err(AgentError::NoDraft.into()),
+                None => Ok(None),
+            };
+        };
+
+        let Some(agent) = self.update(id, user_id, None, Some(&draft.code)).await? else {
+            return Ok(None);
+        };
+
+        // Keep the draft if it was saved again in the meantime
+        sqlx::query!(
+            r#"
+            UPDATE agents
+            SET draft = NULL, draft_updated_at = NULL
+            WHERE id = ? AND draft = ?
+            "#,
+            id,
+            draft.code,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(Some(Agent {
+            has_draft: false,
+            ..agent
+        }))
+    }
+
+    /// Put an agent (must belong to the user) on its game's ladder, or take
+    /// it off.
+    pub async fn set_ladder(&self, id: i64, user_id: i64, ladder: bool) -> Result<Option<Agent>> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE agents
+            SET ladder = ?
+            WHERE id = ? AND user_id = ?
+            "#,
+            ladder,
+            id,
+            user_id,
+        )
+        .execute(self.db)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(None);
+        }
 
-        Ok(Some(agent))
+        self.find_by_id(id, user_id).await
+    }
+
+    /// List all versions of an agent, newest first. Check who owns the agent
+    /// before showing them to anyone.
+    pub async fn find_versions(&self, agent_id: i64) -> Result<Vec<AgentVersion>> {
+        let versions = sqlx::query_as!(
+            AgentVersion,
+            r#"
+            SELECT agent_id, version, code, created_at
+            FROM agent_versions
+            WHERE agent_id = ?
+            ORDER BY version DESC
+            "#,
+            agent_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(versions)
+    }
+
+    /// Find one version of an agent. Check who owns the agent before showing
+    /// it to anyone.
+    pub async fn find_version(&self, agent_id: i64, version: i64) -> Result<Option<AgentVersion>> {
+        let version = sqlx::query_as!(
+            AgentVersion,
+            r#"
+            SELECT agent_id, version, code, created_at
+            FROM agent_versions
+            WHERE agent_id = ? AND version = ?
+            "#,
+            agent_id,
+            version,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(version)
+    }
+
+    /// Make the code of an older version the current code again, saved as a
+    /// new version so the history is kept.
+    pub async fn restore(&self, id: i64, user_id: i64, version: i64) -> Result<Option<Agent>> {
+        let Some(restored) = self.find_version(id, version).await? else {
+            return Ok(None);
+        };
+
+        self.update(id, user_id, None, Some(&restored.code)).await
     }
 
     /// Delete an agent by ID, only if it belongs to the specified user.
@@ -267,6 +497,25 @@ mod tests {
         assert!(not_found.is_none());
     }
 
+    #[tokio::test]
+    async fn test_find_by_id_any_owner() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Agent 1", "-- agent 1")
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id_any_owner(created.id).await.unwrap();
+        assert_eq!(found.map(|a| a.user_id), Some(user_id));
+
+        let not_found = repo.find_by_id_any_owner(created.id + 1).await.unwrap();
+        assert!(not_found.is_none());
+    }
+
     #[tokio::test]
     async fn test_find_by_user_and_game() {
         let pool = setup_test_db().await;
@@ -317,6 +566,239 @@ mod tests {
         assert_eq!(updated.code, "-- new code");
     }
 
+    #[tokio::test]
+    async fn test_update_code_creates_version() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Versioned", "-- v1")
+            .await
+            .unwrap();
+        assert_eq!(created.version, 1);
+
+        let updated = repo
+            .update(created.id, user_id, None, Some("-- v2"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.version, 2);
+
+        // Renaming or saving the same code keeps the version
+        let renamed = repo
+            .update(created.id, user_id, Some("Renamed"), Some("-- v2"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.version, 2);
+
+        let versions = repo.find_versions(created.id).await.unwrap();
+        let codes: Vec<&str> = versions.iter().map(|v| v.code.as_str()).collect();
+        assert_eq!(codes, vec!["-- v2", "-- v1"]);
+
+        let first = repo.find_version(created.id, 1).await.unwrap().unwrap();
+        assert_eq!(first.code, "-- v1");
+        assert!(repo.find_version(created.id, 3).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_updates_create_distinct_versions() {
+        // In-memory databases share one cache between connections, so use a
+        // file to get real locking between the two saves
+        let path = std::env::temp_dir().join(format!(
+            "agent-versions-{}-{}.db",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Versioned", "-- v1")
+            .await
+            .unwrap();
+
+        let (first, second) = tokio::join!(
+            repo.update(created.id, user_id, None, Some("-- first")),
+            repo.update(created.id, user_id, None, Some("-- second")),
+        );
+        let mut versions = vec![
+            first.unwrap().unwrap().version,
+            second.unwrap().unwrap().version,
+        ];
+        versions.sort();
+        assert_eq!(versions, vec![2, 3]);
+        assert_eq!(repo.find_versions(created.id).await.unwrap().len(), 3);
+
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_restore_version() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Versioned", "-- v1")
+            .await
+            .unwrap();
+        repo.update(created.id, user_id, None, Some("-- v2"))
+            .await
+            .unwrap();
+
+        let restored = repo.restore(created.id, user_id, 1).await.unwrap().unwrap();
+        assert_eq!(restored.code, "-- v1");
+        assert_eq!(restored.version, 3);
+        assert_eq!(repo.find_versions(created.id).await.unwrap().len(), 3);
+
+        // Unknown versions and other users' agents can't be restored
+        assert!(
+            repo.restore(created.id, user_id, 9)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repo.restore(created.id, user_id + 999, 1)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_broken_draft() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Drafty", "-- v1")
+            .await
+            .unwrap();
+        assert!(!created.has_draft);
+        assert!(
+            repo.find_draft(created.id, user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let draft = repo
+            .save_draft(created.id, user_id, "function think(")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(draft.code, "function think(");
+
+        let agent = repo.find_by_id(created.id, user_id).await.unwrap().unwrap();
+        assert!(agent.has_draft);
+        assert_eq!(agent.code, "-- v1");
+
+        // Other users can't see or save drafts
+        assert!(
+            repo.find_draft(created.id, user_id + 999)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repo.save_draft(created.id, user_id + 999, "-- mine")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        assert!(repo.discard_draft(created.id, user_id).await.unwrap());
+        assert!(!repo.discard_draft(created.id, user_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_publish_draft() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Drafty", "-- v1")
+            .await
+            .unwrap();
+
+        // Broken drafts are kept when publishing fails
+        repo.save_draft(created.id, user_id, "function think(")
+            .await
+            .unwrap();
+        let result = repo.publish_draft(created.id, user_id).await;
+        assert!(matches!(
+            result,
+            Err(Error::Agent(AgentError::InvalidLuaSyntax(_)))
+        ));
+        assert!(
+            repo.find_draft(created.id, user_id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        repo.save_draft(created.id, user_id, "-- v2").await.unwrap();
+        let published = repo
+            .publish_draft(created.id, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(published.code, "-- v2");
+        assert_eq!(published.version, 2);
+        assert!(!published.has_draft);
+        assert!(
+            repo.find_draft(created.id, user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        assert!(matches!(
+            repo.publish_draft(created.id, user_id).await,
+            Err(Error::Agent(AgentError::NoDraft))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_draft_same_as_code_is_not_a_change() {
+        let pool = setup_test_db().await;
+        let user_id = create_test_user(&pool).await;
+        let game_id = get_test_game_id(&pool).await;
+
+        let repo = AgentRepository::new(&pool);
+        let created = repo
+            .create(user_id, game_id, "Drafty", "-- v1")
+            .await
+            .unwrap();
+        repo.save_draft(created.id, user_id, "-- v1").await.unwrap();
+
+        let agent = repo.find_by_id(created.id, user_id).await.unwrap().unwrap();
+        assert!(!agent.has_draft);
+    }
+
     #[tokio::test]
     async fn test_delete_agent() {
         let pool = setup_test_db().await;
@@ -0,0 +1,107 @@
+use crate::models::{Difficulty, HouseBot};
+use crate::prelude::*;
+use sqlx::SqlitePool;
+
+/// Repository for house bot database operations.
+pub struct BotRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> BotRepository<'a> {
+    /// Create a new BotRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// ID of the system user that owns the house bots.
+    pub async fn owner_id(&self) -> Result<i64> {
+        let id = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id!"
+            FROM users
+            WHERE system
+            ORDER BY id
+            LIMIT 1
+            "#,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Find the house bots of a game, easiest first.
+    pub async fn find_by_game(&self, game_id: i64) -> Result<Vec<HouseBot>> {
+        let bots = sqlx::query_as!(
+            HouseBot,
+            r#"
+            SELECT
+                a.id as "agent_id!",
+                a.game_id,
+                a.name,
+                a.difficulty as "difficulty!: Difficulty",
+                a.code,
+                a.version,
+                r.rating as "rating?: f64",
+                r.deviation as "rating_deviation?: f64"
+            FROM agents a
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.game_id = ? AND a.difficulty IS NOT NULL
+            ORDER BY CASE a.difficulty WHEN 'easy' THEN 0 WHEN 'medium' THEN 1 ELSE 2 END
+            "#,
+            game_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(bots)
+    }
+
+    /// Find the house bot of a game with the given difficulty.
+    pub async fn find_by_difficulty(
+        &self,
+        game_id: i64,
+        difficulty: Difficulty,
+    ) -> Result<Option<HouseBot>> {
+        let bot = sqlx::query_as!(
+            HouseBot,
+            r#"
+            SELECT
+                a.id as "agent_id!",
+                a.game_id,
+                a.name,
+                a.difficulty as "difficulty!: Difficulty",
+                a.code,
+                a.version,
+                r.rating as "rating?: f64",
+                r.deviation as "rating_deviation?: f64"
+            FROM agents a
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.game_id = ? AND a.difficulty = ?
+            "#,
+            game_id,
+            difficulty,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(bot)
+    }
+
+    /// Mark an agent as the house bot of its game with the given difficulty.
+    pub async fn set_difficulty(&self, agent_id: i64, difficulty: Difficulty) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE agents
+            SET difficulty = ?
+            WHERE id = ?
+            "#,
+            difficulty,
+            agent_id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+}
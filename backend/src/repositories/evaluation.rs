@@ -0,0 +1,528 @@
+use crate::evaluation::{MAX_PENDING, Played};
+use crate::models::{Evaluation, EvaluationError, EvaluationSeed, EvaluationSummary, JobStatus};
+use crate::prelude::*;
+use sqlx::SqlitePool;
+
+/// An agent version taking part in an evaluation.
+#[derive(Debug, Clone, Copy)]
+pub struct EvaluatedAgent {
+    pub agent_id: i64,
+    pub version: i64,
+}
+
+/// Repository for evaluation database operations.
+pub struct EvaluationRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> EvaluationRepository<'a> {
+    /// Create a new EvaluationRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Create an evaluation and queue a job for each of its seeds. Fails if
+    /// the user already has [`MAX_PENDING`] evaluations waiting to be played.
+    pub async fn create(
+        &self,
+        game_id: i64,
+        user_id: i64,
+        a: EvaluatedAgent,
+        b: EvaluatedAgent,
+        seed: i64,
+        seeds: i64,
+    ) -> Result<Evaluation> {
+        let mut tx = self.db.begin().await?;
+
+        // Counted in the insert itself, so concurrent requests can't both
+        // slip under the limit
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO evaluations (
+                game_id, user_id, agent_a_id, agent_a_version, agent_b_id, agent_b_version,
+                seed, seeds
+            )
+            SELECT ?, ?, ?, ?, ?, ?, ?, ?
+            WHERE (
+                SELECT COUNT(*) FROM evaluations
+                WHERE user_id = ? AND status IN ('queued', 'running')
+            ) < ?
+            RETURNING id as "id!"
+            "#,
+            game_id,
+            user_id,
+            a.agent_id,
+            a.version,
+            b.agent_id,
+            b.version,
+            seed,
+            seeds,
+            user_id,
+            MAX_PENDING,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(EvaluationError::TooManyPending(MAX_PENDING))?;
+
+        for seed in seed..seed + seeds {
+            let seed_id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO evaluation_seeds (evaluation_id, seed)
+                VALUES (?, ?)
+                RETURNING id as "id!"
+                "#,
+                id,
+                seed,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO jobs (kind, target_id)
+                VALUES ('evaluation_seed', ?)
+                "#,
+                seed_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        self.find_by_id(id).await?.ok_or(Error::NotFound)
+    }
+
+    /// Find an evaluation by ID.
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Evaluation>> {
+        let evaluation = sqlx::query_as!(
+            Evaluation,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                agent_a_id,
+                agent_a_version,
+                agent_b_id,
+                agent_b_version,
+                seed,
+                seeds,
+                status as "status: JobStatus",
+                error,
+                wins,
+                draws,
+                losses,
+                mean_score_diff,
+                ci_low,
+                ci_high,
+                created_at,
+                finished_at
+            FROM evaluations
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(evaluation)
+    }
+
+    /// Find all evaluations a user asked for, newest first.
+    pub async fn find_by_user(&self, user_id: i64) -> Result<Vec<Evaluation>> {
+        let evaluations = sqlx::query_as!(
+            Evaluation,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                agent_a_id,
+                agent_a_version,
+                agent_b_id,
+                agent_b_version,
+                seed,
+                seeds,
+                status as "status: JobStatus",
+                error,
+                wins,
+                draws,
+                losses,
+                mean_score_diff,
+                ci_low,
+                ci_high,
+                created_at,
+                finished_at
+            FROM evaluations
+            WHERE user_id = ?
+            ORDER BY id DESC
+            "#,
+            user_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(evaluations)
+    }
+
+    /// Mark a queued evaluation as running, once the first of its seeds is
+    /// played.
+    pub async fn start(&self, id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE evaluations
+            SET status = 'running'
+            WHERE id = ? AND status = 'queued'
+            "#,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find a seed of an evaluation by ID.
+    pub async fn find_seed(&self, id: i64) -> Result<Option<EvaluationSeed>> {
+        let seed = sqlx::query_as!(
+            EvaluationSeed,
+            r#"
+            SELECT
+                id as "id!",
+                evaluation_id,
+                seed,
+                status as "status: JobStatus"
+            FROM evaluation_seeds
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(seed)
+    }
+
+    /// Set the status of a seed. A failed seed fails its whole evaluation,
+    /// which can't be summed up without it.
+    pub async fn set_seed_status(
+        &self,
+        id: i64,
+        status: JobStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE evaluation_seeds
+            SET status = ?
+            WHERE id = ?
+            "#,
+            status,
+            id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if status == JobStatus::Failed {
+            sqlx::query!(
+                r#"
+                UPDATE evaluations
+                SET status = 'failed', error = ?
+                WHERE id = (SELECT evaluation_id FROM evaluation_seeds WHERE id = ?)
+                    AND status <> 'finished'
+                "#,
+                error,
+                id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Store the two matches played on a seed and mark it as finished.
+    pub async fn finish_seed(&self, id: i64, [first, second]: [Played; 2]) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE evaluation_seeds
+            SET
+                status = 'finished',
+                first_a_won = ?,
+                first_score_diff = ?,
+                second_a_won = ?,
+                second_score_diff = ?
+            WHERE id = ?
+            "#,
+            first.a_won,
+            first.score_diff,
+            second.a_won,
+            second.score_diff,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find the matches of every finished seed of an evaluation, in seed
+    /// order.
+    pub async fn find_played(&self, evaluation_id: i64) -> Result<Vec<[Played; 2]>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                first_a_won as "first_a_won: bool",
+                first_score_diff as "first_score_diff!",
+                second_a_won as "second_a_won: bool",
+                second_score_diff as "second_score_diff!"
+            FROM evaluation_seeds
+            WHERE evaluation_id = ? AND status = 'finished'
+            ORDER BY seed
+            "#,
+            evaluation_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                [
+                    Played {
+                        a_won: row.first_a_won,
+                        score_diff: row.first_score_diff,
+                    },
+                    Played {
+                        a_won: row.second_a_won,
+                        score_diff: row.second_score_diff,
+                    },
+                ]
+            })
+            .collect())
+    }
+
+    /// Store the results of a played evaluation and mark it as finished.
+    pub async fn finish(&self, id: i64, summary: &EvaluationSummary) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE evaluations
+            SET
+                status = 'finished',
+                error = NULL,
+                wins = ?,
+                draws = ?,
+                losses = ?,
+                mean_score_diff = ?,
+                ci_low = ?,
+                ci_high = ?,
+                finished_at = datetime('now')
+            WHERE id = ?
+            "#,
+            summary.wins,
+            summary.draws,
+            summary.losses,
+            summary.mean_score_diff,
+            summary.ci_low,
+            summary.ci_high,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::JobKind;
+    use crate::repositories::{AgentRepository, GameRepository, JobRepository, UserRepository};
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    /// Create a snake agent, returning the game, user and agent IDs.
+    async fn create_agent(pool: &SqlitePool) -> (i64, i64, i64) {
+        let user = UserRepository::new(pool)
+            .create("testuser", "TestPass123!", false)
+            .await
+            .unwrap();
+        let game = GameRepository::new(pool)
+            .find_by_name("snake")
+            .await
+            .unwrap()
+            .unwrap();
+        let agent = AgentRepository::new(pool)
+            .create(user.id, game.id, "Slither", "function think() end")
+            .await
+            .unwrap();
+        (game.id, user.id, agent.id)
+    }
+
+    fn summary() -> EvaluationSummary {
+        EvaluationSummary {
+            wins: 2,
+            draws: 1,
+            losses: 1,
+            mean_score_diff: 0.5,
+            ci_low: -0.25,
+            ci_high: 1.25,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_queues_job_per_seed() {
+        let pool = setup_test_db().await;
+        let (game_id, user_id, agent_id) = create_agent(&pool).await;
+        let agent = EvaluatedAgent {
+            agent_id,
+            version: 1,
+        };
+
+        let repo = EvaluationRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, agent, agent, 5, 10)
+            .await
+            .unwrap();
+        assert_eq!(created.status, JobStatus::Queued);
+        assert_eq!(created.seed, 5);
+        assert_eq!(created.seeds, 10);
+        assert_eq!(created.wins, None);
+
+        // One job per seed, in seed order
+        let jobs = JobRepository::new(&pool);
+        for offset in 0..10 {
+            let job = jobs.claim_next().await.unwrap().unwrap();
+            assert_eq!(job.kind, JobKind::EvaluationSeed);
+            let seed = repo.find_seed(job.target_id).await.unwrap().unwrap();
+            assert_eq!(seed.evaluation_id, created.id);
+            assert_eq!(seed.seed, 5 + offset);
+            assert_eq!(seed.status, JobStatus::Queued);
+        }
+        assert!(jobs.claim_next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_limits_pending_evaluations() {
+        let pool = setup_test_db().await;
+        let (game_id, user_id, agent_id) = create_agent(&pool).await;
+        let agent = EvaluatedAgent {
+            agent_id,
+            version: 1,
+        };
+
+        let repo = EvaluationRepository::new(&pool);
+        for _ in 0..MAX_PENDING {
+            repo.create(game_id, user_id, agent, agent, 0, 2)
+                .await
+                .unwrap();
+        }
+        let result = repo.create(game_id, user_id, agent, agent, 0, 2).await;
+        assert!(matches!(
+            result,
+            Err(Error::Evaluation(EvaluationError::TooManyPending(_)))
+        ));
+
+        // Finished evaluations no longer count
+        repo.finish(1, &summary()).await.unwrap();
+        repo.create(game_id, user_id, agent, agent, 0, 2)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_seed_fails_evaluation() {
+        let pool = setup_test_db().await;
+        let (game_id, user_id, agent_id) = create_agent(&pool).await;
+        let agent = EvaluatedAgent {
+            agent_id,
+            version: 1,
+        };
+        let repo = EvaluationRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, agent, agent, 0, 2)
+            .await
+            .unwrap();
+
+        let job = JobRepository::new(&pool)
+            .claim_next()
+            .await
+            .unwrap()
+            .unwrap();
+        repo.set_seed_status(job.target_id, JobStatus::Failed, Some("broken"))
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.status, JobStatus::Failed);
+        assert_eq!(found.error.as_deref(), Some("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_find_played_returns_finished_seeds() {
+        let pool = setup_test_db().await;
+        let (game_id, user_id, agent_id) = create_agent(&pool).await;
+        let agent = EvaluatedAgent {
+            agent_id,
+            version: 1,
+        };
+        let repo = EvaluationRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, agent, agent, 0, 2)
+            .await
+            .unwrap();
+
+        let won = Played {
+            a_won: Some(true),
+            score_diff: 2.0,
+        };
+        let drawn = Played {
+            a_won: None,
+            score_diff: 0.0,
+        };
+        repo.finish_seed(1, [won, drawn]).await.unwrap();
+
+        let played = repo.find_played(created.id).await.unwrap();
+        assert_eq!(played, vec![[won, drawn]]);
+        let seed = repo.find_seed(1).await.unwrap().unwrap();
+        assert_eq!(seed.status, JobStatus::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_finish_stores_summary() {
+        let pool = setup_test_db().await;
+        let (game_id, user_id, agent_id) = create_agent(&pool).await;
+        let agent = EvaluatedAgent {
+            agent_id,
+            version: 1,
+        };
+        let repo = EvaluationRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, agent, agent, 0, 2)
+            .await
+            .unwrap();
+
+        repo.finish(created.id, &summary()).await.unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.status, JobStatus::Finished);
+        assert_eq!(found.wins, Some(2));
+        assert_eq!(found.draws, Some(1));
+        assert_eq!(found.losses, Some(1));
+        assert_eq!(found.mean_score_diff, Some(0.5));
+        assert_eq!(found.ci_low, Some(-0.25));
+        assert_eq!(found.ci_high, Some(1.25));
+        assert!(found.finished_at.is_some());
+        assert_eq!(repo.find_by_user(user_id).await.unwrap().len(), 1);
+    }
+}
@@ -18,7 +18,7 @@ impl<'a> GameRepository<'a> {
         let games = sqlx::query_as!(
             Game,
             r#"
-            SELECT id as "id!", name, display_name
+            SELECT id as "id!", name, display_name, agent_memory_limit
             FROM games
             ORDER BY name
             "#,
@@ -29,12 +29,29 @@ impl<'a> GameRepository<'a> {
         Ok(games)
     }
 
+    /// Find a game by ID.
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Game>> {
+        let game = sqlx::query_as!(
+            Game,
+            r#"
+            SELECT id as "id!", name, display_name, agent_memory_limit
+            FROM games
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(game)
+    }
+
     /// Find a game by its unique name.
     pub async fn find_by_name(&self, name: &str) -> Result<Option<Game>> {
         let game = sqlx::query_as!(
             Game,
             r#"
-            SELECT id as "id!", name, display_name
+            SELECT id as "id!", name, display_name, agent_memory_limit
             FROM games
             WHERE name = ?
             "#,
@@ -87,8 +104,9 @@ mod tests {
             .find_by_name("robotsumo")
             .await
             .expect("Failed to find game");
-        assert!(game.is_some());
-        assert_eq!(game.unwrap().display_name, "Robot Sumo");
+        let game = game.expect("robotsumo should exist");
+        assert_eq!(game.display_name, "Robot Sumo");
+        assert_eq!(game.agent_memory_limit, 16 * 1024 * 1024);
 
         let game = repo
             .find_by_name("nonexistent")
@@ -96,4 +114,16 @@ mod tests {
             .expect("Failed to find game");
         assert!(game.is_none());
     }
+
+    #[tokio::test]
+    async fn test_find_by_id() {
+        let pool = setup_test_db().await;
+        let repo = GameRepository::new(&pool);
+
+        let snake = repo.find_by_name("snake").await.unwrap().unwrap();
+        let game = repo.find_by_id(snake.id).await.unwrap();
+        assert_eq!(game.map(|g| g.name), Some("snake".to_string()));
+
+        assert!(repo.find_by_id(999).await.unwrap().is_none());
+    }
 }
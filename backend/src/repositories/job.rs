@@ -0,0 +1,245 @@
+use crate::models::{Job, JobKind, JobStatus};
+use crate::prelude::*;
+use sqlx::SqlitePool;
+
+/// Repository for background job database operations.
+pub struct JobRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> JobRepository<'a> {
+    /// Create a new JobRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Add a job to the end of the queue.
+    pub async fn enqueue(&self, kind: JobKind, target_id: i64) -> Result<Job> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            INSERT INTO jobs (kind, target_id)
+            VALUES (?, ?)
+            RETURNING
+                id as "id!",
+                kind as "kind: JobKind",
+                target_id,
+                status as "status: JobStatus",
+                attempts,
+                error,
+                created_at,
+                started_at,
+                finished_at
+            "#,
+            kind,
+            target_id,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Find a job by ID.
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Job>> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT
+                id as "id!",
+                kind as "kind: JobKind",
+                target_id,
+                status as "status: JobStatus",
+                attempts,
+                error,
+                created_at,
+                started_at,
+                finished_at
+            FROM jobs
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Take the oldest queued job and mark it as running. Safe to call from
+    /// several workers at once, every job is handed out only once.
+    pub async fn claim_next(&self) -> Result<Option<Job>> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            UPDATE jobs
+            SET status = 'running', attempts = attempts + 1, started_at = datetime('now')
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE status = 'queued'
+                ORDER BY id
+                LIMIT 1
+            )
+            RETURNING
+                id as "id!",
+                kind as "kind: JobKind",
+                target_id,
+                status as "status: JobStatus",
+                attempts,
+                error,
+                created_at,
+                started_at,
+                finished_at
+            "#,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Find all jobs marked as running.
+    pub async fn find_running(&self) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT
+                id as "id!",
+                kind as "kind: JobKind",
+                target_id,
+                status as "status: JobStatus",
+                attempts,
+                error,
+                created_at,
+                started_at,
+                finished_at
+            FROM jobs
+            WHERE status = 'running'
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    /// Put a job back in the queue, keeping its place.
+    pub async fn requeue(&self, id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = 'queued', started_at = NULL
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark a job as done.
+    pub async fn finish(&self, id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = 'finished', error = NULL, finished_at = datetime('now')
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark a job as failed for good.
+    pub async fn fail(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = 'failed', error = ?, finished_at = datetime('now')
+            WHERE id = ?
+            "#,
+            error,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_and_claim_in_order() {
+        let pool = setup_test_db().await;
+        let repo = JobRepository::new(&pool);
+
+        let first = repo.enqueue(JobKind::Match, 1).await.unwrap();
+        let second = repo.enqueue(JobKind::Match, 2).await.unwrap();
+        assert_eq!(first.status, JobStatus::Queued);
+        assert_eq!(first.attempts, 0);
+
+        let claimed = repo.claim_next().await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+        assert!(claimed.started_at.is_some());
+
+        let claimed = repo.claim_next().await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+
+        assert!(repo.claim_next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finish_and_fail() {
+        let pool = setup_test_db().await;
+        let repo = JobRepository::new(&pool);
+        let first = repo.enqueue(JobKind::Match, 1).await.unwrap();
+        let second = repo.enqueue(JobKind::Match, 2).await.unwrap();
+
+        repo.finish(first.id).await.unwrap();
+        repo.fail(second.id, "broken").await.unwrap();
+
+        let first = repo.find_by_id(first.id).await.unwrap().unwrap();
+        assert_eq!(first.status, JobStatus::Finished);
+        assert!(first.finished_at.is_some());
+
+        let second = repo.find_by_id(second.id).await.unwrap().unwrap();
+        assert_eq!(second.status, JobStatus::Failed);
+        assert_eq!(second.error.as_deref(), Some("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_requeue_running_job() {
+        let pool = setup_test_db().await;
+        let repo = JobRepository::new(&pool);
+        let job = repo.enqueue(JobKind::Match, 1).await.unwrap();
+        repo.claim_next().await.unwrap();
+
+        let running = repo.find_running().await.unwrap();
+        assert_eq!(running.len(), 1);
+
+        repo.requeue(job.id).await.unwrap();
+        assert!(repo.find_running().await.unwrap().is_empty());
+
+        let claimed = repo.claim_next().await.unwrap().unwrap();
+        assert_eq!(claimed.id, job.id);
+        assert_eq!(claimed.attempts, 2);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::models::LadderStatus;
+use crate::prelude::*;
+use crate::repositories::insert_match;
+use sqlx::SqlitePool;
+
+/// An agent on the ladder that can be paired, see
+/// [`LadderRepository::find_candidates`].
+#[derive(Debug, Clone)]
+pub struct LadderCandidate {
+    pub agent_id: i64,
+    /// Owner of the agent
+    pub user_id: i64,
+    pub game_id: i64,
+    pub game_name: String,
+    /// Current version of the agent, the one that plays
+    pub version: i64,
+    /// Rating of the current version, `None` before its first rated match
+    pub rating: Option<f64>,
+}
+
+/// Repository for the ladder scheduler's database operations.
+pub struct LadderRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> LadderRepository<'a> {
+    /// Create a new LadderRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// The state of the scheduler, with the settings it runs with.
+    pub async fn find_status(&self, config: &Config) -> Result<LadderStatus> {
+        let row = sqlx::query!(
+            r#"
+            SELECT paused as "paused: bool", last_run_at
+            FROM ladder_scheduler
+            WHERE id = 1
+            "#,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(LadderStatus {
+            paused: row.paused,
+            last_run_at: row.last_run_at,
+            interval_secs: config.ladder_interval_secs,
+            rating_window: config.ladder_rating_window,
+            daily_match_cap: config.ladder_daily_match_cap,
+        })
+    }
+
+    /// Pause the scheduler, or let it run again.
+    pub async fn set_paused(&self, paused: bool) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE ladder_scheduler
+            SET paused = ?
+            WHERE id = 1
+            "#,
+            paused,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find the agents on the ladder that can play now: those without a
+    /// ladder match waiting to be played, and with fewer than `daily_cap`
+    /// ladder matches today. Sorted by game.
+    pub async fn find_candidates(&self, daily_cap: i64) -> Result<Vec<LadderCandidate>> {
+        let candidates = sqlx::query_as!(
+            LadderCandidate,
+            r#"
+            SELECT
+                a.id as "agent_id!",
+                a.user_id,
+                a.game_id,
+                g.name as game_name,
+                a.version,
+                r.rating as "rating?: f64"
+            FROM agents a
+            JOIN games g ON g.id = a.game_id
+            LEFT JOIN ratings r ON r.agent_id = a.id AND r.agent_version = a.version
+            WHERE a.ladder
+              AND NOT EXISTS (
+                SELECT 1
+                FROM match_agents ma
+                JOIN matches m ON m.id = ma.match_id
+                WHERE ma.agent_id = a.id AND m.ladder AND m.status IN ('queued', 'running')
+              )
+              AND (
+                SELECT COUNT(*)
+                FROM match_agents ma
+                JOIN matches m ON m.id = ma.match_id
+                WHERE ma.agent_id = a.id AND m.ladder AND date(m.created_at) = date('now')
+              ) < ?
+            ORDER BY a.game_id, a.id
+            "#,
+            daily_cap,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(candidates)
+    }
+
+    /// Queue a rated ladder match between the current versions of two agents,
+    /// started by the owner of the first. Returns the ID of the match.
+    pub async fn queue_match(
+        &self,
+        first: &LadderCandidate,
+        second: &LadderCandidate,
+        seed: i64,
+    ) -> Result<i64> {
+        let mut tx = self.db.begin().await?;
+
+        let seats = [
+            (first.agent_id, Some(first.version)),
+            (second.agent_id, Some(second.version)),
+        ];
+        let id = insert_match(&mut tx, first.game_id, first.user_id, seed, &seats, true).await?;
+        sqlx::query!(
+            r#"
+            UPDATE matches
+            SET ladder = TRUE
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Record that the scheduler has just paired agents.
+    pub async fn finish_run(&self) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE ladder_scheduler
+            SET last_run_at = datetime('now')
+            WHERE id = 1
+            "#,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,741 @@
+use super::rating;
+use crate::models::{
+    JobStatus, LogLine, Match, MatchAgent, MatchOutcome, MemorySnapshot, Termination,
+};
+use crate::prelude::*;
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// A row of the `matches` table, without the participants.
+struct MatchRow {
+    id: i64,
+    game_id: i64,
+    user_id: i64,
+    seed: i64,
+    game_version: Option<String>,
+    lua_version: Option<String>,
+    rated: bool,
+    ladder: bool,
+    status: JobStatus,
+    error: Option<String>,
+    winner: Option<i64>,
+    ticks: Option<i64>,
+    termination: Option<Termination>,
+    created_at: String,
+    finished_at: Option<String>,
+}
+
+/// Repository for match database operations.
+pub struct MatchRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> MatchRepository<'a> {
+    /// Create a new MatchRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Create a match and queue a job to play it. `agent_ids` are in seat
+    /// order, each agent plays with its latest version. Only `rated` matches
+    /// change the ratings of the agents.
+    pub async fn create(
+        &self,
+        game_id: i64,
+        user_id: i64,
+        seed: i64,
+        agent_ids: &[i64],
+        rated: bool,
+    ) -> Result<Match> {
+        let seats: Vec<(i64, Option<i64>)> = agent_ids.iter().map(|&id| (id, None)).collect();
+
+        let mut tx = self.db.begin().await?;
+        let id = insert_match(&mut tx, game_id, user_id, seed, &seats, rated).await?;
+        tx.commit().await?;
+
+        self.find_by_id(id).await?.ok_or(Error::NotFound)
+    }
+
+    /// Update the status of a match, with the reason when it failed. A
+    /// finished match keeps its result and status.
+    pub async fn set_status(&self, id: i64, status: JobStatus, error: Option<&str>) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE matches
+            SET status = ?, error = ?
+            WHERE id = ? AND status <> 'finished'
+            "#,
+            status,
+            error,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store the result of a played match, mark it as finished and update
+    /// the ratings, all at once. Does nothing for a match that is already
+    /// finished, so storing a result twice keeps the first one.
+    pub async fn finish(&self, id: i64, outcome: &MatchOutcome) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        let winner = outcome.winner.map(|seat| seat as i64);
+        let ticks = i64::from(outcome.ticks);
+        let finished = sqlx::query!(
+            r#"
+            UPDATE matches
+            SET status = 'finished', error = NULL, winner = ?, ticks = ?, termination = ?,
+                game_version = ?, lua_version = ?, finished_at = datetime('now')
+            WHERE id = ? AND status <> 'finished'
+            "#,
+            winner,
+            ticks,
+            outcome.termination,
+            outcome.game_version,
+            outcome.lua_version,
+            id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if finished.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        for (seat, (score, error)) in outcome.scores.iter().zip(&outcome.errors).enumerate() {
+            let seat = seat as i64;
+            let score = i64::from(*score);
+            let error = error.as_ref().map(|error| error.to_string());
+            sqlx::query!(
+                r#"
+                UPDATE match_agents
+                SET score = ?, error = ?
+                WHERE match_id = ? AND seat = ?
+                "#,
+                score,
+                error,
+                id,
+                seat,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO match_replays (match_id, data)
+            VALUES (?, ?)
+            "#,
+            id,
+            outcome.replay,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for (line, log) in outcome.logs.iter().enumerate() {
+            let line = line as i64;
+            sqlx::query!(
+                r#"
+                INSERT INTO match_logs (match_id, line, seat, tick, message)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                id,
+                line,
+                log.seat,
+                log.tick,
+                log.message,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for snapshot in &outcome.memory {
+            let memory = snapshot.memory.to_string();
+            sqlx::query!(
+                r#"
+                INSERT INTO match_memory (match_id, seat, tick, memory)
+                VALUES (?, ?, ?, ?)
+                "#,
+                id,
+                snapshot.seat,
+                snapshot.tick,
+                memory,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        rating::record_match(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Find the lines the agents of a match printed, by tick. With
+    /// `agent_id`, only the lines of the seats that agent played in, and with
+    /// `user_id`, only the seats of agents that user owns.
+    pub async fn find_logs(
+        &self,
+        id: i64,
+        agent_id: Option<i64>,
+        user_id: Option<i64>,
+    ) -> Result<Vec<LogLine>> {
+        let lines = sqlx::query_as!(
+            LogLine,
+            r#"
+            SELECT ml.seat, ml.tick, ml.message
+            FROM match_logs ml
+            JOIN match_agents ma ON ma.match_id = ml.match_id AND ma.seat = ml.seat
+            LEFT JOIN agents a ON a.id = ma.agent_id
+            WHERE ml.match_id = ?
+                AND (? IS NULL OR ma.agent_id = ?)
+                AND (? IS NULL OR a.user_id = ?)
+            ORDER BY ml.line
+            "#,
+            id,
+            agent_id,
+            agent_id,
+            user_id,
+            user_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(lines)
+    }
+
+    /// Find what the agents of a match remembered, by tick and then seat.
+    /// With `agent_id`, only the seats that agent played in, and with
+    /// `user_id`, only the seats of agents that user owns.
+    pub async fn find_memory(
+        &self,
+        id: i64,
+        agent_id: Option<i64>,
+        user_id: Option<i64>,
+    ) -> Result<Vec<MemorySnapshot>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT mm.seat, mm.tick, mm.memory
+            FROM match_memory mm
+            JOIN match_agents ma ON ma.match_id = mm.match_id AND ma.seat = mm.seat
+            LEFT JOIN agents a ON a.id = ma.agent_id
+            WHERE mm.match_id = ?
+                AND (? IS NULL OR ma.agent_id = ?)
+                AND (? IS NULL OR a.user_id = ?)
+            ORDER BY mm.tick, mm.seat
+            "#,
+            id,
+            agent_id,
+            agent_id,
+            user_id,
+            user_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(MemorySnapshot {
+                    seat: row.seat,
+                    tick: row.tick,
+                    memory: serde_json::from_str(&row.memory)
+                        .map_err(|error| sqlx::Error::Decode(error.into()))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Find the encoded replay of a match, `None` until it has been played.
+    pub async fn find_replay(&self, id: i64) -> Result<Option<Vec<u8>>> {
+        let data = sqlx::query_scalar!(
+            r#"
+            SELECT data
+            FROM match_replays
+            WHERE match_id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(data)
+    }
+
+    /// Find a match by ID.
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Match>> {
+        let row = sqlx::query_as!(
+            MatchRow,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                seed,
+                game_version,
+                lua_version,
+                rated as "rated: bool",
+                ladder as "ladder: bool",
+                status as "status: JobStatus",
+                error,
+                winner,
+                ticks,
+                termination as "termination: Termination",
+                created_at,
+                finished_at
+            FROM matches
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(self.with_agents(row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Find all matches an agent took part in, newest first.
+    pub async fn find_by_agent(&self, agent_id: i64) -> Result<Vec<Match>> {
+        let rows = sqlx::query_as!(
+            MatchRow,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                seed,
+                game_version,
+                lua_version,
+                rated as "rated: bool",
+                ladder as "ladder: bool",
+                status as "status: JobStatus",
+                error,
+                winner,
+                ticks,
+                termination as "termination: Termination",
+                created_at,
+                finished_at
+            FROM matches
+            WHERE id IN (SELECT match_id FROM match_agents WHERE agent_id = ?)
+            ORDER BY id DESC
+            "#,
+            agent_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        let mut matches = Vec::with_capacity(rows.len());
+        for row in rows {
+            matches.push(self.with_agents(row).await?);
+        }
+        Ok(matches)
+    }
+
+    /// Load the participants of a match.
+    async fn with_agents(&self, row: MatchRow) -> Result<Match> {
+        let agents = sqlx::query_as!(
+            MatchAgent,
+            r#"
+            SELECT seat, agent_id, agent_version, score, error
+            FROM match_agents
+            WHERE match_id = ?
+            ORDER BY seat
+            "#,
+            row.id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(Match {
+            id: row.id,
+            game_id: row.game_id,
+            user_id: row.user_id,
+            seed: row.seed,
+            game_version: row.game_version,
+            lua_version: row.lua_version,
+            rated: row.rated,
+            ladder: row.ladder,
+            status: row.status,
+            error: row.error,
+            winner: row.winner,
+            ticks: row.ticks,
+            termination: row.termination,
+            created_at: row.created_at,
+            finished_at: row.finished_at,
+            agents,
+        })
+    }
+}
+
+/// Insert a match and queue a job to play it, returning the match ID.
+/// `seats` are pairs of an agent and the version it plays, its latest if
+/// `None`, in seat order. Fails with [`Error::NotFound`] if an agent was
+/// deleted in the meantime, the caller's transaction must then be dropped.
+pub(crate) async fn insert_match(
+    conn: &mut SqliteConnection,
+    game_id: i64,
+    user_id: i64,
+    seed: i64,
+    seats: &[(i64, Option<i64>)],
+    rated: bool,
+) -> Result<i64> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO matches (game_id, user_id, seed, rated)
+        VALUES (?, ?, ?, ?)
+        RETURNING id as "id!"
+        "#,
+        game_id,
+        user_id,
+        seed,
+        rated,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    for (seat, (agent_id, version)) in seats.iter().enumerate() {
+        let seat = seat as i64;
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO match_agents (match_id, seat, agent_id, agent_version)
+            SELECT ?, ?, id, COALESCE(?, version) FROM agents WHERE id = ?
+            "#,
+            id,
+            seat,
+            version,
+            agent_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        if inserted.rows_affected() != 1 {
+            return Err(Error::NotFound);
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO jobs (kind, target_id)
+        VALUES ('match', ?)
+        "#,
+        id,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AgentError, JobKind};
+    use crate::repositories::{
+        AgentRepository, GameRepository, JobRepository, RatingRepository, UserRepository,
+    };
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    /// Create a user with two robot sumo agents, returning the user and agent IDs.
+    async fn create_agents(pool: &SqlitePool) -> (i64, i64, [i64; 2]) {
+        let user = UserRepository::new(pool)
+            .create("testuser", "TestPass123!", false)
+            .await
+            .unwrap();
+        let game = GameRepository::new(pool)
+            .find_by_name("robotsumo")
+            .await
+            .unwrap()
+            .unwrap();
+        let repo = AgentRepository::new(pool);
+        let first = repo
+            .create(user.id, game.id, "First", "-- first")
+            .await
+            .unwrap();
+        let second = repo
+            .create(user.id, game.id, "Second", "-- second")
+            .await
+            .unwrap();
+        (user.id, game.id, [first.id, second.id])
+    }
+
+    #[tokio::test]
+    async fn test_create_match() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, 42, &agents, true)
+            .await
+            .unwrap();
+
+        assert_eq!(created.game_id, game_id);
+        assert_eq!(created.seed, 42);
+        assert_eq!(created.status, JobStatus::Queued);
+        assert_eq!(created.termination, None);
+        assert_eq!(created.agents.len(), 2);
+        assert_eq!(created.agents[0].agent_id, Some(agents[0]));
+        assert_eq!(created.agents[0].agent_version, Some(1));
+        assert_eq!(created.agents[1].seat, 1);
+        assert_eq!(created.agents[1].score, None);
+        assert_eq!(repo.find_replay(created.id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_finish_match() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+        let outcome = MatchOutcome {
+            winner: Some(0),
+            scores: vec![1, 0],
+            ticks: 17,
+            termination: Termination::AgentError,
+            errors: vec![None, Some(AgentError::MissingFunction("think".into()))],
+            replay: vec![1, 2, 3],
+            logs: vec![
+                LogLine {
+                    seat: 1,
+                    tick: 0,
+                    message: "ready".to_string(),
+                },
+                LogLine {
+                    seat: 0,
+                    tick: 2,
+                    message: "hello\t2".to_string(),
+                },
+            ],
+            memory: vec![
+                MemorySnapshot {
+                    seat: 0,
+                    tick: 0,
+                    memory: serde_json::json!({ "seen": [1, 2] }),
+                },
+                MemorySnapshot {
+                    seat: 1,
+                    tick: 3,
+                    memory: serde_json::json!({ "turns": 3 }),
+                },
+            ],
+            game_version: "1.0.0",
+            lua_version: "Lua 5.4",
+        };
+        repo.finish(created.id, &outcome).await.unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.status, JobStatus::Finished);
+        assert_eq!(found.winner, Some(0));
+        assert_eq!(found.ticks, Some(17));
+        assert_eq!(found.termination, Some(Termination::AgentError));
+        assert_eq!(found.game_version.as_deref(), Some("1.0.0"));
+        assert_eq!(found.lua_version.as_deref(), Some("Lua 5.4"));
+        assert!(found.finished_at.is_some());
+        assert_eq!(found.agents[0].score, Some(1));
+        assert_eq!(found.agents[0].error, None);
+        assert!(found.agents[1].error.as_ref().unwrap().contains("think"));
+
+        let replay = repo.find_replay(created.id).await.unwrap();
+        assert_eq!(replay, Some(vec![1, 2, 3]));
+
+        let logs = repo.find_logs(created.id, None, None).await.unwrap();
+        assert_eq!(logs, outcome.logs);
+        let logs = repo
+            .find_logs(created.id, Some(agents[0]), None)
+            .await
+            .unwrap();
+        assert_eq!(logs, outcome.logs[1..]);
+        let logs = repo
+            .find_logs(created.id, None, Some(user_id + 1))
+            .await
+            .unwrap();
+        assert!(logs.is_empty());
+
+        let memory = repo.find_memory(created.id, None, None).await.unwrap();
+        assert_eq!(memory, outcome.memory);
+        let memory = repo
+            .find_memory(created.id, Some(agents[1]), None)
+            .await
+            .unwrap();
+        assert_eq!(memory, outcome.memory[1..]);
+        let memory = repo
+            .find_memory(created.id, None, Some(user_id))
+            .await
+            .unwrap();
+        assert_eq!(memory, outcome.memory);
+        let memory = repo
+            .find_memory(created.id, None, Some(user_id + 1))
+            .await
+            .unwrap();
+        assert!(memory.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_queues_job() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let created = MatchRepository::new(&pool)
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+
+        let job = JobRepository::new(&pool)
+            .claim_next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.kind, JobKind::Match);
+        assert_eq!(job.target_id, created.id);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_deleted_agent_fails() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+        AgentRepository::new(&pool)
+            .delete(agents[1], user_id)
+            .await
+            .unwrap();
+
+        let result = MatchRepository::new(&pool)
+            .create(game_id, user_id, 1, &agents, true)
+            .await;
+        assert!(matches!(result, Err(Error::NotFound)));
+
+        // Nothing of the match is left behind
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM matches")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        let job = JobRepository::new(&pool).claim_next().await.unwrap();
+        assert!(job.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_status() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+        repo.set_status(created.id, JobStatus::Failed, Some("broken"))
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.status, JobStatus::Failed);
+        assert_eq!(found.error.as_deref(), Some("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_finished_match_keeps_its_result() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+        let outcome = |winner: usize| MatchOutcome {
+            winner: Some(winner),
+            scores: vec![u32::from(winner == 0), u32::from(winner == 1)],
+            ticks: 5,
+            termination: Termination::Finished(robotsumo_core::RING_OUT.to_string()),
+            errors: vec![None, None],
+            replay: vec![winner as u8],
+            logs: vec![LogLine {
+                seat: 0,
+                tick: 0,
+                message: "hi".to_string(),
+            }],
+            memory: vec![MemorySnapshot {
+                seat: 0,
+                tick: 0,
+                memory: serde_json::json!({ "x": 1 }),
+            }],
+            game_version: "1.0.0",
+            lua_version: "Lua 5.4",
+        };
+        repo.finish(created.id, &outcome(0)).await.unwrap();
+
+        // Storing it again, as a retried job would, changes nothing
+        repo.finish(created.id, &outcome(1)).await.unwrap();
+        repo.set_status(created.id, JobStatus::Failed, Some("broken"))
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.status, JobStatus::Finished);
+        assert_eq!(found.error, None);
+        assert_eq!(found.winner, Some(0));
+        assert_eq!(repo.find_replay(created.id).await.unwrap(), Some(vec![0]));
+        assert_eq!(
+            repo.find_logs(created.id, None, None).await.unwrap().len(),
+            1
+        );
+        let rating = RatingRepository::new(&pool)
+            .find(agents[0], 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rating.matches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_agent() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let first = repo
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+        let second = repo
+            .create(game_id, user_id, 2, &[agents[0], agents[0]], true)
+            .await
+            .unwrap();
+
+        let matches = repo.find_by_agent(agents[0]).await.unwrap();
+        let ids: Vec<i64> = matches.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![second.id, first.id]);
+
+        let matches = repo.find_by_agent(agents[1]).await.unwrap();
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_agent_keeps_match() {
+        let pool = setup_test_db().await;
+        let (user_id, game_id, agents) = create_agents(&pool).await;
+
+        let repo = MatchRepository::new(&pool);
+        let created = repo
+            .create(game_id, user_id, 1, &agents, true)
+            .await
+            .unwrap();
+        AgentRepository::new(&pool)
+            .delete(agents[1], user_id)
+            .await
+            .unwrap();
+
+        let found = repo.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.agents.len(), 2);
+        assert_eq!(found.agents[1].agent_id, None);
+    }
+}
@@ -0,0 +1,625 @@
+use crate::models::{AgentRating, LeaderboardEntry};
+use crate::prelude::*;
+use crate::rating::{self, Rating, Standing};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// A seat of a played match, as needed for rating it.
+struct RatedSeat {
+    agent_id: Option<i64>,
+    agent_version: Option<i64>,
+    score: Option<i64>,
+    error: Option<String>,
+}
+
+/// Repository for agent rating database operations.
+pub struct RatingRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> RatingRepository<'a> {
+    /// Create a new RatingRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Find the rating of a version of an agent, `None` until it has played a
+    /// rated match.
+    pub async fn find(&self, agent_id: i64, agent_version: i64) -> Result<Option<AgentRating>> {
+        let rating = sqlx::query_as!(
+            AgentRating,
+            r#"
+            SELECT
+                agent_id,
+                agent_version,
+                game_id,
+                rating,
+                deviation,
+                volatility,
+                matches,
+                updated_at
+            FROM ratings
+            WHERE agent_id = ? AND agent_version = ?
+            "#,
+            agent_id,
+            agent_version,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(rating)
+    }
+
+    /// Rank the rated agents of a game, best first, by the rating of their
+    /// newest rated version, and return `limit` of them from `offset` on.
+    /// Wins, losses, draws and games count the rated matches finished in the
+    /// last `days` days, or ever if `None`. Only agents with at least
+    /// `min_games` such matches are ranked.
+    pub async fn leaderboard(
+        &self,
+        game_id: i64,
+        days: Option<i64>,
+        min_games: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let window = days.map(|days| format!("-{} days", days));
+        let entries = sqlx::query_as!(
+            LeaderboardEntry,
+            r#"
+            WITH latest AS (
+                SELECT agent_id, MAX(agent_version) AS agent_version
+                FROM ratings
+                WHERE game_id = ?1
+                GROUP BY agent_id
+            ),
+            results AS (
+                SELECT
+                    ma.agent_id,
+                    m.id,
+                    CASE
+                        WHEN m.winner IS NULL THEN 0
+                        WHEN MAX(ma.seat = m.winner) THEN 1
+                        ELSE -1
+                    END AS result
+                FROM match_agents ma
+                JOIN matches m ON m.id = ma.match_id
+                WHERE m.game_id = ?1 AND m.rated AND m.status = 'finished'
+                    AND (?2 IS NULL OR m.finished_at >= datetime('now', ?2))
+                GROUP BY ma.agent_id, m.id
+            ),
+            standings AS (
+                SELECT
+                    agent_id,
+                    SUM(result = 1) AS wins,
+                    SUM(result = -1) AS losses,
+                    SUM(result = 0) AS draws,
+                    COUNT(*) AS games
+                FROM results
+                GROUP BY agent_id
+            )
+            SELECT
+                ROW_NUMBER() OVER (ORDER BY r.rating DESC, a.id) as "rank!: i64",
+                a.id as "agent_id!",
+                a.name as "agent_name!",
+                u.username as "owner!",
+                l.agent_version as "agent_version!: i64",
+                r.rating as "rating!",
+                r.deviation as "deviation!",
+                s.wins as "wins!: i64",
+                s.losses as "losses!: i64",
+                s.draws as "draws!: i64",
+                s.games as "games!: i64"
+            FROM latest l
+            JOIN ratings r ON r.agent_id = l.agent_id AND r.agent_version = l.agent_version
+            JOIN agents a ON a.id = l.agent_id
+            JOIN users u ON u.id = a.user_id
+            JOIN standings s ON s.agent_id = a.id
+            WHERE s.games >= ?3
+            ORDER BY r.rating DESC, a.id
+            LIMIT ?4 OFFSET ?5
+            "#,
+            game_id,
+            window,
+            min_games,
+            limit,
+            offset,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Count the agents ranked on the leaderboard of a game, with the same
+    /// filters as [`Self::leaderboard`] and the same query up to the page.
+    pub async fn count_leaderboard(
+        &self,
+        game_id: i64,
+        days: Option<i64>,
+        min_games: i64,
+    ) -> Result<i64> {
+        let window = days.map(|days| format!("-{} days", days));
+        let total = sqlx::query_scalar!(
+            r#"
+            WITH latest AS (
+                SELECT agent_id, MAX(agent_version) AS agent_version
+                FROM ratings
+                WHERE game_id = ?1
+                GROUP BY agent_id
+            ),
+            results AS (
+                SELECT
+                    ma.agent_id,
+                    m.id,
+                    CASE
+                        WHEN m.winner IS NULL THEN 0
+                        WHEN MAX(ma.seat = m.winner) THEN 1
+                        ELSE -1
+                    END AS result
+                FROM match_agents ma
+                JOIN matches m ON m.id = ma.match_id
+                WHERE m.game_id = ?1 AND m.rated AND m.status = 'finished'
+                    AND (?2 IS NULL OR m.finished_at >= datetime('now', ?2))
+                GROUP BY ma.agent_id, m.id
+            ),
+            standings AS (
+                SELECT
+                    agent_id,
+                    SUM(result = 1) AS wins,
+                    SUM(result = -1) AS losses,
+                    SUM(result = 0) AS draws,
+                    COUNT(*) AS games
+                FROM results
+                GROUP BY agent_id
+            )
+            SELECT COUNT(*) as "total!: i64"
+            FROM latest l
+            JOIN ratings r ON r.agent_id = l.agent_id AND r.agent_version = l.agent_version
+            JOIN agents a ON a.id = l.agent_id
+            JOIN users u ON u.id = a.user_id
+            JOIN standings s ON s.agent_id = a.id
+            WHERE s.games >= ?3
+            "#,
+            game_id,
+            window,
+            min_games,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// Update the ratings of the agents in a finished match. Does nothing for
+    /// unrated matches and matches that already counted. Returns whether any
+    /// rating changed.
+    pub async fn record_match(&self, match_id: i64) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+        let changed = record_match(&mut tx, match_id).await?;
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// Rebuild every rating from the match history, applying rated matches
+    /// in the same order as when they were played. Returns the number of
+    /// matches that changed ratings.
+    pub async fn recompute(&self) -> Result<usize> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!("DELETE FROM ratings")
+            .execute(&mut *tx)
+            .await?;
+
+        // Matches that never counted, e.g. because the server stopped right
+        // after playing them, go last in the order they finished
+        let match_ids = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id!"
+            FROM matches
+            WHERE rated AND status = 'finished'
+            ORDER BY rating_order IS NULL, rating_order, finished_at, id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut changed = 0;
+        for match_id in match_ids {
+            sqlx::query!(
+                r#"
+                UPDATE matches
+                SET rating_order = (SELECT COALESCE(MAX(rating_order), 0) + 1 FROM matches)
+                WHERE id = ? AND rating_order IS NULL
+                "#,
+                match_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if apply_match(&mut tx, match_id).await? {
+                changed += 1;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+}
+
+/// Update the ratings of the agents in a finished match, as part of a
+/// transaction, so a match can be stored and rated at once. Does nothing for
+/// unrated matches and matches that already counted. Returns whether any
+/// rating changed.
+pub(crate) async fn record_match(conn: &mut SqliteConnection, match_id: i64) -> Result<bool> {
+    // Claiming the match first also locks the database before any ratings
+    // are read
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE matches
+        SET rating_order = (SELECT COALESCE(MAX(rating_order), 0) + 1 FROM matches)
+        WHERE id = ? AND rated AND status = 'finished' AND rating_order IS NULL
+        "#,
+        match_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+    if claimed.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    apply_match(conn, match_id).await
+}
+
+/// Update the ratings of every agent version in a match from their pairwise
+/// results. Matches need a known version in every seat and at least two
+/// different versions to count.
+async fn apply_match(conn: &mut SqliteConnection, match_id: i64) -> Result<bool> {
+    let played = sqlx::query!(
+        r#"
+        SELECT game_id, winner
+        FROM matches
+        WHERE id = ?
+        "#,
+        match_id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let seats = sqlx::query_as!(
+        RatedSeat,
+        r#"
+        SELECT agent_id, agent_version, score, error
+        FROM match_agents
+        WHERE match_id = ?
+        ORDER BY seat
+        "#,
+        match_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut players = Vec::with_capacity(seats.len());
+    let mut standings = Vec::with_capacity(seats.len());
+    for (seat, rated_seat) in seats.iter().enumerate() {
+        let (Some(agent_id), Some(agent_version), Some(score)) = (
+            rated_seat.agent_id,
+            rated_seat.agent_version,
+            rated_seat.score,
+        ) else {
+            return Ok(false);
+        };
+        players.push((agent_id, agent_version));
+        standings.push(Standing {
+            won: played.winner == Some(seat as i64),
+            finished: rated_seat.error.is_none(),
+            score,
+        });
+    }
+
+    // The same version may take several seats, it is rated once
+    let mut versions = players.clone();
+    versions.sort_unstable();
+    versions.dedup();
+    if versions.len() < 2 {
+        return Ok(false);
+    }
+
+    let mut before = Vec::with_capacity(versions.len());
+    for &(agent_id, agent_version) in &versions {
+        let rating = sqlx::query_as!(
+            Rating,
+            r#"
+            SELECT rating, deviation, volatility
+            FROM ratings
+            WHERE agent_id = ? AND agent_version = ?
+            "#,
+            agent_id,
+            agent_version,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        before.push(rating.unwrap_or_default());
+    }
+    let index = |player| {
+        versions
+            .binary_search(player)
+            .expect("every player has a version")
+    };
+
+    // Results against other versions only, from every seat of the version
+    let mut results = vec![Vec::new(); versions.len()];
+    for (seat, scores) in rating::pairwise_scores(&standings).into_iter().enumerate() {
+        for (other, score) in scores {
+            if players[other] != players[seat] {
+                results[index(&players[seat])].push((before[index(&players[other])], score));
+            }
+        }
+    }
+
+    for (i, &(agent_id, agent_version)) in versions.iter().enumerate() {
+        let after = before[i].update(&results[i]);
+        sqlx::query!(
+            r#"
+            INSERT INTO ratings (agent_id, agent_version, game_id, rating, deviation, volatility, matches)
+            VALUES (?, ?, ?, ?, ?, ?, 1)
+            ON CONFLICT (agent_id, agent_version) DO UPDATE SET
+                rating = excluded.rating,
+                deviation = excluded.deviation,
+                volatility = excluded.volatility,
+                matches = matches + 1,
+                updated_at = datetime('now')
+            "#,
+            agent_id,
+            agent_version,
+            played.game_id,
+            after.rating,
+            after.deviation,
+            after.volatility,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MatchOutcome, Termination};
+    use crate::rating::DEFAULT_RATING;
+    use crate::repositories::{AgentRepository, GameRepository, MatchRepository, UserRepository};
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    /// Create a user with two robot sumo agents, returning the user, game and
+    /// agent IDs.
+    async fn create_agents(pool: &SqlitePool) -> (i64, i64, [i64; 2]) {
+        let user = UserRepository::new(pool)
+            .create("testuser", "TestPass123!", false)
+            .await
+            .unwrap();
+        let game = GameRepository::new(pool)
+            .find_by_name("robotsumo")
+            .await
+            .unwrap()
+            .unwrap();
+        let repo = AgentRepository::new(pool);
+        let first = repo
+            .create(user.id, game.id, "First", "-- first")
+            .await
+            .unwrap();
+        let second = repo
+            .create(user.id, game.id, "Second", "-- second")
+            .await
+            .unwrap();
+        (user.id, game.id, [first.id, second.id])
+    }
+
+    /// Store a played match where the agent in seat `winner` won.
+    async fn play(pool: &SqlitePool, agents: &[i64], winner: usize, rated: bool) -> i64 {
+        let agent = AgentRepository::new(pool)
+            .find_by_id_any_owner(agents[0])
+            .await
+            .unwrap()
+            .unwrap();
+        let repo = MatchRepository::new(pool);
+        let created = repo
+            .create(agent.game_id, agent.user_id, 1, agents, rated)
+            .await
+            .unwrap();
+        let outcome = MatchOutcome {
+            winner: Some(winner),
+            scores: (0..agents.len())
+                .map(|seat| u32::from(seat == winner))
+                .collect(),
+            ticks: 10,
+            termination: Termination::Finished(robotsumo_core::RING_OUT.to_string()),
+            errors: agents.iter().map(|_| None).collect(),
+            replay: Vec::new(),
+            logs: Vec::new(),
+            memory: Vec::new(),
+            game_version: "1.0.0",
+            lua_version: "Lua 5.4",
+        };
+        repo.finish(created.id, &outcome).await.unwrap();
+        created.id
+    }
+
+    async fn rating_of(pool: &SqlitePool, agent_id: i64) -> Option<AgentRating> {
+        RatingRepository::new(pool).find(agent_id, 1).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_finished_match_updates_ratings() {
+        let pool = setup_test_db().await;
+        let (_, game_id, agents) = create_agents(&pool).await;
+        let match_id = play(&pool, &agents, 0, true).await;
+
+        let repo = RatingRepository::new(&pool);
+
+        let winner = rating_of(&pool, agents[0]).await.unwrap();
+        let loser = rating_of(&pool, agents[1]).await.unwrap();
+        assert!(winner.rating > DEFAULT_RATING);
+        assert!(loser.rating < DEFAULT_RATING);
+        assert_eq!(winner.matches, 1);
+        assert_eq!(winner.game_id, game_id);
+
+        // A match only counts once
+        assert!(!repo.record_match(match_id).await.unwrap());
+        assert_eq!(rating_of(&pool, agents[0]).await.unwrap().matches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_unrated_and_self_play_matches_do_not_count() {
+        let pool = setup_test_db().await;
+        let (_, _, agents) = create_agents(&pool).await;
+        let unrated = play(&pool, &agents, 0, false).await;
+        let self_play = play(&pool, &[agents[0], agents[0]], 0, true).await;
+
+        let repo = RatingRepository::new(&pool);
+        assert!(!repo.record_match(unrated).await.unwrap());
+        assert!(!repo.record_match(self_play).await.unwrap());
+        assert!(rating_of(&pool, agents[0]).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_ranks_by_rating() {
+        let pool = setup_test_db().await;
+        let (_, game_id, agents) = create_agents(&pool).await;
+        let repo = RatingRepository::new(&pool);
+        for winner in [0, 1, 1] {
+            play(&pool, &agents, winner, true).await;
+        }
+        // Unrated matches don't show up in the results
+        play(&pool, &agents, 0, false).await;
+
+        let leaderboard = repo.leaderboard(game_id, None, 1, 10, 0).await.unwrap();
+
+        assert_eq!(leaderboard.len(), 2);
+        let first = &leaderboard[0];
+        assert_eq!((first.rank, first.agent_id), (1, agents[1]));
+        assert_eq!(first.owner, "testuser");
+        assert_eq!((first.wins, first.losses, first.draws), (2, 1, 0));
+        assert_eq!(first.games, 3);
+        assert_eq!(
+            (leaderboard[1].rank, leaderboard[1].agent_id),
+            (2, agents[0])
+        );
+        assert!(first.rating > leaderboard[1].rating);
+
+        // Pages keep the rank on the whole leaderboard
+        let second_page = repo.leaderboard(game_id, None, 1, 1, 1).await.unwrap();
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(
+            (second_page[0].rank, second_page[0].agent_id),
+            (2, agents[0])
+        );
+        assert_eq!(repo.count_leaderboard(game_id, None, 1).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_filters_by_games_and_window() {
+        let pool = setup_test_db().await;
+        let (_, game_id, agents) = create_agents(&pool).await;
+        let repo = RatingRepository::new(&pool);
+        play(&pool, &agents, 0, true).await;
+
+        assert!(
+            repo.leaderboard(game_id, None, 2, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(repo.count_leaderboard(game_id, None, 2).await.unwrap(), 0);
+        assert_eq!(
+            repo.leaderboard(game_id, Some(1), 1, 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        sqlx::query!("UPDATE matches SET finished_at = datetime('now', '-10 days')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(
+            repo.leaderboard(game_id, Some(7), 1, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            repo.count_leaderboard(game_id, Some(7), 1).await.unwrap(),
+            0
+        );
+        assert_eq!(
+            repo.count_leaderboard(game_id, Some(30), 1).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            repo.leaderboard(game_id, Some(30), 1, 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recompute_matches_incremental_ratings() {
+        let pool = setup_test_db().await;
+        let (_, _, agents) = create_agents(&pool).await;
+        let repo = RatingRepository::new(&pool);
+        for winner in [0, 0, 1, 0] {
+            play(&pool, &agents, winner, true).await;
+        }
+        let first = rating_of(&pool, agents[0]).await.unwrap();
+        let second = rating_of(&pool, agents[1]).await.unwrap();
+
+        assert_eq!(repo.recompute().await.unwrap(), 4);
+
+        let recomputed = rating_of(&pool, agents[0]).await.unwrap();
+        assert_eq!(recomputed.rating, first.rating);
+        assert_eq!(recomputed.deviation, first.deviation);
+        assert_eq!(recomputed.matches, 4);
+        assert_eq!(
+            rating_of(&pool, agents[1]).await.unwrap().rating,
+            second.rating
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recompute_includes_uncounted_matches() {
+        let pool = setup_test_db().await;
+        let (_, _, agents) = create_agents(&pool).await;
+        play(&pool, &agents, 1, true).await;
+        // Like matches stored before results and ratings were saved together
+        sqlx::query!("UPDATE matches SET rating_order = NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM ratings")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(rating_of(&pool, agents[1]).await.is_none());
+
+        let repo = RatingRepository::new(&pool);
+        assert_eq!(repo.recompute().await.unwrap(), 1);
+        assert!(rating_of(&pool, agents[1]).await.unwrap().rating > DEFAULT_RATING);
+
+        // Recomputing again gives the same result
+        let before = rating_of(&pool, agents[1]).await.unwrap();
+        assert_eq!(repo.recompute().await.unwrap(), 1);
+        assert_eq!(
+            rating_of(&pool, agents[1]).await.unwrap().rating,
+            before.rating
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use crate::models::{
+    JobStatus, Tournament, TournamentBracket, TournamentEntrant, TournamentError, TournamentFormat,
+    TournamentPairing, TournamentStatus, validate_tournament_name,
+};
+use crate::prelude::*;
+use crate::repositories::insert_match;
+use sqlx::SqlitePool;
+
+/// A pairing to schedule, see [`TournamentRepository::schedule_round`].
+pub struct NewPairing<'a> {
+    pub first: &'a TournamentEntrant,
+    /// `None` for a bye
+    pub second: Option<&'a TournamentEntrant>,
+    pub bracket: Option<TournamentBracket>,
+    /// Seed of the match, unused for a bye
+    pub seed: i64,
+}
+
+/// Repository for tournament database operations.
+pub struct TournamentRepository<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> TournamentRepository<'a> {
+    /// Create a new TournamentRepository with a database connection pool.
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Create a tournament, open for enrolling agents.
+    pub async fn create(
+        &self,
+        game_id: i64,
+        user_id: i64,
+        name: &str,
+        format: TournamentFormat,
+        rounds: Option<i64>,
+    ) -> Result<Tournament> {
+        validate_tournament_name(name)?;
+        let swiss = format == TournamentFormat::Swiss;
+        if rounds.is_some_and(|rounds| !swiss || rounds < 1) {
+            return Err(TournamentError::InvalidRounds.into());
+        }
+
+        let name = name.trim();
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO tournaments (game_id, user_id, name, format, rounds)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id as "id!"
+            "#,
+            game_id,
+            user_id,
+            name,
+            format,
+            rounds,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        self.find_by_id(id).await?.ok_or(Error::NotFound)
+    }
+
+    /// Find a tournament by ID.
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Tournament>> {
+        let tournament = sqlx::query_as!(
+            Tournament,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                name,
+                format as "format: TournamentFormat",
+                rounds,
+                status as "status: TournamentStatus",
+                current_round,
+                created_at,
+                started_at,
+                finished_at
+            FROM tournaments
+            WHERE id = ?
+            "#,
+            id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(tournament)
+    }
+
+    /// Find all tournaments, or those of one game, newest first.
+    pub async fn find_all(&self, game_id: Option<i64>) -> Result<Vec<Tournament>> {
+        let tournaments = sqlx::query_as!(
+            Tournament,
+            r#"
+            SELECT
+                id as "id!",
+                game_id,
+                user_id,
+                name,
+                format as "format: TournamentFormat",
+                rounds,
+                status as "status: TournamentStatus",
+                current_round,
+                created_at,
+                started_at,
+                finished_at
+            FROM tournaments
+            WHERE ?1 IS NULL OR game_id = ?1
+            ORDER BY id DESC
+            "#,
+            game_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(tournaments)
+    }
+
+    /// Find the IDs of all running tournaments.
+    pub async fn find_running(&self) -> Result<Vec<i64>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id!"
+            FROM tournaments
+            WHERE status = 'running'
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Find the tournament a match was played in, if any.
+    pub async fn find_by_match(&self, match_id: i64) -> Result<Option<i64>> {
+        let id = sqlx::query_scalar!(
+            r#"
+            SELECT tournament_id
+            FROM tournament_pairings
+            WHERE match_id = ?
+            "#,
+            match_id,
+        )
+        .fetch_optional(self.db)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Whether an agent is enrolled in a tournament that hasn't finished.
+    pub async fn is_enrolled(&self, agent_id: i64) -> Result<bool> {
+        let enrolled = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM tournament_entrants e
+                JOIN tournaments t ON t.id = e.tournament_id
+                WHERE e.agent_id = ? AND t.status <> 'finished'
+            ) as "enrolled!: bool"
+            "#,
+            agent_id,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(enrolled)
+    }
+
+    /// Enroll a version of an agent in a tournament that hasn't started.
+    pub async fn enroll(
+        &self,
+        tournament_id: i64,
+        agent_id: i64,
+        agent_version: i64,
+    ) -> Result<TournamentEntrant> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO tournament_entrants (tournament_id, agent_id, agent_version)
+            SELECT id, ?, ? FROM tournaments WHERE id = ? AND status = 'pending'
+            RETURNING id as "id!"
+            "#,
+            agent_id,
+            agent_version,
+            tournament_id,
+        )
+        .fetch_optional(self.db)
+        .await
+        .map_err(|e| {
+            if let sqlx::Error::Database(ref db_err) = e
+                && db_err.is_unique_violation()
+            {
+                return Error::Conflict(format!("Agent {} is already enrolled", agent_id));
+            }
+            Error::Database(e)
+        })?
+        .ok_or(TournamentError::AlreadyStarted)?;
+
+        let entrant = sqlx::query_as!(
+            TournamentEntrant,
+            r#"
+            SELECT e.id as "id!", e.agent_id, a.name as "agent_name?", e.agent_version, e.seed
+            FROM tournament_entrants e
+            LEFT JOIN agents a ON a.id = e.agent_id
+            WHERE e.id = ?
+            "#,
+            id,
+        )
+        .fetch_one(self.db)
+        .await?;
+
+        Ok(entrant)
+    }
+
+    /// Find the entrants of a tournament in seeding order, or in enrollment
+    /// order before it starts.
+    pub async fn find_entrants(&self, tournament_id: i64) -> Result<Vec<TournamentEntrant>> {
+        let entrants = sqlx::query_as!(
+            TournamentEntrant,
+            r#"
+            SELECT e.id as "id!", e.agent_id, a.name as "agent_name?", e.agent_version, e.seed
+            FROM tournament_entrants e
+            LEFT JOIN agents a ON a.id = e.agent_id
+            WHERE e.tournament_id = ?
+            ORDER BY e.seed, e.id
+            "#,
+            tournament_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(entrants)
+    }
+
+    /// Find the pairings of every round of a tournament, with the results of
+    /// their matches.
+    pub async fn find_pairings(&self, tournament_id: i64) -> Result<Vec<TournamentPairing>> {
+        let pairings = sqlx::query_as!(
+            TournamentPairing,
+            r#"
+            SELECT
+                p.id as "id!",
+                p.round,
+                p.bracket as "bracket: TournamentBracket",
+                p.first_entrant_id,
+                p.second_entrant_id,
+                p.match_id,
+                m.status as "status?: JobStatus",
+                CASE m.winner
+                    WHEN 0 THEN p.first_entrant_id
+                    WHEN 1 THEN p.second_entrant_id
+                END as "winner_entrant_id?: i64"
+            FROM tournament_pairings p
+            LEFT JOIN matches m ON m.id = p.match_id
+            WHERE p.tournament_id = ?
+            ORDER BY p.round, p.id
+            "#,
+            tournament_id,
+        )
+        .fetch_all(self.db)
+        .await?;
+
+        Ok(pairings)
+    }
+
+    /// Start a pending tournament, seeding `entrant_ids` in order. Returns
+    /// whether the tournament was pending.
+    pub async fn start(&self, tournament_id: i64, entrant_ids: &[i64]) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+
+        let started = sqlx::query!(
+            r#"
+            UPDATE tournaments
+            SET status = 'running', started_at = datetime('now')
+            WHERE id = ? AND status = 'pending'
+            "#,
+            tournament_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if started.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for (seed, entrant_id) in entrant_ids.iter().enumerate() {
+            let seed = seed as i64 + 1;
+            sqlx::query!(
+                r#"
+                UPDATE tournament_entrants
+                SET seed = ?
+                WHERE id = ? AND tournament_id = ?
+                "#,
+                seed,
+                entrant_id,
+                tournament_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Store the pairings of the round after the current one and queue their
+    /// matches. Returns `false`, changing nothing, if the round was already
+    /// scheduled or the tournament isn't running.
+    pub async fn schedule_round(
+        &self,
+        tournament: &Tournament,
+        pairings: &[NewPairing<'_>],
+    ) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+
+        let round = tournament.current_round + 1;
+        let claimed = sqlx::query!(
+            r#"
+            UPDATE tournaments
+            SET current_round = ?
+            WHERE id = ? AND status = 'running' AND current_round = ?
+            "#,
+            round,
+            tournament.id,
+            tournament.current_round,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for pairing in pairings {
+            // An entrant whose agent is gone can't play, the pairing counts as a draw
+            let second = pairing
+                .second
+                .and_then(|entrant| Some((entrant.agent_id?, Some(entrant.agent_version))));
+            let match_id = match (pairing.first.agent_id, second) {
+                (Some(first), Some(second)) => {
+                    let seats = [(first, Some(pairing.first.agent_version)), second];
+                    let id = insert_match(
+                        &mut tx,
+                        tournament.game_id,
+                        tournament.user_id,
+                        pairing.seed,
+                        &seats,
+                        true,
+                    )
+                    .await?;
+                    Some(id)
+                }
+                _ => None,
+            };
+
+            let second_entrant_id = pairing.second.map(|entrant| entrant.id);
+            sqlx::query!(
+                r#"
+                INSERT INTO tournament_pairings
+                    (tournament_id, round, bracket, first_entrant_id, second_entrant_id, match_id)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+                tournament.id,
+                round,
+                pairing.bracket,
+                pairing.first.id,
+                second_entrant_id,
+                match_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Mark a running tournament as finished.
+    pub async fn finish(&self, id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE tournaments
+            SET status = 'finished', finished_at = datetime('now')
+            WHERE id = ? AND status = 'running'
+            "#,
+            id,
+        )
+        .execute(self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{AgentRepository, GameRepository, MatchRepository, UserRepository};
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    /// Create a robot sumo tournament with two enrolled agents, returning the
+    /// tournament and the agent IDs.
+    async fn create_tournament(pool: &SqlitePool) -> (Tournament, [i64; 2]) {
+        let user = UserRepository::new(pool)
+            .create("teacher", "TestPass123!", true)
+            .await
+            .unwrap();
+        let game = GameRepository::new(pool)
+            .find_by_name("robotsumo")
+            .await
+            .unwrap()
+            .unwrap();
+        let agents = AgentRepository::new(pool);
+        let first = agents
+            .create(user.id, game.id, "First", "-- first")
+            .await
+            .unwrap();
+        let second = agents
+            .create(user.id, game.id, "Second", "-- second")
+            .await
+            .unwrap();
+
+        let repo = TournamentRepository::new(pool);
+        let tournament = repo
+            .create(
+                game.id,
+                user.id,
+                "End of term",
+                TournamentFormat::RoundRobin,
+                None,
+            )
+            .await
+            .unwrap();
+        repo.enroll(tournament.id, first.id, 1).await.unwrap();
+        repo.enroll(tournament.id, second.id, 1).await.unwrap();
+        (tournament, [first.id, second.id])
+    }
+
+    #[tokio::test]
+    async fn test_create_tournament() {
+        let pool = setup_test_db().await;
+        let (tournament, _) = create_tournament(&pool).await;
+
+        assert_eq!(tournament.name, "End of term");
+        assert_eq!(tournament.status, TournamentStatus::Pending);
+        assert_eq!(tournament.current_round, 0);
+
+        let repo = TournamentRepository::new(&pool);
+        let result = repo
+            .create(
+                tournament.game_id,
+                tournament.user_id,
+                "Knockout",
+                TournamentFormat::SingleElimination,
+                Some(3),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Tournament(TournamentError::InvalidRounds))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_enroll_twice_conflicts() {
+        let pool = setup_test_db().await;
+        let (tournament, agents) = create_tournament(&pool).await;
+
+        let repo = TournamentRepository::new(&pool);
+        let result = repo.enroll(tournament.id, agents[0], 1).await;
+        assert!(matches!(result, Err(Error::Conflict(_))));
+
+        let entrants = repo.find_entrants(tournament.id).await.unwrap();
+        assert_eq!(entrants.len(), 2);
+        assert_eq!(entrants[0].agent_name.as_deref(), Some("First"));
+        assert!(repo.is_enrolled(agents[0]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_start_seeds_entrants_once() {
+        let pool = setup_test_db().await;
+        let (tournament, agents) = create_tournament(&pool).await;
+        let repo = TournamentRepository::new(&pool);
+        let entrants = repo.find_entrants(tournament.id).await.unwrap();
+
+        assert!(
+            repo.start(tournament.id, &[entrants[1].id, entrants[0].id])
+                .await
+                .unwrap()
+        );
+        assert!(!repo.start(tournament.id, &[]).await.unwrap());
+
+        let seeded = repo.find_entrants(tournament.id).await.unwrap();
+        assert_eq!(seeded[0].agent_id, Some(agents[1]));
+        assert_eq!(seeded[0].seed, Some(1));
+        let result = repo.enroll(tournament.id, agents[0], 1).await;
+        assert!(matches!(
+            result,
+            Err(Error::Tournament(TournamentError::AlreadyStarted))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_round_queues_matches_once() {
+        let pool = setup_test_db().await;
+        let (tournament, agents) = create_tournament(&pool).await;
+        let repo = TournamentRepository::new(&pool);
+        let entrants = repo.find_entrants(tournament.id).await.unwrap();
+        let ids: Vec<i64> = entrants.iter().map(|entrant| entrant.id).collect();
+        repo.start(tournament.id, &ids).await.unwrap();
+        let tournament = repo.find_by_id(tournament.id).await.unwrap().unwrap();
+
+        let round = [NewPairing {
+            first: &entrants[0],
+            second: Some(&entrants[1]),
+            bracket: None,
+            seed: 7,
+        }];
+        assert!(repo.schedule_round(&tournament, &round).await.unwrap());
+        assert!(!repo.schedule_round(&tournament, &round).await.unwrap());
+
+        let pairings = repo.find_pairings(tournament.id).await.unwrap();
+        assert_eq!(pairings.len(), 1);
+        assert_eq!(pairings[0].round, 1);
+        assert_eq!(pairings[0].status, Some(JobStatus::Queued));
+        let match_id = pairings[0].match_id.unwrap();
+        assert_eq!(
+            repo.find_by_match(match_id).await.unwrap(),
+            Some(tournament.id)
+        );
+
+        let queued = MatchRepository::new(&pool)
+            .find_by_id(match_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(queued.seed, 7);
+        assert_eq!(queued.agents[0].agent_id, Some(agents[0]));
+        assert_eq!(queued.agents[1].agent_version, Some(1));
+    }
+}
@@ -55,7 +55,7 @@ impl<'a> UserRepository<'a> {
             r#"
             SELECT id, username, password_hash, admin as "admin: bool"
             FROM users
-            WHERE id = ?
+            WHERE id = ? AND NOT system
             "#,
             id,
         )
@@ -72,7 +72,7 @@ impl<'a> UserRepository<'a> {
             r#"
             SELECT id, username, password_hash, admin as "admin: bool"
             FROM users
-            WHERE username = ?
+            WHERE username = ? AND NOT system
             "#,
             username,
         )
@@ -89,6 +89,7 @@ impl<'a> UserRepository<'a> {
             r#"
             SELECT id, username, password_hash, admin as "admin: bool"
             FROM users
+            WHERE NOT system
             ORDER BY id
             "#,
         )
@@ -104,7 +105,7 @@ impl<'a> UserRepository<'a> {
             r#"
             UPDATE users
             SET username = ?, admin = ?, updated_at = datetime('now')
-            WHERE id = ?
+            WHERE id = ? AND NOT system
             "#,
             username,
             admin,
@@ -128,7 +129,7 @@ impl<'a> UserRepository<'a> {
             r#"
             UPDATE users
             SET password_hash = ?, updated_at = datetime('now')
-            WHERE id = ?
+            WHERE id = ? AND NOT system
             "#,
             password_hash,
             id,
@@ -144,7 +145,7 @@ impl<'a> UserRepository<'a> {
         let result = sqlx::query!(
             r#"
             DELETE FROM users
-            WHERE id = ?
+            WHERE id = ? AND NOT system
             "#,
             id,
         )
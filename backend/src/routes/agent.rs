@@ -1,10 +1,14 @@
-use crate::models::{Agent, CreateAgentRequest, UpdateAgentRequest};
+use crate::diff;
+use crate::models::{
+    Agent, AgentDiff, AgentDraft, AgentError, AgentVersion, CreateAgentRequest, SaveDraftRequest,
+    UpdateAgentRequest,
+};
 use crate::prelude::*;
-use crate::repositories::AgentRepository;
+use crate::repositories::{AgentRepository, TournamentRepository};
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    routing::{get, post, put},
 };
 use serde::Deserialize;
 
@@ -15,6 +19,16 @@ pub fn routes() -> Router<AppState> {
             "/{id}",
             get(get_agent).put(update_agent).delete(delete_agent),
         )
+        .route(
+            "/{id}/draft",
+            get(get_draft).put(save_draft).delete(discard_draft),
+        )
+        .route("/{id}/draft/publish", post(publish_draft))
+        .route("/{id}/ladder", put(join_ladder).delete(leave_ladder))
+        .route("/{id}/versions", get(list_versions))
+        .route("/{id}/versions/{version}", get(get_version))
+        .route("/{id}/versions/{version}/restore", post(restore_version))
+        .route("/{id}/versions/{from}/diff/{to}", get(diff_versions))
 }
 
 #[derive(Deserialize)]
@@ -87,13 +101,21 @@ async fn update_agent(
     Ok(Json(agent))
 }
 
-/// Delete an agent (must belong to current user).
+/// Delete an agent (must belong to current user) that isn't playing in a
+/// tournament.
 async fn delete_agent(
     State(state): State<AppState>,
     claims: Claims,
     Path(id): Path<i64>,
 ) -> Result<()> {
     let repo = AgentRepository::new(&state.db);
+    repo.find_by_id(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    if TournamentRepository::new(&state.db).is_enrolled(id).await? {
+        return Err(AgentError::InTournament.into());
+    }
+
     let deleted = repo.delete(id, claims.user_id).await?;
     if deleted {
         Ok(())
@@ -101,3 +123,166 @@ async fn delete_agent(
         Err(Error::NotFound)
     }
 }
+
+/// Put an agent on its game's ladder (must belong to current user).
+async fn join_ladder(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Agent>> {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .set_ladder(id, claims.user_id, true)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(agent))
+}
+
+/// Take an agent off its game's ladder (must belong to current user). Ladder
+/// matches already queued are still played.
+async fn leave_ladder(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Agent>> {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .set_ladder(id, claims.user_id, false)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(agent))
+}
+
+/// Get the unpublished draft of an agent (must belong to current user).
+async fn get_draft(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<AgentDraft>> {
+    let repo = AgentRepository::new(&state.db);
+    let draft = repo
+        .find_draft(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(draft))
+}
+
+/// Save a draft of an agent, even if the code has errors (must belong to
+/// current user).
+async fn save_draft(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+    Json(payload): Json<SaveDraftRequest>,
+) -> Result<Json<AgentDraft>> {
+    let repo = AgentRepository::new(&state.db);
+    let draft = repo
+        .save_draft(id, claims.user_id, &payload.code)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(draft))
+}
+
+/// Throw away the draft of an agent (must belong to current user).
+async fn discard_draft(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<()> {
+    let repo = AgentRepository::new(&state.db);
+    let discarded = repo.discard_draft(id, claims.user_id).await?;
+    if discarded {
+        Ok(())
+    } else {
+        Err(Error::NotFound)
+    }
+}
+
+/// Check the draft of an agent and make it the agent's code as a new version
+/// (must belong to current user).
+async fn publish_draft(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Agent>> {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .publish_draft(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(agent))
+}
+
+/// List all versions of an agent, newest first (must belong to current user).
+async fn list_versions(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<AgentVersion>>> {
+    let repo = AgentRepository::new(&state.db);
+    repo.find_by_id(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let versions = repo.find_versions(id).await?;
+    Ok(Json(versions))
+}
+
+/// Get a specific version of an agent (must belong to current user).
+async fn get_version(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((id, version)): Path<(i64, i64)>,
+) -> Result<Json<AgentVersion>> {
+    let repo = AgentRepository::new(&state.db);
+    repo.find_by_id(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let version = repo
+        .find_version(id, version)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(version))
+}
+
+/// Restore the code of an older version as a new version (must belong to
+/// current user).
+async fn restore_version(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((id, version)): Path<(i64, i64)>,
+) -> Result<Json<Agent>> {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .restore(id, claims.user_id, version)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(agent))
+}
+
+/// Compare two versions of an agent (must belong to current user).
+async fn diff_versions(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((id, from, to)): Path<(i64, i64, i64)>,
+) -> Result<Json<AgentDiff>> {
+    let repo = AgentRepository::new(&state.db);
+    repo.find_by_id(id, claims.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let old = repo.find_version(id, from).await?.ok_or(Error::NotFound)?;
+    let new = repo.find_version(id, to).await?.ok_or(Error::NotFound)?;
+
+    let hunks = diff::diff(&old.code, &new.code).ok_or(AgentError::DiffTooLarge)?;
+    let unified = diff::unified(
+        &hunks,
+        &format!("version {}", from),
+        &format!("version {}", to),
+    );
+    Ok(Json(AgentDiff {
+        agent_id: id,
+        from,
+        to,
+        unified,
+        hunks,
+    }))
+}
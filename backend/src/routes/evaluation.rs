@@ -0,0 +1,131 @@
+use crate::evaluation::{DEFAULT_SEEDS, MAX_SEEDS, MIN_SEEDS};
+use crate::models::{CreateEvaluationRequest, Evaluation, EvaluationError};
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, EvaluatedAgent, EvaluationRepository, GameRepository};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::get,
+};
+use std::hash::{BuildHasher, RandomState};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_evaluations).post(create_evaluation))
+        .route("/{id}", get(get_evaluation))
+}
+
+/// List the evaluations of the current user, newest first.
+async fn list_evaluations(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Evaluation>>> {
+    let repo = EvaluationRepository::new(&state.db);
+    let evaluations = repo.find_by_user(claims.user_id).await?;
+    Ok(Json(evaluations))
+}
+
+/// Queue an evaluation of a version of one of the caller's agents against
+/// another version, or the current version of another user's agent.
+async fn create_evaluation(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateEvaluationRequest>,
+) -> Result<Json<Evaluation>> {
+    let agent_repo = AgentRepository::new(&state.db);
+    let agent_a = agent_repo
+        .find_by_id_any_owner(payload.agent_a_id)
+        .await?
+        .ok_or(EvaluationError::InvalidAgent(payload.agent_a_id))?;
+    if agent_a.user_id != claims.user_id && !claims.admin {
+        return Err(EvaluationError::NotOwner.into());
+    }
+    let agent_b = agent_repo
+        .find_by_id_any_owner(payload.agent_b_id)
+        .await?
+        .ok_or(EvaluationError::InvalidAgent(payload.agent_b_id))?;
+    if agent_a.game_id != agent_b.game_id {
+        return Err(EvaluationError::DifferentGames.into());
+    }
+
+    let game = GameRepository::new(&state.db)
+        .find_by_id(agent_a.game_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let head_to_head = state
+        .games
+        .get(&game.name)
+        .is_some_and(|rules| (rules.min_players()..=rules.max_players()).contains(&2));
+    if !head_to_head {
+        return Err(EvaluationError::NotHeadToHead.into());
+    }
+
+    let mut evaluated = Vec::with_capacity(2);
+    for (agent, version) in [
+        (&agent_a, payload.agent_a_version),
+        (&agent_b, payload.agent_b_version),
+    ] {
+        let version = version.unwrap_or(agent.version);
+        // Older versions of other users' agents are private to their owners
+        if version != agent.version && agent.user_id != claims.user_id && !claims.admin {
+            return Err(EvaluationError::PrivateVersion(agent.id).into());
+        }
+        agent_repo
+            .find_version(agent.id, version)
+            .await?
+            .ok_or(EvaluationError::InvalidVersion(agent.id, version))?;
+        evaluated.push(EvaluatedAgent {
+            agent_id: agent.id,
+            version,
+        });
+    }
+
+    let seeds = payload.seeds.unwrap_or(DEFAULT_SEEDS);
+    if seeds < MIN_SEEDS {
+        return Err(EvaluationError::TooFewSeeds(MIN_SEEDS).into());
+    }
+    if seeds > MAX_SEEDS {
+        return Err(EvaluationError::TooManySeeds(MAX_SEEDS).into());
+    }
+
+    // Only the lower 62 bits, so every seed played fits in an SQLite integer
+    let seed = payload
+        .seed
+        .unwrap_or_else(|| (RandomState::new().hash_one(()) >> 2) as i64);
+    if seed < 0 {
+        return Err(EvaluationError::NegativeSeed.into());
+    }
+    if seed.checked_add(seeds).is_none() {
+        return Err(EvaluationError::SeedTooLarge(seeds).into());
+    }
+
+    let repo = EvaluationRepository::new(&state.db);
+    let created = repo
+        .create(
+            game.id,
+            claims.user_id,
+            evaluated[0],
+            evaluated[1],
+            seed,
+            seeds,
+        )
+        .await?;
+    state.queue.notify();
+
+    Ok(Json(created))
+}
+
+/// Get an evaluation of the current user, with its results once played.
+async fn get_evaluation(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Evaluation>> {
+    let repo = EvaluationRepository::new(&state.db);
+    let found = repo
+        .find_by_id(id)
+        .await?
+        .filter(|evaluation| evaluation.user_id == claims.user_id || claims.admin)
+        .ok_or(Error::NotFound)?;
+    Ok(Json(found))
+}
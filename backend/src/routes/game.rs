@@ -1,16 +1,27 @@
-use crate::models::Game;
+use crate::models::{Game, HouseBot, Leaderboard, PlaygroundRequest, PlaygroundResult};
+use crate::playground;
 use crate::prelude::*;
-use crate::repositories::GameRepository;
+use crate::repositories::{BotRepository, GameRepository, RatingRepository};
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    routing::get,
+    extract::{Path, Query, State},
+    routing::{get, post},
 };
+use serde::Deserialize;
+
+/// Leaderboard entries per page unless asked for otherwise.
+const DEFAULT_PER_PAGE: u32 = 20;
+
+/// Most leaderboard entries returned in one page.
+const MAX_PER_PAGE: u32 = 100;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/", get(list_games))
         .route("/{name}", get(get_game))
+        .route("/{name}/bots", get(list_bots))
+        .route("/{name}/leaderboard", get(get_leaderboard))
+        .route("/{name}/playground", post(run_playground))
 }
 
 /// List all available games.
@@ -26,3 +37,81 @@ async fn get_game(State(state): State<AppState>, Path(name): Path<String>) -> Re
     let game = repo.find_by_name(&name).await?.ok_or(Error::NotFound)?;
     Ok(Json(game))
 }
+
+/// List the house bots of a game, easiest first.
+async fn list_bots(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<HouseBot>>> {
+    let game = GameRepository::new(&state.db)
+        .find_by_name(&name)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let bots = BotRepository::new(&state.db).find_by_game(game.id).await?;
+    Ok(Json(bots))
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    /// Page to return, starting at 1
+    page: Option<u32>,
+    per_page: Option<u32>,
+    /// Only count matches finished in this many last days
+    days: Option<u32>,
+    /// Only rank agents that played at least this many counted matches
+    min_games: Option<u32>,
+}
+
+/// Get a page of the agents of a game ranked by rating.
+async fn get_leaderboard(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<Leaderboard>> {
+    let game = GameRepository::new(&state.db)
+        .find_by_name(&name)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let min_games = query.min_games.unwrap_or(1).max(1);
+
+    let days = query.days.map(i64::from);
+    let min_games = i64::from(min_games);
+    let per_page = i64::from(per_page);
+    let page = i64::from(page);
+
+    let repo = RatingRepository::new(&state.db);
+    let entries = repo
+        .leaderboard(game.id, days, min_games, per_page, (page - 1) * per_page)
+        .await?;
+    let total = repo.count_leaderboard(game.id, days, min_games).await?;
+
+    Ok(Json(Leaderboard {
+        game: game.name,
+        page,
+        per_page,
+        total,
+        entries,
+    }))
+}
+
+/// Play unsaved code against a house bot or one of the user's agents and
+/// return the result right away. Nothing is stored.
+async fn run_playground(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(name): Path<String>,
+    Json(payload): Json<PlaygroundRequest>,
+) -> Result<Json<PlaygroundResult>> {
+    let game = GameRepository::new(&state.db)
+        .find_by_name(&name)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let result = playground::play(&state, claims.user_id, game, payload).await?;
+    Ok(Json(result))
+}
@@ -0,0 +1,43 @@
+use crate::models::LadderStatus;
+use crate::prelude::*;
+use crate::repositories::LadderRepository;
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_status))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+}
+
+/// Get the state and settings of the ladder scheduler.
+async fn get_status(State(state): State<AppState>, _claims: Claims) -> Result<Json<LadderStatus>> {
+    let repo = LadderRepository::new(&state.db);
+    let status = repo.find_status(&state.config).await?;
+    Ok(Json(status))
+}
+
+/// Stop queueing ladder matches (admin only). Queued matches are still played.
+async fn pause(State(state): State<AppState>, claims: Claims) -> Result<Json<LadderStatus>> {
+    set_paused(&state, &claims, true).await
+}
+
+/// Queue ladder matches again (admin only).
+async fn resume(State(state): State<AppState>, claims: Claims) -> Result<Json<LadderStatus>> {
+    set_paused(&state, &claims, false).await
+}
+
+async fn set_paused(state: &AppState, claims: &Claims, paused: bool) -> Result<Json<LadderStatus>> {
+    if !claims.admin {
+        return Err(Error::NotFound);
+    }
+
+    let repo = LadderRepository::new(&state.db);
+    repo.set_paused(paused).await?;
+    let status = repo.find_status(&state.config).await?;
+    Ok(Json(status))
+}
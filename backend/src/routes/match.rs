@@ -0,0 +1,243 @@
+use crate::live::LiveMatch;
+use crate::models::{
+    CreateMatchRequest, LogLine, MIN_MATCH_PLAYERS, Match, MatchError, MatchStatus,
+    MatchVerification, MemorySnapshot,
+};
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, BotRepository, GameRepository, MatchRepository};
+use crate::verify;
+use axum::{
+    Json, Router,
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::header,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use serde::Deserialize;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_matches).post(create_match))
+        .route("/{id}", get(get_match))
+        .route("/{id}/status", get(get_match_status))
+        .route("/{id}/replay", get(get_match_replay))
+        .route("/{id}/logs", get(get_match_logs))
+        .route("/{id}/memory", get(get_match_memory))
+        .route("/{id}/live", get(watch_match))
+        .route("/{id}/verify", post(verify_match))
+}
+
+#[derive(Deserialize)]
+struct ListMatchesQuery {
+    agent_id: i64,
+}
+
+/// List all matches an agent took part in, newest first.
+async fn list_matches(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Query(query): Query<ListMatchesQuery>,
+) -> Result<Json<Vec<Match>>> {
+    let repo = MatchRepository::new(&state.db);
+    let matches = repo.find_by_agent(query.agent_id).await?;
+    Ok(Json(matches))
+}
+
+/// Queue a match between agents of any user and house bots. The match is played in the
+/// background, poll its status to know when the result is ready. Matches are only
+/// rated when asked for, and only between the caller's own agents.
+async fn create_match(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateMatchRequest>,
+) -> Result<Json<Match>> {
+    let game = GameRepository::new(&state.db)
+        .find_by_id(payload.game_id)
+        .await?
+        .ok_or(MatchError::UnknownGame)?;
+    let rules = state.games.get(&game.name).ok_or(MatchError::UnknownGame)?;
+
+    let players = payload.agent_ids.len() + payload.bots.len();
+    let min_players = rules.min_players().max(MIN_MATCH_PLAYERS);
+    if players < min_players {
+        return Err(MatchError::TooFewAgents(min_players).into());
+    }
+    if players > rules.max_players() {
+        return Err(MatchError::TooManyAgents(rules.max_players()).into());
+    }
+
+    let agent_repo = AgentRepository::new(&state.db);
+    let mut owns_every_seat = payload.bots.is_empty();
+    for &agent_id in &payload.agent_ids {
+        let agent = agent_repo
+            .find_by_id_any_owner(agent_id)
+            .await?
+            .filter(|agent| agent.game_id == game.id)
+            .ok_or(MatchError::InvalidAgent(agent_id))?;
+        owns_every_seat &= agent.user_id == claims.user_id;
+    }
+
+    // Otherwise anyone could raise or sink other users' ratings at will
+    let rated = payload.rated.unwrap_or(false);
+    if rated && !owns_every_seat && !claims.admin {
+        return Err(MatchError::RatedNotAllowed.into());
+    }
+
+    let mut agent_ids = payload.agent_ids;
+    let bot_repo = BotRepository::new(&state.db);
+    for difficulty in payload.bots {
+        let bot = bot_repo
+            .find_by_difficulty(game.id, difficulty)
+            .await?
+            .ok_or(MatchError::MissingBot(difficulty))?;
+        agent_ids.push(bot.agent_id);
+    }
+
+    // Only the lower 63 bits, so the seed fits in an SQLite integer
+    let seed = payload
+        .seed
+        .unwrap_or_else(|| (RandomState::new().hash_one(()) >> 1) as i64);
+    if seed < 0 {
+        return Err(MatchError::NegativeSeed.into());
+    }
+
+    let repo = MatchRepository::new(&state.db);
+    let created = repo
+        .create(game.id, claims.user_id, seed, &agent_ids, rated)
+        .await?;
+    state.queue.notify();
+
+    Ok(Json(created))
+}
+
+/// Get a specific match by ID.
+async fn get_match(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Match>> {
+    let repo = MatchRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    Ok(Json(found))
+}
+
+/// Get the progress of a match, cheap enough to poll.
+async fn get_match_status(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<MatchStatus>> {
+    let repo = MatchRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    Ok(Json(MatchStatus {
+        id: found.id,
+        status: found.status,
+        error: found.error,
+    }))
+}
+
+/// Download the replay of a played match, in the `game_core::replay` format.
+async fn get_match_replay(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+    let repo = MatchRepository::new(&state.db);
+    let replay = repo.find_replay(id).await?.ok_or(Error::NotFound)?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], replay))
+}
+
+/// Optionally narrows the logs or memory of a match down to one agent.
+#[derive(Deserialize)]
+struct MatchAgentQuery {
+    agent_id: Option<i64>,
+}
+
+/// Get the lines the agents of a match printed, tagged with tick and seat,
+/// optionally for one agent only. Like memory, only the caller's own agents
+/// are included, unless the caller is an admin.
+async fn get_match_logs(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+    Query(query): Query<MatchAgentQuery>,
+) -> Result<Json<Vec<LogLine>>> {
+    let repo = MatchRepository::new(&state.db);
+    repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let owner = (!claims.admin).then_some(claims.user_id);
+    let logs = repo.find_logs(id, query.agent_id, owner).await?;
+    Ok(Json(logs))
+}
+
+/// Get what the agents of a match had in their `memory` table on every tick
+/// it changed, optionally for one agent only. Memory gives away how an agent
+/// plays, so only the caller's own agents are included, unless the caller is
+/// an admin.
+async fn get_match_memory(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+    Query(query): Query<MatchAgentQuery>,
+) -> Result<Json<Vec<MemorySnapshot>>> {
+    let repo = MatchRepository::new(&state.db);
+    repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let owner = (!claims.admin).then_some(claims.user_id);
+    let memory = repo.find_memory(id, query.agent_id, owner).await?;
+    Ok(Json(memory))
+}
+
+/// Play a finished match again and compare the new replay byte for byte with
+/// the stored one (admin only).
+async fn verify_match(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<MatchVerification>> {
+    if !claims.admin {
+        return Err(Error::NotFound);
+    }
+
+    let verification = verify::verify(&state, id).await?;
+    Ok(Json(verification))
+}
+
+/// Watch a match while it is played. Every frame of the stream, in the
+/// `game_core::replay` frame format, is sent as a binary WebSocket message.
+async fn watch_match(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse> {
+    let repo = MatchRepository::new(&state.db);
+    repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let live = state.live.get(id).ok_or(MatchError::NotLive)?;
+    Ok(ws.on_upgrade(move |socket| stream_match(socket, live)))
+}
+
+/// Send the frames of a live match until it is over or the spectator leaves.
+async fn stream_match(mut socket: WebSocket, live: Arc<LiveMatch>) {
+    let (mut frames, mut receiver) = live.subscribe();
+
+    loop {
+        for frame in frames.drain(..) {
+            if socket.send(Message::Binary(frame)).await.is_err() {
+                return;
+            }
+        }
+
+        match receiver.recv().await {
+            Ok(frame) => frames.push(frame),
+            Err(RecvError::Lagged(_)) => (frames, receiver) = live.resubscribe(),
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    let _ = socket.send(Message::Close(None)).await;
+}
@@ -2,14 +2,22 @@ use crate::prelude::*;
 use axum::Router;
 
 mod agent;
+mod evaluation;
 mod game;
 mod health;
+mod ladder;
+mod r#match;
+mod tournament;
 mod user;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .nest("/agents", agent::routes())
+        .nest("/evaluations", evaluation::routes())
         .nest("/games", game::routes())
         .nest("/health", health::routes())
+        .nest("/ladder", ladder::routes())
+        .nest("/matches", r#match::routes())
+        .nest("/tournaments", tournament::routes())
         .nest("/users", user::routes())
 }
@@ -0,0 +1,147 @@
+use crate::models::{
+    CreateTournamentRequest, EnrollAgentRequest, Tournament, TournamentDetails, TournamentEntrant,
+    TournamentError, TournamentRound,
+};
+use crate::prelude::*;
+use crate::repositories::{AgentRepository, GameRepository, TournamentRepository};
+use crate::tournament;
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+};
+use serde::Deserialize;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_tournaments).post(create_tournament))
+        .route("/{id}", get(get_tournament))
+        .route("/{id}/entrants", post(enroll_agent))
+        .route("/{id}/start", post(start_tournament))
+        .route("/{id}/bracket", get(get_bracket))
+}
+
+#[derive(Deserialize)]
+struct ListTournamentsQuery {
+    game_id: Option<i64>,
+}
+
+/// List all tournaments, or those of one game, newest first.
+async fn list_tournaments(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Query(query): Query<ListTournamentsQuery>,
+) -> Result<Json<Vec<Tournament>>> {
+    let repo = TournamentRepository::new(&state.db);
+    let tournaments = repo.find_all(query.game_id).await?;
+    Ok(Json(tournaments))
+}
+
+/// Create a tournament for a game played by two agents (admin only).
+async fn create_tournament(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateTournamentRequest>,
+) -> Result<Json<Tournament>> {
+    if !claims.admin {
+        return Err(Error::NotFound);
+    }
+
+    let game = GameRepository::new(&state.db)
+        .find_by_id(payload.game_id)
+        .await?
+        .ok_or(TournamentError::UnknownGame)?;
+    let rules = state
+        .games
+        .get(&game.name)
+        .ok_or(TournamentError::UnknownGame)?;
+    if !(rules.min_players()..=rules.max_players()).contains(&2) {
+        return Err(TournamentError::NotHeadToHead.into());
+    }
+
+    let repo = TournamentRepository::new(&state.db);
+    let created = repo
+        .create(
+            game.id,
+            claims.user_id,
+            &payload.name,
+            payload.format,
+            payload.rounds,
+        )
+        .await?;
+    Ok(Json(created))
+}
+
+/// Get a tournament with its entrants, standings and progress.
+async fn get_tournament(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<TournamentDetails>> {
+    let repo = TournamentRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let details = tournament::details(&state.db, found).await?;
+    Ok(Json(details))
+}
+
+/// Enroll a version of any user's agent in a tournament that hasn't started
+/// (admin only).
+async fn enroll_agent(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+    Json(payload): Json<EnrollAgentRequest>,
+) -> Result<Json<TournamentEntrant>> {
+    if !claims.admin {
+        return Err(Error::NotFound);
+    }
+
+    let repo = TournamentRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+
+    let agent_repo = AgentRepository::new(&state.db);
+    let agent = agent_repo
+        .find_by_id_any_owner(payload.agent_id)
+        .await?
+        .filter(|agent| agent.game_id == found.game_id)
+        .ok_or(TournamentError::InvalidAgent(payload.agent_id))?;
+    let version = payload.version.unwrap_or(agent.version);
+    agent_repo
+        .find_version(agent.id, version)
+        .await?
+        .ok_or(TournamentError::InvalidVersion(agent.id, version))?;
+
+    let entrant = repo.enroll(found.id, agent.id, version).await?;
+    Ok(Json(entrant))
+}
+
+/// Seed the entrants and schedule the first round (admin only).
+async fn start_tournament(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<TournamentDetails>> {
+    if !claims.admin {
+        return Err(Error::NotFound);
+    }
+
+    let repo = TournamentRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    tournament::start(&state, &found).await?;
+
+    let started = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let details = tournament::details(&state.db, started).await?;
+    Ok(Json(details))
+}
+
+/// Get the pairings of every round so far, with the results of their matches.
+async fn get_bracket(
+    State(state): State<AppState>,
+    _claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<TournamentRound>>> {
+    let repo = TournamentRepository::new(&state.db);
+    let found = repo.find_by_id(id).await?.ok_or(Error::NotFound)?;
+    let rounds = tournament::bracket(&state.db, found.id).await?;
+    Ok(Json(rounds))
+}
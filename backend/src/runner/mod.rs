@@ -0,0 +1,535 @@
+//! Plays matches between agents.
+//!
+//! Every agent gets its own [`Sandbox`] with the game's Lua API loaded, so
+//! agents can't see or change each other's globals, code or state. All they
+//! learn about each other is in the read-only observation the game builds
+//! for them. On each tick all agents are asked for their action before any
+//! of them is applied. An agent whose code fails forfeits: the match ends on that tick
+//! with [`Termination::AgentError`], and if exactly one agent is left without
+//! an error, it wins.
+//!
+//! Lua VMs can't be moved between threads, so a match has to be played from
+//! start to end on one thread, typically inside `spawn_blocking`.
+//!
+//! Every applied tick is recorded, and the outcome carries the encoded
+//! [`Replay`](game_core::Replay), everything the agents printed and what
+//! they had in their `memory` table after every tick it changed, up to
+//! [`MAX_MEMORY_TRACE_BYTES`] per agent. When a
+//! [`LiveMatch`] is given, the same frames are streamed to spectators as the
+//! match is played.
+//!
+//! A match can be cut short after a number of ticks, it then ends in a draw
+//! with [`Termination::Timeout`].
+
+use crate::games::{self, decide};
+use crate::live::LiveMatch;
+use crate::models::{AgentError, LogLine, MatchOutcome, MemorySnapshot, Termination};
+use crate::sandbox::{self, Limits, Sandbox};
+use game_core::replay::{self, Recorder};
+use game_core::{Frame, GameRules};
+
+/// Most memory snapshots of one agent a match keeps, in bytes of JSON.
+/// Later changes are not recorded.
+pub const MAX_MEMORY_TRACE_BYTES: usize = 256 * 1024;
+
+/// An agent taking a seat in a match.
+#[derive(Debug, Clone)]
+pub struct Entrant {
+    /// ID of the agent, `None` for agents that aren't stored
+    pub id: Option<i64>,
+    pub name: String,
+    pub code: String,
+}
+
+/// Play a match of `G` with its default settings between `entrants`, one per
+/// seat, stopping after `max_ticks` if given.
+pub fn run<G: GameRules>(
+    lua_api: &str,
+    seed: u64,
+    entrants: &[Entrant],
+    limits: Limits,
+    max_ticks: Option<u32>,
+    live: Option<&LiveMatch>,
+) -> MatchOutcome {
+    let settings = G::Settings::default();
+    let mut game = G::new_game(seed, settings.clone(), entrants.len());
+    let mut errors: Vec<Option<AgentError>> = entrants.iter().map(|_| None).collect();
+
+    let replay_agents = entrants
+        .iter()
+        .map(|entrant| replay::Agent {
+            id: entrant.id,
+            name: entrant.name.clone(),
+        })
+        .collect();
+    let mut recorder = Recorder::new(&game, seed, settings, replay_agents);
+    if let Some(live) = live {
+        let replay = recorder.replay();
+        live.send(&Frame::<G>::Header(replay.header.clone()));
+        live.send(&Frame::Snapshot(replay.snapshots[0].clone()));
+    }
+
+    let mut agents = Vec::with_capacity(entrants.len());
+    for (seat, entrant) in entrants.iter().enumerate() {
+        match start_agent(lua_api, &entrant.code, limits) {
+            Ok(sandbox) => agents.push((seat, sandbox)),
+            Err(error) => errors[seat] = Some(error),
+        }
+    }
+
+    // Memory starts out as an empty table, only changes to it are recorded
+    let mut remembered: Vec<_> = entrants
+        .iter()
+        .map(|_| (serde_json::Value::Object(Default::default()), 0))
+        .collect();
+    let mut memory = Vec::new();
+
+    // Stops as soon as an agent fails, so every seat has a sandbox while playing
+    while !game.is_over()
+        && errors.iter().all(Option::is_none)
+        && max_ticks.is_none_or(|max| game.tick() < max)
+    {
+        let mut actions = Vec::with_capacity(agents.len());
+        for &(seat, ref sandbox) in &agents {
+            sandbox.set_tick(u64::from(game.tick()));
+            let decided = decide::<G>(sandbox, &game.observe(seat))
+                .and_then(|action| Ok((action, sandbox.memory()?)));
+            match decided {
+                Ok((action, current)) => {
+                    actions.push(action);
+                    let (last, bytes) = &mut remembered[seat];
+                    if current != *last {
+                        *bytes += current.to_string().len();
+                        if *bytes <= MAX_MEMORY_TRACE_BYTES {
+                            memory.push(MemorySnapshot {
+                                seat: seat as i64,
+                                tick: i64::from(game.tick()),
+                                memory: current.clone(),
+                            });
+                        }
+                        *last = current;
+                    }
+                }
+                Err(error) => errors[seat] = Some(error),
+            }
+        }
+
+        if errors.iter().all(Option::is_none) {
+            let tick = game.tick();
+            game.apply(&actions);
+            if let Some(live) = live {
+                live.send(&Frame::<G>::Tick {
+                    tick,
+                    actions: actions.clone(),
+                });
+            }
+
+            recorder.record(actions, &game);
+            let snapshot = recorder.replay().snapshots.last();
+            if let Some(live) = live
+                && let Some(snapshot) = snapshot.filter(|s| s.tick == game.tick())
+            {
+                live.send(&Frame::Snapshot(snapshot.clone()));
+            }
+        }
+    }
+
+    let (termination, winner) = if errors.iter().any(Option::is_some) {
+        let mut survivors = (0..errors.len()).filter(|&seat| errors[seat].is_none());
+        let winner = match (survivors.next(), survivors.next()) {
+            (Some(seat), None) => Some(seat),
+            _ => None,
+        };
+        (Termination::AgentError, winner)
+    } else {
+        match game.termination() {
+            Some(termination) => (termination.into(), game.winner()),
+            None => (Termination::Timeout, None),
+        }
+    };
+
+    // Seat by seat, then sorted by tick, so lines of one tick stay in seat order
+    let mut logs: Vec<LogLine> = agents
+        .iter()
+        .flat_map(|(seat, sandbox)| {
+            sandbox.take_output().into_iter().map(|printed| LogLine {
+                seat: *seat as i64,
+                tick: printed.tick as i64,
+                message: printed.text,
+            })
+        })
+        .collect();
+    logs.sort_by_key(|line| line.tick);
+
+    if let Some(live) = live {
+        live.send(&Frame::<G>::End);
+    }
+
+    MatchOutcome {
+        winner,
+        scores: game.scores(),
+        ticks: game.tick(),
+        termination,
+        errors,
+        replay: recorder.finish().encode(),
+        logs,
+        memory,
+        game_version: G::VERSION,
+        lua_version: sandbox::lua_version(),
+    }
+}
+
+/// Create a sandbox with the game's API and the agent's code loaded.
+fn start_agent(lua_api: &str, code: &str, limits: Limits) -> Result<Sandbox, AgentError> {
+    let sandbox = Sandbox::new(limits)?;
+    games::load_agent(&sandbox, lua_api, code)?;
+    Ok(sandbox)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Budget;
+    use game_core::{LiveView, Replay};
+
+    const SNAKE_API: &str = include_str!("../games/snake.lua");
+    const ROBOTSUMO_API: &str = include_str!("../games/robotsumo.lua");
+
+    fn entrants(codes: &[&str]) -> Vec<Entrant> {
+        codes
+            .iter()
+            .enumerate()
+            .map(|(seat, code)| Entrant {
+                id: Some(seat as i64 + 1),
+                name: format!("Agent {seat}"),
+                code: code.to_string(),
+            })
+            .collect()
+    }
+
+    fn sumo(agents: &[&str]) -> MatchOutcome {
+        run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(agents),
+            Limits::default(),
+            None,
+            None,
+        )
+    }
+
+    /// Steers towards the opponent, using the last move to know its heading.
+    const PUSHER: &str = r#"
+        local last_x, last_y
+        function think()
+            local x, y = get_position()
+            local ox, oy = get_opponent_position()
+            if last_x then
+                local cross = (x - last_x) * (oy - y) - (y - last_y) * (ox - x)
+                if cross > 0.01 then turn_left() elseif cross < -0.01 then turn_right() end
+            end
+            last_x, last_y = x, y
+            move_forward()
+        end
+    "#;
+    const IDLE: &str = "function think() end";
+
+    #[test]
+    fn idle_robots_time_out() {
+        let outcome = sumo(&[IDLE, IDLE]);
+
+        assert_eq!(outcome.termination, Termination::Timeout);
+        assert_eq!(outcome.winner, None);
+        assert_eq!(outcome.ticks, robotsumo_core::Settings::default().max_ticks);
+        assert!(outcome.errors.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn pusher_wins_by_ring_out() {
+        let outcome = sumo(&[IDLE, PUSHER]);
+
+        assert_eq!(
+            outcome.termination,
+            Termination::Finished(robotsumo_core::RING_OUT.to_string())
+        );
+        assert_eq!(outcome.winner, Some(1));
+        assert_eq!(outcome.scores, vec![0, 1]);
+    }
+
+    #[test]
+    fn match_is_recorded() {
+        let outcome = sumo(&[IDLE, PUSHER]);
+        let replay = Replay::<robotsumo_core::Game>::decode(&outcome.replay).unwrap();
+
+        assert_eq!(replay.header.seed, 1);
+        assert_eq!(replay.header.agents[1].id, Some(2));
+        assert_eq!(replay.header.agents[1].name, "Agent 1");
+        assert_eq!(replay.ticks(), outcome.ticks);
+
+        let end = replay.state_at(outcome.ticks).unwrap();
+        assert_eq!(end.winner(), outcome.winner);
+        assert_eq!(end.scores(), outcome.scores);
+    }
+
+    #[test]
+    fn live_stream_matches_replay() {
+        let live = LiveMatch::new();
+        let (_, mut receiver) = live.subscribe();
+        let outcome = run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(&[IDLE, PUSHER]),
+            Limits::default(),
+            None,
+            Some(&live),
+        );
+        let replay = Replay::<robotsumo_core::Game>::decode(&outcome.replay).unwrap();
+
+        let mut view = LiveView::new();
+        let mut frames = 0;
+        while let Ok(bytes) = receiver.try_recv() {
+            view.receive(Frame::decode(&bytes).unwrap());
+            frames += 1;
+        }
+
+        // Header, every tick, every snapshot and the end
+        let snapshots = replay.snapshots.len();
+        assert_eq!(frames, 1 + outcome.ticks as usize + snapshots + 1);
+        assert!(view.is_ended());
+        assert_eq!(view.header(), Some(&replay.header));
+        assert_eq!(view.game(), replay.state_at(outcome.ticks).as_ref());
+    }
+
+    #[test]
+    fn forfeited_tick_is_not_recorded() {
+        let broken = "function think() error('oops') end";
+        let outcome = sumo(&[PUSHER, broken]);
+        let replay = Replay::<robotsumo_core::Game>::decode(&outcome.replay).unwrap();
+
+        assert_eq!(replay.ticks(), 0);
+        assert_eq!(replay.snapshots.len(), 1);
+    }
+
+    #[test]
+    fn snake_crash_is_a_collision() {
+        let straight = "function think() end";
+        let outcome = run::<snake_core::Game>(
+            SNAKE_API,
+            1,
+            &entrants(&[straight, straight]),
+            Limits::default(),
+            None,
+            None,
+        );
+
+        assert_eq!(
+            outcome.termination,
+            Termination::Finished(snake_core::COLLISION.to_string())
+        );
+        assert_eq!(outcome.scores.len(), 2);
+    }
+
+    #[test]
+    fn runtime_error_forfeits() {
+        let broken = "function think() error('oops') end";
+        let outcome = sumo(&[PUSHER, broken]);
+
+        assert_eq!(outcome.termination, Termination::AgentError);
+        assert_eq!(outcome.winner, Some(0));
+        assert_eq!(outcome.ticks, 0);
+        assert!(outcome.errors[0].is_none());
+        assert!(matches!(outcome.errors[1], Some(AgentError::Runtime(_))));
+    }
+
+    #[test]
+    fn error_while_loading_forfeits() {
+        let outcome = sumo(&["error('at load')", IDLE]);
+
+        assert_eq!(outcome.termination, Termination::AgentError);
+        assert_eq!(outcome.winner, Some(1));
+    }
+
+    #[test]
+    fn missing_think_forfeits() {
+        let outcome = sumo(&[IDLE, "x = 1"]);
+
+        assert!(matches!(
+            outcome.errors[1],
+            Some(AgentError::MissingFunction(_))
+        ));
+    }
+
+    #[test]
+    fn both_failing_is_a_draw() {
+        let broken = "function think() error('oops') end";
+        let outcome = sumo(&[broken, broken]);
+
+        assert_eq!(outcome.termination, Termination::AgentError);
+        assert_eq!(outcome.winner, None);
+    }
+
+    #[test]
+    fn budget_error_reports_tick() {
+        let slow_later = r#"
+            calls = 0
+            function think()
+                calls = calls + 1
+                if calls == 5 then while true do end end
+            end
+        "#;
+        let outcome = sumo(&[IDLE, slow_later]);
+
+        assert_eq!(outcome.ticks, 4);
+        assert!(matches!(
+            outcome.errors[1],
+            Some(AgentError::BudgetExceeded {
+                tick: 4,
+                budget: Budget::Instructions(_)
+            })
+        ));
+    }
+
+    #[test]
+    fn match_stops_at_max_ticks() {
+        let outcome = run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(&[IDLE, IDLE]),
+            Limits::default(),
+            Some(10),
+            None,
+        );
+        let replay = Replay::<robotsumo_core::Game>::decode(&outcome.replay).unwrap();
+
+        assert_eq!(outcome.ticks, 10);
+        assert_eq!(outcome.termination, Termination::Timeout);
+        assert_eq!(outcome.winner, None);
+        assert_eq!(replay.ticks(), 10);
+    }
+
+    #[test]
+    fn printed_lines_are_logged_by_seat_and_tick() {
+        let chatty = "function think() print(observation.tick) end";
+        let outcome = run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(&[chatty, "print('ready') function think() end"]),
+            Limits::default(),
+            Some(2),
+            None,
+        );
+
+        let logs: Vec<(i64, i64, &str)> = outcome
+            .logs
+            .iter()
+            .map(|line| (line.seat, line.tick, line.message.as_str()))
+            .collect();
+        assert_eq!(logs, vec![(0, 0, "0"), (1, 0, "ready"), (0, 1, "1")]);
+    }
+
+    #[test]
+    fn memory_changes_are_recorded() {
+        // Counts up every other tick, so every other snapshot is the same
+        let counter = r#"
+            function think()
+                if observation.tick % 2 == 0 then
+                    memory.count = (memory.count or 0) + 1
+                end
+            end
+        "#;
+        let outcome = run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(&[IDLE, counter]),
+            Limits::default(),
+            Some(5),
+            None,
+        );
+
+        let memory: Vec<(i64, i64, serde_json::Value)> = outcome
+            .memory
+            .into_iter()
+            .map(|snapshot| (snapshot.seat, snapshot.tick, snapshot.memory))
+            .collect();
+        assert_eq!(
+            memory,
+            vec![
+                (1, 0, serde_json::json!({ "count": 1 })),
+                (1, 2, serde_json::json!({ "count": 2 })),
+                (1, 4, serde_json::json!({ "count": 3 })),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_over_the_limit_forfeits() {
+        let hoarder = r#"
+            function think()
+                table.insert(memory, string.rep("x", 1000))
+            end
+        "#;
+        let outcome = sumo(&[IDLE, hoarder]);
+
+        assert_eq!(outcome.termination, Termination::AgentError);
+        assert_eq!(outcome.winner, Some(0));
+        assert!(matches!(
+            outcome.errors[1],
+            Some(AgentError::MemoryTooLarge { tick: 8, .. })
+        ));
+        assert_eq!(outcome.memory.len(), 8);
+    }
+
+    /// Tries to reach the other agent through everything two Lua VMs could share.
+    const TAMPERER: &str = r#"
+        function think()
+            secret = "leaked"
+            string.upper = function() return "pwned" end
+            getmetatable("").__index = { len = function() return -1 end }
+            math.random = function() return 4 end
+            get_opponent_position = function() return 0, 0 end
+            setmetatable(_G, { __index = function() return "hijacked" end })
+            move_forward()
+        end
+    "#;
+
+    /// Prints what it sees of everything the tamperer changed in its own VM.
+    const WITNESS: &str = r#"
+        function think()
+            local x = get_opponent_position()
+            print(tostring(secret), ("a"):upper(), ("abc"):len(), tostring(undefined),
+                math.random(1, 1), tostring(x ~= 0))
+        end
+    "#;
+
+    #[test]
+    fn agents_cannot_reach_each_other() {
+        let outcome = run::<robotsumo_core::Game>(
+            ROBOTSUMO_API,
+            1,
+            &entrants(&[TAMPERER, WITNESS]),
+            Limits::default(),
+            Some(5),
+            None,
+        );
+
+        assert!(outcome.errors.iter().all(Option::is_none));
+        assert_eq!(outcome.logs.len(), 5);
+        for line in &outcome.logs {
+            assert_eq!(line.seat, 1);
+            assert_eq!(line.message, "nil\tA\t3\tnil\t1\ttrue");
+        }
+    }
+
+    #[test]
+    fn tampering_with_observation_forfeits() {
+        let tamperer = "function think() observation.position.x = 0 end";
+        let outcome = sumo(&[tamperer, IDLE]);
+
+        assert_eq!(outcome.termination, Termination::AgentError);
+        assert_eq!(outcome.winner, Some(1));
+        assert!(matches!(
+            &outcome.errors[0],
+            Some(AgentError::Runtime(message)) if message == "attempt to modify a read-only table"
+        ));
+    }
+}
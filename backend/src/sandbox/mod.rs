@@ -0,0 +1,1540 @@
+//! Restricted Lua environment for agent code.
+//!
+//! Every Lua VM the backend creates for an agent goes through [`Sandbox`], so
+//! saving an agent and running it in a match see exactly the same language.
+//! Agents only get a whitelisted part of the standard library: no `io`, `os`,
+//! `debug`, `package` or `coroutine`, and no way to load other code.
+//!
+//! Each call into agent code also runs on a budget: a maximum number of Lua
+//! instructions and a wall-clock deadline. Going over either one stops the
+//! call with [`AgentError::BudgetExceeded`]. The VM as a whole has a memory
+//! limit, and allocations beyond it fail with [`AgentError::OutOfMemory`].
+//! Agents can catch their own errors with `pcall` and `xpcall`, but not a
+//! budget running out.
+//!
+//! Library functions run in C where the budget can't stop them, so the ones
+//! that can take long are wrapped: `string.rep` refuses huge results, and the
+//! pattern functions (`find`, `match`, `gmatch` and `gsub`) charge their
+//! worst-case number of steps to the instruction budget before they run.
+//!
+//! Whatever the agent passes to `print` is kept, up to [`MAX_OUTPUT_BYTES`],
+//! and can be collected with [`Sandbox::take_output`].
+//!
+//! Agents keep whatever they need between ticks in the `memory` global, a
+//! table that starts out empty and lives as long as the VM. After each tick
+//! [`Sandbox::memory`] reads it back as JSON. It may only hold numbers,
+//! strings, booleans and tables of those, and at most [`MAX_MEMORY_BYTES`]
+//! of them, or the agent fails with [`AgentError::MemoryTooLarge`] or
+//! [`AgentError::InvalidMemory`].
+//!
+//! Every sandbox is a Lua VM of its own, nothing is shared between two of
+//! them: not `_G`, not the `string` metatable, not the standard library
+//! tables. Agents in a match each get their own sandbox, so the only data
+//! that reaches them is what the game sets with
+//! [`Sandbox::set_readonly_global`], copied into each VM and read-only.
+
+use crate::models::{AgentError, Budget};
+use mlua::{
+    ChunkMode, FromLuaMulti, Function, HookTriggers, IntoLuaMulti, Lua, LuaOptions, LuaSerdeExt,
+    MultiValue, SerializeOptions, StdLib, Table, Value, VmState,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+type Result<T> = std::result::Result<T, AgentError>;
+
+/// Globals an agent is allowed to see. Everything else is removed after the
+/// libraries are loaded, including `load`, `dofile` and `collectgarbage`.
+const ALLOWED_GLOBALS: &[&str] = &[
+    "_G",
+    "_VERSION",
+    "assert",
+    "error",
+    "getmetatable",
+    "ipairs",
+    "math",
+    "next",
+    "pairs",
+    "pcall",
+    "print",
+    "rawequal",
+    "rawget",
+    "rawlen",
+    "rawset",
+    "select",
+    "setmetatable",
+    "string",
+    "table",
+    "tonumber",
+    "tostring",
+    "type",
+    "utf8",
+    "xpcall",
+];
+
+/// Longest string `string.rep` is allowed to build, in bytes.
+pub const MAX_REPEAT_LENGTH: usize = 64 * 1024;
+
+/// What `getmetatable` returns for read-only tables, so agents can't reach
+/// the real metatable or replace it.
+const READONLY_METATABLE: &str = "read-only";
+
+/// Name used for agent chunks in Lua error messages.
+const CHUNK_NAME: &str = "agent";
+
+/// Number of Lua instructions executed between two budget checks.
+const INSTRUCTIONS_PER_CHECK: u32 = 1000;
+
+/// Default number of Lua instructions a single agent call may execute.
+pub const DEFAULT_INSTRUCTION_LIMIT: u64 = 1_000_000;
+
+/// Default wall-clock time a single agent call may take, in milliseconds.
+pub const DEFAULT_TIME_LIMIT_MS: u64 = 50;
+
+/// Default memory an agent VM may use, in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Most text `print` keeps per VM, in bytes. Later lines are dropped.
+pub const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// Global table agents keep their state in between ticks.
+pub const MEMORY_GLOBAL: &str = "memory";
+
+/// Largest the `memory` table may get, in bytes of its JSON encoding.
+pub const MAX_MEMORY_BYTES: usize = 8 * 1024;
+
+/// Deepest tables may be nested in `memory`. Also stops at tables that
+/// contain themselves.
+const MAX_MEMORY_DEPTH: usize = 32;
+
+/// Resource limits applied to every call into agent code.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Maximum number of Lua instructions per call.
+    pub instructions: u64,
+    /// Maximum wall-clock time per call.
+    pub time: Duration,
+    /// Maximum memory the whole VM may use, in bytes.
+    pub memory: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            instructions: DEFAULT_INSTRUCTION_LIMIT,
+            time: Duration::from_millis(DEFAULT_TIME_LIMIT_MS),
+            memory: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+}
+
+/// Tracks how much of its budget the current call has used.
+struct Meter {
+    instructions: u64,
+    started: Instant,
+    exceeded: Option<Budget>,
+}
+
+impl Meter {
+    fn new() -> Self {
+        Self {
+            instructions: 0,
+            started: Instant::now(),
+            exceeded: None,
+        }
+    }
+
+    /// Add instructions to the current call and check both budgets. Once a
+    /// budget is exceeded every check fails, so the error can't be outrun.
+    fn charge(&mut self, instructions: u64, limits: &Limits) -> mlua::Result<()> {
+        self.instructions = self.instructions.saturating_add(instructions);
+
+        if self.exceeded.is_none() {
+            if self.instructions > limits.instructions {
+                self.exceeded = Some(Budget::Instructions(limits.instructions));
+            } else if self.started.elapsed() > limits.time {
+                self.exceeded = Some(Budget::Time(limits.time.as_millis() as u64));
+            }
+        }
+
+        match self.exceeded {
+            Some(budget) => Err(mlua::Error::runtime(budget.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A line the agent printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printed {
+    /// Tick set with [`Sandbox::set_tick`] when the line was printed
+    pub tick: u64,
+    pub text: String,
+}
+
+/// Lines printed so far, and how many bytes they take.
+#[derive(Default)]
+struct Output {
+    lines: Vec<Printed>,
+    bytes: usize,
+}
+
+/// Turns tables into read-only proxies. A proxy is an empty table whose
+/// metatable reads from the real table and refuses every write.
+struct ReadOnly {
+    /// Every proxy handed out, as weak keys so they can still be collected
+    proxies: Table,
+    /// Metamethods shared by the metatables of all proxies
+    newindex: Function,
+    len: Function,
+    pairs: Function,
+}
+
+impl ReadOnly {
+    fn new(lua: &Lua) -> mlua::Result<Self> {
+        let proxies = lua.create_table()?;
+        let weak = lua.create_table()?;
+        weak.raw_set("__mode", "k")?;
+        proxies.set_metatable(Some(weak))?;
+
+        let newindex = lua.create_function(|_, _: MultiValue| -> mlua::Result<()> {
+            Err(mlua::Error::runtime("attempt to modify a read-only table"))
+        })?;
+        let len = lua.create_function(|_, proxy: Table| Ok(proxied(&proxy)?.raw_len()))?;
+        // Iterates over the real table without handing it out to the agent
+        let next: Function = lua.globals().get("next")?;
+        let proxy_next = lua.create_function(move |_, (proxy, key): (Table, Value)| {
+            next.call::<MultiValue>((proxied(&proxy)?, key))
+        })?;
+        let pairs = lua
+            .create_function(move |_, proxy: Table| Ok((proxy_next.clone(), proxy, Value::Nil)))?;
+
+        Ok(Self {
+            proxies,
+            newindex,
+            len,
+            pairs,
+        })
+    }
+
+    /// Make a value read-only, replacing it and every table in it by a proxy.
+    fn freeze(&self, lua: &Lua, value: Value) -> mlua::Result<Value> {
+        let Value::Table(table) = value else {
+            return Ok(value);
+        };
+
+        let mut nested = Vec::new();
+        for pair in table.pairs::<Value, Value>() {
+            let (key, value) = pair?;
+            if value.is_table() {
+                nested.push((key, value));
+            }
+        }
+        for (key, value) in nested {
+            table.raw_set(key, self.freeze(lua, value)?)?;
+        }
+
+        let metatable = lua.create_table()?;
+        metatable.raw_set("__index", table)?;
+        metatable.raw_set("__newindex", &self.newindex)?;
+        metatable.raw_set("__len", &self.len)?;
+        metatable.raw_set("__pairs", &self.pairs)?;
+        metatable.raw_set("__metatable", READONLY_METATABLE)?;
+        let proxy = lua.create_table()?;
+        proxy.set_metatable(Some(metatable))?;
+        self.proxies.raw_set(&proxy, true)?;
+        Ok(Value::Table(proxy))
+    }
+
+    /// Whether a table is a proxy handed out by [`ReadOnly::freeze`].
+    fn contains(&self, table: &Table) -> mlua::Result<bool> {
+        self.proxies.raw_get(table)
+    }
+}
+
+/// The real table behind a read-only proxy.
+fn proxied(proxy: &Table) -> mlua::Result<Table> {
+    proxy
+        .metatable()
+        .ok_or_else(|| mlua::Error::runtime("not a read-only table"))?
+        .raw_get("__index")
+}
+
+/// A Lua VM with only the agent-safe subset of Lua available.
+pub struct Sandbox {
+    lua: Lua,
+    meter: Rc<RefCell<Meter>>,
+    tick: Rc<Cell<u64>>,
+    output: Rc<RefCell<Output>>,
+    readonly: Rc<ReadOnly>,
+    memory_limit: usize,
+}
+
+impl Sandbox {
+    /// Create a new, empty sandboxed VM.
+    pub fn new(limits: Limits) -> Result<Self> {
+        // The base library is always loaded, these are the extra libraries agents get
+        let libraries = StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8;
+        let lua = Lua::new_with(libraries, LuaOptions::default()).map_err(runtime_error)?;
+        let readonly = Rc::new(ReadOnly::new(&lua).map_err(runtime_error)?);
+        restrict_globals(&lua, readonly.clone()).map_err(runtime_error)?;
+
+        let tick = Rc::new(Cell::new(0));
+        let output = Rc::new(RefCell::new(Output::default()));
+        install_print(&lua, tick.clone(), output.clone()).map_err(runtime_error)?;
+
+        let meter = Rc::new(RefCell::new(Meter::new()));
+        install_budget_hook(&lua, limits, meter.clone()).map_err(runtime_error)?;
+        install_pattern_costs(&lua, limits, meter.clone()).map_err(runtime_error)?;
+        install_protected_calls(&lua, meter.clone()).map_err(runtime_error)?;
+        lua.set_memory_limit(limits.memory).map_err(runtime_error)?;
+        lua.globals()
+            .set(MEMORY_GLOBAL, lua.create_table().map_err(runtime_error)?)
+            .map_err(runtime_error)?;
+
+        Ok(Self {
+            lua,
+            meter,
+            tick,
+            output,
+            readonly,
+            memory_limit: limits.memory,
+        })
+    }
+
+    /// Set the game tick reported in errors from later calls.
+    pub fn set_tick(&self, tick: u64) {
+        self.tick.set(tick);
+    }
+
+    /// Take the lines printed since the last call, oldest first.
+    pub fn take_output(&self) -> Vec<Printed> {
+        std::mem::take(&mut self.output.borrow_mut().lines)
+    }
+
+    /// Compile agent code without running any of it.
+    pub fn compile(&self, code: &str) -> Result<()> {
+        self.compile_chunk(code)?;
+        Ok(())
+    }
+
+    /// Compile and run the top level of agent code, defining its functions.
+    pub fn load(&self, code: &str) -> Result<()> {
+        let chunk = self.compile_chunk(code)?;
+        self.metered(|| chunk.call::<()>(()))
+    }
+
+    /// Memory currently used by the VM, in bytes.
+    pub fn used_memory(&self) -> usize {
+        self.lua.used_memory()
+    }
+
+    /// Call a global function defined by the agent.
+    pub fn call<R: FromLuaMulti>(&self, name: &str, args: impl IntoLuaMulti) -> Result<R> {
+        let function = match self.lua.globals().get::<Value>(name) {
+            Ok(Value::Function(function)) => function,
+            _ => return Err(AgentError::MissingFunction(name.to_string())),
+        };
+        self.metered(|| function.call(args))
+    }
+
+    /// Set a global to a Rust value, converted to Lua tables. `None` becomes `nil`.
+    pub fn set_global<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        let value = self.to_lua(value)?;
+        self.lua
+            .globals()
+            .set(name, value)
+            .map_err(|error| self.lua_error(error))
+    }
+
+    /// Set a global like [`Sandbox::set_global`], but with every table in it
+    /// read-only. Agent code reads it as usual, also with `pairs`, `ipairs`
+    /// and `#`, but every attempt to change it fails.
+    pub fn set_readonly_global<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        let value = self.to_lua(value)?;
+        let value = self
+            .readonly
+            .freeze(&self.lua, value)
+            .map_err(|error| self.lua_error(error))?;
+        self.lua
+            .globals()
+            .set(name, value)
+            .map_err(|error| self.lua_error(error))
+    }
+
+    /// Read a global back into a Rust value.
+    pub fn global<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        let value: Value = self.lua.globals().get(name).map_err(runtime_error)?;
+        self.lua.from_value(value).map_err(runtime_error)
+    }
+
+    /// What the agent has in its `memory` global, as JSON. Tables that are
+    /// sequences become arrays, all other tables objects with their keys
+    /// turned into strings.
+    pub fn memory(&self) -> Result<serde_json::Value> {
+        let value: Value = self
+            .lua
+            .globals()
+            .get(MEMORY_GLOBAL)
+            .map_err(runtime_error)?;
+        let mut entries = 0;
+        let memory = self.memory_json(value, 0, &mut entries)?;
+
+        let bytes = serde_json::to_string(&memory).map_or(usize::MAX, |json| json.len());
+        if bytes > MAX_MEMORY_BYTES {
+            return Err(self.memory_too_large());
+        }
+        Ok(memory)
+    }
+
+    /// Convert a value in `memory` to JSON. Every entry takes at least a
+    /// byte, so counting them stops huge tables before they are converted.
+    fn memory_json(
+        &self,
+        value: Value,
+        depth: usize,
+        entries: &mut usize,
+    ) -> Result<serde_json::Value> {
+        Ok(match value {
+            Value::Nil => serde_json::Value::Null,
+            Value::Boolean(value) => value.into(),
+            Value::Integer(value) => value.into(),
+            Value::Number(value) => serde_json::Number::from_f64(value)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::String(value) => value.to_string_lossy().into(),
+            Value::Table(table) => {
+                if depth >= MAX_MEMORY_DEPTH {
+                    return Err(AgentError::InvalidMemory(format!(
+                        "tables can be nested at most {} deep",
+                        MAX_MEMORY_DEPTH
+                    )));
+                }
+                // A read-only observation stored in memory is saved as what it shows
+                let table = if self.readonly.contains(&table).map_err(runtime_error)? {
+                    proxied(&table).map_err(runtime_error)?
+                } else {
+                    table
+                };
+
+                let mut pairs = Vec::new();
+                for pair in table.pairs::<Value, Value>() {
+                    pairs.push(pair.map_err(runtime_error)?);
+                    *entries += 1;
+                    if *entries > MAX_MEMORY_BYTES {
+                        return Err(self.memory_too_large());
+                    }
+                }
+
+                // `len` different keys that are all in 1..=len make a sequence
+                let len = pairs.len();
+                let index = |key: &Value| {
+                    key.as_integer()
+                        .filter(|&index| index >= 1 && index as usize <= len)
+                        .map(|index| index as usize - 1)
+                };
+                if len > 0 && pairs.iter().all(|(key, _)| index(key).is_some()) {
+                    let mut array = vec![serde_json::Value::Null; len];
+                    for (key, value) in pairs {
+                        let index = index(&key).unwrap_or_default();
+                        array[index] = self.memory_json(value, depth + 1, entries)?;
+                    }
+                    serde_json::Value::Array(array)
+                } else {
+                    let mut object = serde_json::Map::new();
+                    for (key, value) in pairs {
+                        let key = match key {
+                            Value::String(key) => key.to_string_lossy(),
+                            Value::Integer(_) | Value::Number(_) | Value::Boolean(_) => {
+                                key.to_string().map_err(runtime_error)?
+                            }
+                            key => {
+                                return Err(AgentError::InvalidMemory(format!(
+                                    "a {} can't be a key",
+                                    key.type_name()
+                                )));
+                            }
+                        };
+                        object.insert(key, self.memory_json(value, depth + 1, entries)?);
+                    }
+                    serde_json::Value::Object(object)
+                }
+            }
+            value => {
+                return Err(AgentError::InvalidMemory(format!(
+                    "it can only hold numbers, strings, booleans and tables, not a {}",
+                    value.type_name()
+                )));
+            }
+        })
+    }
+
+    /// The error for a `memory` table over [`MAX_MEMORY_BYTES`].
+    fn memory_too_large(&self) -> AgentError {
+        AgentError::MemoryTooLarge {
+            tick: self.tick.get(),
+            limit: MAX_MEMORY_BYTES,
+        }
+    }
+
+    /// Convert a Rust value to Lua, `None` becomes `nil`.
+    fn to_lua<T: Serialize>(&self, value: &T) -> Result<Value> {
+        let options = SerializeOptions::new()
+            .serialize_none_to_null(false)
+            .serialize_unit_to_null(false);
+        self.lua
+            .to_value_with(value, options)
+            .map_err(|error| self.lua_error(error))
+    }
+
+    /// Run a call into agent code with a fresh budget.
+    fn metered<R>(&self, call: impl FnOnce() -> mlua::Result<R>) -> Result<R> {
+        *self.meter.borrow_mut() = Meter::new();
+        let result = call();
+
+        if let Some(budget) = self.meter.borrow_mut().exceeded.take() {
+            return Err(AgentError::BudgetExceeded {
+                tick: self.tick.get(),
+                budget,
+            });
+        }
+        result.map_err(|error| self.lua_error(error))
+    }
+
+    /// Turn an error from the VM into an agent error, recognising memory errors.
+    fn lua_error(&self, error: mlua::Error) -> AgentError {
+        if is_memory_error(&error) {
+            AgentError::OutOfMemory {
+                tick: self.tick.get(),
+                limit: self.memory_limit,
+            }
+        } else {
+            runtime_error(error)
+        }
+    }
+
+    /// Compile agent code into a function. Only source text is accepted, never bytecode.
+    fn compile_chunk(&self, code: &str) -> Result<Function> {
+        self.lua
+            .load(code)
+            .set_name(CHUNK_NAME)
+            .set_mode(ChunkMode::Text)
+            .into_function()
+            .map_err(|error| {
+                if is_memory_error(&error) {
+                    AgentError::OutOfMemory {
+                        tick: self.tick.get(),
+                        limit: self.memory_limit,
+                    }
+                } else {
+                    syntax_error(error)
+                }
+            })
+    }
+}
+
+/// Check that agent code is valid Lua without executing it.
+pub fn check_syntax(code: &str) -> Result<()> {
+    Sandbox::new(Limits::default())?.compile(code)
+}
+
+/// Version of the Lua runtime agents run on, as Lua reports it in `_VERSION`.
+pub fn lua_version() -> &'static str {
+    static VERSION: LazyLock<String> =
+        LazyLock::new(|| Lua::new().globals().get("_VERSION").unwrap_or_default());
+    &VERSION
+}
+
+/// Count instructions and check the clock while agent code runs.
+fn install_budget_hook(lua: &Lua, limits: Limits, meter: Rc<RefCell<Meter>>) -> mlua::Result<()> {
+    let triggers = HookTriggers::new().every_nth_instruction(INSTRUCTIONS_PER_CHECK);
+    lua.set_global_hook(triggers, move |_, _| {
+        meter
+            .borrow_mut()
+            .charge(INSTRUCTIONS_PER_CHECK as u64, &limits)?;
+        Ok(VmState::Continue)
+    })
+}
+
+/// Wrap the pattern functions of `string` so they charge their worst-case
+/// number of steps to the budget first. The hook never runs while they do,
+/// and backtracking patterns can take forever on long subjects.
+fn install_pattern_costs(lua: &Lua, limits: Limits, meter: Rc<RefCell<Meter>>) -> mlua::Result<()> {
+    let string: Table = lua.globals().get("string")?;
+    for name in ["find", "match", "gmatch", "gsub"] {
+        let original: Function = string.get(name)?;
+        let meter = meter.clone();
+        let wrapped = lua.create_function(move |_, args: MultiValue| {
+            // Only `find` has a plain mode, where the pattern is just text
+            let plain = name == "find"
+                && args
+                    .get(3)
+                    .is_some_and(|value| !matches!(value, Value::Nil | Value::Boolean(false)));
+            let subject = string_argument(args.front());
+            let cost = match string_argument(args.get(1)) {
+                _ if plain => subject.len() as u64 + 1,
+                pattern => pattern_cost(&pattern, subject.len()),
+            };
+            meter.borrow_mut().charge(cost, &limits)?;
+            original.call::<MultiValue>(args)
+        })?;
+        string.set(name, wrapped)?;
+    }
+    Ok(())
+}
+
+/// Wrap `pcall` and `xpcall` so they pass on the error of an exceeded budget
+/// instead of catching it. Otherwise a loop around `pcall` could catch it
+/// every time and never stop.
+fn install_protected_calls(lua: &Lua, meter: Rc<RefCell<Meter>>) -> mlua::Result<()> {
+    let globals = lua.globals();
+
+    let pcall: Function = globals.get("pcall")?;
+    let pcall_meter = meter.clone();
+    let wrapped = lua.create_function(move |_, args: MultiValue| {
+        let results = pcall.call::<MultiValue>(args)?;
+        pass_on_exceeded(&pcall_meter)?;
+        Ok(results)
+    })?;
+    globals.set("pcall", wrapped)?;
+
+    // Budget errors are raised from the hook, and Lua runs the message handler
+    // for them with hooks off, so the agent's handler must not run for them
+    let xpcall: Function = globals.get("xpcall")?;
+    let wrapped = lua.create_function(move |lua, mut args: MultiValue| {
+        if let Some(Value::Function(handler)) = args.get(1).cloned() {
+            let meter = meter.clone();
+            let guarded = lua.create_function(move |_, error: MultiValue| {
+                if meter.borrow().exceeded.is_some() {
+                    return Ok(error);
+                }
+                handler.call::<MultiValue>(error)
+            })?;
+            args[1] = Value::Function(guarded);
+        }
+        let results = xpcall.call::<MultiValue>(args)?;
+        pass_on_exceeded(&meter)?;
+        Ok(results)
+    })?;
+    globals.set("xpcall", wrapped)?;
+
+    Ok(())
+}
+
+/// Fail if the current call has gone over its budget.
+fn pass_on_exceeded(meter: &RefCell<Meter>) -> mlua::Result<()> {
+    match meter.borrow().exceeded {
+        Some(budget) => Err(mlua::Error::runtime(budget.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Bytes of a string argument the way Lua sees it, numbers included. Anything
+/// else is left for the library function to complain about.
+fn string_argument(value: Option<&Value>) -> Vec<u8> {
+    match value {
+        Some(Value::String(text)) => text.as_bytes().to_vec(),
+        Some(value @ (Value::Integer(_) | Value::Number(_))) => {
+            value.to_string().unwrap_or_default().into_bytes()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Worst-case number of steps Lua takes to match `pattern` against a subject
+/// of `length` bytes. Every `*`, `+`, `-` and `%b` that has more pattern after
+/// it can backtrack over the whole subject, so each one multiplies the work by
+/// its length, and so does trying the pattern at every position.
+fn pattern_cost(pattern: &[u8], length: usize) -> u64 {
+    let length = length as u64 + 1;
+    let anchored = pattern.first() == Some(&b'^');
+    let mut i = usize::from(anchored);
+    let mut backtracking = 0u32;
+    // Whether the last item was a quantifier nothing but captures came after
+    let mut trailing = false;
+
+    while i < pattern.len() {
+        let quantifiable = match pattern[i] {
+            b'(' | b')' => {
+                i += 1;
+                continue;
+            }
+            b'%' => match pattern.get(i + 1) {
+                Some(b'b') => {
+                    i += 4;
+                    backtracking += 1;
+                    trailing = true;
+                    continue;
+                }
+                Some(b'f') => {
+                    i = skip_set(pattern, i + 2);
+                    false
+                }
+                _ => {
+                    i += 2;
+                    true
+                }
+            },
+            b'[' => {
+                i = skip_set(pattern, i);
+                true
+            }
+            _ => {
+                i += 1;
+                true
+            }
+        };
+
+        trailing = false;
+        if quantifiable && matches!(pattern.get(i), Some(b'*' | b'+' | b'-')) {
+            i += 1;
+            backtracking += 1;
+            trailing = true;
+        } else if quantifiable && pattern.get(i) == Some(&b'?') {
+            i += 1;
+        }
+    }
+
+    // A quantifier at the very end has nothing to backtrack for. With a `$`
+    // after it, the `$` is the last item instead and it does count.
+    if trailing {
+        backtracking -= 1;
+    }
+
+    let starts = if anchored { 1 } else { length };
+    starts.saturating_mul(length.saturating_pow(backtracking))
+}
+
+/// Index just past the set (`[...]`) that starts at `start`. The first
+/// character of a set is always part of it, even if it is a `]`.
+fn skip_set(pattern: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    if pattern.get(i) == Some(&b'^') {
+        i += 1;
+    }
+    loop {
+        match pattern.get(i) {
+            None => return i,
+            Some(b'%') => i += 2,
+            Some(_) => i += 1,
+        }
+        if pattern.get(i) == Some(&b']') {
+            return i + 1;
+        }
+    }
+}
+
+/// Remove every global that is not whitelisted and replace the library
+/// functions that need tighter limits.
+fn restrict_globals(lua: &Lua, readonly: Rc<ReadOnly>) -> mlua::Result<()> {
+    let globals = lua.globals();
+
+    let mut names = Vec::new();
+    for pair in globals.pairs::<String, Value>() {
+        let (name, _) = pair?;
+        if !ALLOWED_GLOBALS.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    for name in names {
+        globals.raw_remove(name)?;
+    }
+
+    let string: mlua::Table = globals.get("string")?;
+    // Bytecode can't be loaded anyway, so there is no reason to produce it
+    string.raw_remove("dump")?;
+    string.set("rep", lua.create_function(capped_rep)?)?;
+
+    // `rawset` skips `__newindex`, so it has to refuse read-only tables itself
+    let rawset = lua.create_function(move |_, (table, key, value): (Table, Value, Value)| {
+        if readonly.contains(&table)? {
+            return Err(mlua::Error::runtime("attempt to modify a read-only table"));
+        }
+        table.raw_set(key, value)?;
+        Ok(table)
+    })?;
+    globals.set("rawset", rawset)?;
+
+    // Fixed seed so agents that use `math.random` behave the same every run
+    let math: mlua::Table = globals.get("math")?;
+    math.get::<Function>("randomseed")?.call::<()>(0)?;
+
+    Ok(())
+}
+
+/// Replace `print` with one that keeps what is printed, tagged with the tick.
+/// Arguments are joined with tabs like the standard `print` does.
+fn install_print(lua: &Lua, tick: Rc<Cell<u64>>, output: Rc<RefCell<Output>>) -> mlua::Result<()> {
+    let print = lua.create_function(move |_, values: mlua::MultiValue| {
+        let mut parts = Vec::with_capacity(values.len());
+        for value in values {
+            parts.push(value.to_string()?);
+        }
+        let text = parts.join("\t");
+
+        let mut output = output.borrow_mut();
+        if output.bytes + text.len() <= MAX_OUTPUT_BYTES {
+            output.bytes += text.len();
+            output.lines.push(Printed {
+                tick: tick.get(),
+                text,
+            });
+        }
+        Ok(())
+    })?;
+    lua.globals().set("print", print)
+}
+
+/// Replacement for `string.rep` that refuses to build huge strings.
+fn capped_rep(
+    lua: &Lua,
+    (text, count, separator): (mlua::String, i64, Option<mlua::String>),
+) -> mlua::Result<mlua::String> {
+    let text = text.as_bytes().to_vec();
+    let separator = separator.map(|s| s.as_bytes().to_vec()).unwrap_or_default();
+
+    if count <= 0 {
+        return lua.create_string("");
+    }
+
+    let count = count as usize;
+    let length = text
+        .len()
+        .saturating_mul(count)
+        .saturating_add(separator.len().saturating_mul(count - 1));
+    // Repeating nothing any number of times is nothing, without looping `count` times
+    if length == 0 {
+        return lua.create_string("");
+    }
+    if length > MAX_REPEAT_LENGTH {
+        return Err(mlua::Error::runtime(format!(
+            "string.rep result is too large (limit is {} characters)",
+            MAX_REPEAT_LENGTH
+        )));
+    }
+
+    let mut result = Vec::with_capacity(length);
+    for i in 0..count {
+        if i > 0 {
+            result.extend_from_slice(&separator);
+        }
+        result.extend_from_slice(&text);
+    }
+    lua.create_string(result)
+}
+
+/// Check whether a Lua error was caused by hitting the memory limit, also when
+/// it happened inside a Rust callback such as `string.rep`.
+fn is_memory_error(error: &mlua::Error) -> bool {
+    match error {
+        mlua::Error::MemoryError(_) => true,
+        mlua::Error::CallbackError { cause, .. } => is_memory_error(cause),
+        _ => false,
+    }
+}
+
+/// Turn a Lua error into a readable message, removing the
+/// `[string "..."]:` prefix if present.
+fn clean_message(error: &mlua::Error) -> String {
+    // Errors raised by Rust callbacks carry a traceback, only the cause is of interest
+    if let mlua::Error::CallbackError { cause, .. } = error {
+        return match cause.as_ref() {
+            mlua::Error::RuntimeError(message) => message.clone(),
+            cause => clean_message(cause),
+        };
+    }
+    let msg = error.to_string();
+    if let Some(pos) = msg.find("]:") {
+        msg[pos + 2..].trim().to_string()
+    } else {
+        msg
+    }
+}
+
+fn syntax_error(error: mlua::Error) -> AgentError {
+    AgentError::InvalidLuaSyntax(clean_message(&error))
+}
+
+fn runtime_error(error: mlua::Error) -> AgentError {
+    AgentError::Runtime(clean_message(&error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(code: &str) -> Result<Sandbox> {
+        let sandbox = Sandbox::new(Limits::default())?;
+        sandbox.load(code)?;
+        Ok(sandbox)
+    }
+
+    #[test]
+    fn check_syntax_accepts_valid_code() {
+        assert!(check_syntax("function think() return 1 end").is_ok());
+    }
+
+    #[test]
+    fn check_syntax_rejects_invalid_code() {
+        assert!(matches!(
+            check_syntax("function broken("),
+            Err(AgentError::InvalidLuaSyntax(_))
+        ));
+    }
+
+    #[test]
+    fn check_syntax_does_not_run_infinite_loop() {
+        // Would hang forever if the code were executed
+        assert!(check_syntax("while true do end").is_ok());
+    }
+
+    #[test]
+    fn check_syntax_does_not_run_top_level_code() {
+        assert!(check_syntax("error('should not run')").is_ok());
+    }
+
+    #[test]
+    fn check_syntax_rejects_bytecode() {
+        let bytecode = "\x1bLua\x54\x00";
+        assert!(matches!(
+            check_syntax(bytecode),
+            Err(AgentError::InvalidLuaSyntax(_))
+        ));
+    }
+
+    #[test]
+    fn lua_version_is_reported() {
+        assert_eq!(lua_version(), "Lua 5.4");
+    }
+
+    #[test]
+    fn call_runs_agent_function() {
+        let sandbox = load("function think(x) return x * 2 end").unwrap();
+        let result: i64 = sandbox.call("think", 21).unwrap();
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn call_missing_function_fails() {
+        let sandbox = load("local x = 1").unwrap();
+        assert!(matches!(
+            sandbox.call::<()>("think", ()),
+            Err(AgentError::MissingFunction(_))
+        ));
+    }
+
+    #[test]
+    fn file_access_is_not_available() {
+        assert!(matches!(
+            load("io.open('/etc/passwd')"),
+            Err(AgentError::Runtime(_))
+        ));
+        assert!(matches!(
+            load("dofile('/etc/passwd')"),
+            Err(AgentError::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn os_is_not_available() {
+        assert!(matches!(
+            load("os.execute('echo hacked')"),
+            Err(AgentError::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn unsafe_globals_are_removed() {
+        let sandbox = load(
+            r#"
+            function check()
+                return io == nil and os == nil and debug == nil and package == nil
+                    and require == nil and load == nil and loadfile == nil
+                    and dofile == nil and collectgarbage == nil
+                    and coroutine == nil and string.dump == nil
+            end
+            "#,
+        )
+        .unwrap();
+        assert!(sandbox.call::<bool>("check", ()).unwrap());
+    }
+
+    #[test]
+    fn globals_round_trip_through_lua() {
+        #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: Option<i32>,
+        }
+
+        let sandbox = load("function check() return point.x == 3 and point.y == nil end").unwrap();
+        sandbox
+            .set_global("point", &Point { x: 3, y: None })
+            .unwrap();
+        assert!(sandbox.call::<bool>("check", ()).unwrap());
+
+        sandbox.load("point = { x = 4, y = 5 }").unwrap();
+        let point: Point = sandbox.global("point").unwrap();
+        assert_eq!(point, Point { x: 4, y: Some(5) });
+    }
+
+    /// An observation like the games hand out, with nested tables and arrays.
+    fn readonly_observation() -> Sandbox {
+        let sandbox = Sandbox::new(Limits::default()).unwrap();
+        let observation = serde_json::json!({
+            "tick": 3,
+            "head": { "x": 1, "y": 2 },
+            "body": [{ "x": 1, "y": 2 }, { "x": 1, "y": 3 }],
+        });
+        sandbox
+            .set_readonly_global("observation", &observation)
+            .unwrap();
+        sandbox
+    }
+
+    #[test]
+    fn readonly_global_reads_like_a_table() {
+        let sandbox = readonly_observation();
+        sandbox
+            .load(
+                r#"
+                function check()
+                    local keys = 0
+                    for _ in pairs(observation) do keys = keys + 1 end
+                    local ys = 0
+                    for _, cell in ipairs(observation.body) do ys = ys + cell.y end
+                    return observation.tick == 3 and observation.head.x == 1
+                        and #observation.body == 2 and keys == 3 and ys == 5
+                        and observation.missing == nil
+                end
+                "#,
+            )
+            .unwrap();
+        assert!(sandbox.call::<bool>("check", ()).unwrap());
+    }
+
+    #[test]
+    fn readonly_global_refuses_writes() {
+        let sandbox = readonly_observation();
+        for code in [
+            "observation.tick = 4",
+            "observation.head.x = 4",
+            "observation.body[3] = {}",
+            "table.insert(observation.body, {})",
+            "table.remove(observation.body)",
+            "table.sort(observation.body, function(a, b) return a.y > b.y end)",
+            "rawset(observation, 'tick', 4)",
+            "rawset(observation.head, 'x', 4)",
+        ] {
+            let error = sandbox.load(code).unwrap_err();
+            assert!(
+                matches!(&error, AgentError::Runtime(message) if message == "attempt to modify a read-only table"),
+                "{code}: {error}"
+            );
+        }
+        sandbox
+            .load("function check() return observation.tick == 3 and #observation.body == 2 end")
+            .unwrap();
+        assert!(sandbox.call::<bool>("check", ()).unwrap());
+    }
+
+    #[test]
+    fn readonly_global_hides_its_metatable() {
+        let sandbox = readonly_observation();
+        let error = sandbox
+            .load("setmetatable(observation.head, { __index = function() return 0 end })")
+            .unwrap_err();
+        assert!(error.to_string().contains("protected metatable"));
+
+        sandbox
+            .load(
+                r#"
+                function check()
+                    local _, state = pairs(observation)
+                    return getmetatable(observation) == "read-only" and state == observation
+                        and rawget(observation, "tick") == nil
+                end
+                "#,
+            )
+            .unwrap();
+        assert!(sandbox.call::<bool>("check", ()).unwrap());
+    }
+
+    #[test]
+    fn rawset_still_works_on_own_tables() {
+        let sandbox = readonly_observation();
+        sandbox
+            .load("function check() local t = rawset({}, 'x', 1) return t.x end")
+            .unwrap();
+        assert_eq!(sandbox.call::<i64>("check", ()).unwrap(), 1);
+    }
+
+    #[test]
+    fn sandboxes_do_not_share_globals() {
+        let tamperer = load(
+            r#"
+            secret = "leaked"
+            _G.think = function() return "hijacked" end
+            setmetatable(_G, { __index = function() return "hijacked" end })
+            math.floor = function() return 0 end
+            table.concat = nil
+            "#,
+        )
+        .unwrap();
+        assert_eq!(tamperer.call::<String>("think", ()).unwrap(), "hijacked");
+
+        let witness = load(
+            r#"
+            function think()
+                return secret == nil and undefined == nil and math.floor(1.5) == 1
+                    and table.concat({ "a", "b" }) == "ab"
+            end
+            "#,
+        )
+        .unwrap();
+        assert!(witness.call::<bool>("think", ()).unwrap());
+    }
+
+    #[test]
+    fn sandboxes_do_not_share_string_metatable() {
+        let tamperer = load(
+            r#"
+            local meta = getmetatable("")
+            meta.__index.upper = function() return "pwned" end
+            meta.__add = function() return "pwned" end
+            meta.__index = { len = function() return -1 end }
+            "#,
+        )
+        .unwrap();
+        tamperer
+            .load("function think() return ('abc'):len() end")
+            .unwrap();
+        assert_eq!(tamperer.call::<i64>("think", ()).unwrap(), -1);
+
+        let witness = load(
+            r#"
+            function think()
+                return ("a"):upper() == "A" and ("abc"):len() == 3 and string.upper("b") == "B"
+                    and "10" + 1 == 11
+            end
+            "#,
+        )
+        .unwrap();
+        assert!(witness.call::<bool>("think", ()).unwrap());
+    }
+
+    #[test]
+    fn memory_starts_empty() {
+        let sandbox = load("function think() return type(memory) end").unwrap();
+        assert_eq!(sandbox.call::<String>("think", ()).unwrap(), "table");
+        assert_eq!(sandbox.memory().unwrap(), serde_json::json!({}));
+    }
+
+    #[test]
+    fn memory_is_kept_between_calls() {
+        let sandbox = load(
+            r#"
+            function think()
+                memory.turns = (memory.turns or 0) + 1
+                memory.path = memory.path or {}
+                table.insert(memory.path, { x = memory.turns, y = 0.5 })
+                memory.seen = memory.seen or {}
+                memory.seen[memory.turns * 10] = true
+            end
+            "#,
+        )
+        .unwrap();
+        sandbox.call::<()>("think", ()).unwrap();
+        sandbox.call::<()>("think", ()).unwrap();
+
+        assert_eq!(
+            sandbox.memory().unwrap(),
+            serde_json::json!({
+                "turns": 2,
+                "path": [{ "x": 1, "y": 0.5 }, { "x": 2, "y": 0.5 }],
+                "seen": { "10": true, "20": true },
+            })
+        );
+    }
+
+    #[test]
+    fn memory_can_hold_the_observation() {
+        let sandbox = readonly_observation();
+        sandbox.load("memory.last = observation.head").unwrap();
+        assert_eq!(
+            sandbox.memory().unwrap(),
+            serde_json::json!({ "last": { "x": 1, "y": 2 } })
+        );
+    }
+
+    #[test]
+    fn memory_over_the_limit_fails() {
+        let sandbox = load("function think() memory.big = string.rep('x', 10000) end").unwrap();
+        sandbox.set_tick(5);
+        sandbox.call::<()>("think", ()).unwrap();
+        assert!(matches!(
+            sandbox.memory(),
+            Err(AgentError::MemoryTooLarge {
+                tick: 5,
+                limit: MAX_MEMORY_BYTES
+            })
+        ));
+
+        let sandbox = load("for i = 1, 100000 do memory[i] = 0 end").unwrap();
+        assert!(matches!(
+            sandbox.memory(),
+            Err(AgentError::MemoryTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn memory_only_holds_plain_values() {
+        for code in ["memory.f = print", "memory[{}] = 1", "memory.me = memory"] {
+            let sandbox = load(code).unwrap();
+            assert!(
+                matches!(sandbox.memory(), Err(AgentError::InvalidMemory(_))),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn memory_tables_with_holes_are_objects() {
+        let sandbox = load("memory = { 'a', nil, 'c', x = 1 } memory[5] = 'e'").unwrap();
+        assert_eq!(
+            sandbox.memory().unwrap(),
+            serde_json::json!({ "1": "a", "3": "c", "5": "e", "x": 1 })
+        );
+    }
+
+    #[test]
+    fn memory_can_be_replaced() {
+        let sandbox = load("memory = { 'a', 'b' }").unwrap();
+        assert_eq!(sandbox.memory().unwrap(), serde_json::json!(["a", "b"]));
+        let sandbox = load("memory = nil").unwrap();
+        assert_eq!(sandbox.memory().unwrap(), serde_json::Value::Null);
+    }
+
+    #[test]
+    fn global_with_wrong_shape_fails() {
+        let sandbox = load("value = 'not a number'").unwrap();
+        assert!(matches!(
+            sandbox.global::<i32>("value"),
+            Err(AgentError::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn string_rep_bomb_is_rejected() {
+        assert!(matches!(
+            load("local s = string.rep('x', 1e10)"),
+            Err(AgentError::Runtime(_))
+        ));
+        assert!(matches!(
+            load("local s = ('x'):rep(1e10)"),
+            Err(AgentError::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn string_rep_of_empty_string_returns_quickly() {
+        let sandbox = load(
+            r#"
+            function think()
+                return #string.rep("", 1e18) + #string.rep("", 1e18, "") + #("").rep("", 2^62)
+            end
+            "#,
+        )
+        .unwrap();
+        let started = Instant::now();
+        assert_eq!(sandbox.call::<i64>("think", ()).unwrap(), 0);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn string_rep_still_works_for_small_strings() {
+        let sandbox = load("function think() return string.rep('ab', 3, '-') end").unwrap();
+        let result: String = sandbox.call("think", ()).unwrap();
+        assert_eq!(result, "ab-ab-ab");
+    }
+
+    #[test]
+    fn backtracking_pattern_exceeds_instruction_budget() {
+        let sandbox = load(
+            r#"
+            function think()
+                return string.find(string.rep("a", 65536), string.rep(".-", 8) .. "b")
+            end
+            "#,
+        )
+        .unwrap();
+        let started = Instant::now();
+        assert!(matches!(
+            sandbox.call::<()>("think", ()),
+            Err(AgentError::BudgetExceeded {
+                budget: Budget::Instructions(DEFAULT_INSTRUCTION_LIMIT),
+                ..
+            })
+        ));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let sandbox = load("function think() return ('a'):rep(60000):gmatch('(a*)%1b')() end");
+        assert!(matches!(
+            sandbox.unwrap().call::<()>("think", ()),
+            Err(AgentError::BudgetExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn patterns_still_work_on_ordinary_strings() {
+        let sandbox = load(
+            r##"
+            function think()
+                local line = string.rep("move 12, ", 50) .. "turn [left]"
+                local count = 0
+                for word in line:gmatch("%a+") do count = count + 1 end
+                local replaced = line:gsub("%d+", "#")
+                return count, #replaced, line:match("%[(.-)%]"), line:find("[left]", 1, true)
+            end
+            "##,
+        )
+        .unwrap();
+        let (count, replaced, direction, found): (i64, i64, String, i64) =
+            sandbox.call("think", ()).unwrap();
+        assert_eq!(count, 52);
+        assert_eq!(replaced, 50 * 8 + 11);
+        assert_eq!(direction, "left");
+        assert_eq!(found, 50 * 9 + 6);
+    }
+
+    #[test]
+    fn pattern_cost_counts_backtracking_quantifiers() {
+        assert_eq!(pattern_cost(b"abc", 9), 10);
+        assert_eq!(pattern_cost(b"^abc", 9), 1);
+        assert_eq!(pattern_cost(b"%d+", 9), 10);
+        assert_eq!(pattern_cost(b"(%d+)", 9), 10);
+        assert_eq!(pattern_cost(b"%d+,", 9), 100);
+        assert_eq!(pattern_cost(b"%d+$", 9), 100);
+        assert_eq!(pattern_cost(b"[a-z]x", 9), 10);
+        assert_eq!(pattern_cost(b"[%]-]*x", 9), 100);
+        assert_eq!(pattern_cost(b"%b()x", 9), 100);
+        assert_eq!(pattern_cost(b"%f[%a]%a+", 9), 10);
+        assert_eq!(pattern_cost(b".-.-.-.-b", 65536), u64::MAX);
+    }
+
+    #[test]
+    fn print_is_allowed() {
+        assert!(load("print('hello', 1, true)").is_ok());
+    }
+
+    #[test]
+    fn print_output_is_kept_with_tick() {
+        let sandbox =
+            load("print('hello', 1, true, nil) function think() print('thinking') end").unwrap();
+        sandbox.set_tick(3);
+        sandbox.call::<()>("think", ()).unwrap();
+
+        let output = sandbox.take_output();
+        assert_eq!(
+            output,
+            vec![
+                Printed {
+                    tick: 0,
+                    text: "hello\t1\ttrue\tnil".to_string()
+                },
+                Printed {
+                    tick: 3,
+                    text: "thinking".to_string()
+                },
+            ]
+        );
+        assert!(sandbox.take_output().is_empty());
+    }
+
+    #[test]
+    fn print_output_is_capped() {
+        let sandbox = load("function think() print(string.rep('x', 1000)) end").unwrap();
+        for _ in 0..100 {
+            sandbox.call::<()>("think", ()).unwrap();
+        }
+
+        let bytes: usize = sandbox
+            .take_output()
+            .iter()
+            .map(|line| line.text.len())
+            .sum();
+        assert!(bytes <= MAX_OUTPUT_BYTES);
+        assert!(bytes > MAX_OUTPUT_BYTES - 1000);
+    }
+
+    #[test]
+    fn math_random_is_reproducible() {
+        let code = "function think() return math.random(1, 1000000) end";
+        let first: i64 = load(code).unwrap().call("think", ()).unwrap();
+        let second: i64 = load(code).unwrap().call("think", ()).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn infinite_loop_exceeds_instruction_budget() {
+        let sandbox = load("function think() while true do end end").unwrap();
+        sandbox.set_tick(7);
+        assert!(matches!(
+            sandbox.call::<()>("think", ()),
+            Err(AgentError::BudgetExceeded {
+                tick: 7,
+                budget: Budget::Instructions(DEFAULT_INSTRUCTION_LIMIT)
+            })
+        ));
+    }
+
+    #[test]
+    fn infinite_loop_at_top_level_is_stopped() {
+        assert!(matches!(
+            load("while true do end"),
+            Err(AgentError::BudgetExceeded { tick: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn slow_call_exceeds_time_budget() {
+        let limits = Limits {
+            instructions: u64::MAX,
+            time: Duration::from_millis(20),
+            ..Limits::default()
+        };
+        let sandbox = Sandbox::new(limits).unwrap();
+        sandbox
+            .load("function think() while true do end end")
+            .unwrap();
+        assert!(matches!(
+            sandbox.call::<()>("think", ()),
+            Err(AgentError::BudgetExceeded {
+                budget: Budget::Time(20),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn pcall_catches_agent_errors() {
+        let sandbox = load(
+            r#"
+            function think()
+                local ok, message = pcall(error, "boom", 0)
+                local handled = select(2, xpcall(function() error("bang", 0) end, string.upper))
+                return ok, message, handled
+            end
+            "#,
+        )
+        .unwrap();
+        let result: (bool, String, String) = sandbox.call("think", ()).unwrap();
+        assert_eq!(result, (false, "boom".to_string(), "BANG".to_string()));
+    }
+
+    #[test]
+    fn pcall_does_not_catch_exceeded_budget() {
+        for code in [
+            "function think() pcall(function() while true do end end) return 1 end",
+            "function think() while true do pcall(function() while true do end end) end end",
+            "function think() xpcall(function() while true do end end, print) return 1 end",
+            "function think() xpcall(error, function() while true do end end) return 1 end",
+        ] {
+            let sandbox = load(code).unwrap();
+            let started = Instant::now();
+            assert!(
+                matches!(
+                    sandbox.call::<Option<i64>>("think", ()),
+                    Err(AgentError::BudgetExceeded {
+                        budget: Budget::Instructions(DEFAULT_INSTRUCTION_LIMIT),
+                        ..
+                    })
+                ),
+                "{}",
+                code
+            );
+            assert!(started.elapsed() < Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn budget_resets_between_calls() {
+        let limits = Limits {
+            instructions: 50_000,
+            ..Limits::default()
+        };
+        let sandbox = Sandbox::new(limits).unwrap();
+        sandbox
+            .load("function think() local x = 0 for i = 1, 5000 do x = x + i end return x end")
+            .unwrap();
+
+        // Each call fits the budget, even though all calls together would not
+        for _ in 0..20 {
+            assert_eq!(sandbox.call::<i64>("think", ()).unwrap(), 12_502_500);
+        }
+    }
+
+    #[test]
+    fn call_works_after_budget_was_exceeded() {
+        let sandbox = load(
+            r#"
+            function spin() while true do end end
+            function think() return 1 end
+            "#,
+        )
+        .unwrap();
+        assert!(sandbox.call::<()>("spin", ()).is_err());
+        assert_eq!(sandbox.call::<i64>("think", ()).unwrap(), 1);
+    }
+
+    fn small_memory_limits() -> Limits {
+        Limits {
+            instructions: u64::MAX,
+            time: Duration::from_secs(10),
+            memory: 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn growing_table_runs_out_of_memory() {
+        let sandbox = Sandbox::new(small_memory_limits()).unwrap();
+        sandbox
+            .load("function think() local t = {} for i = 1, 1e9 do t[i] = i end end")
+            .unwrap();
+        sandbox.set_tick(42);
+        assert!(matches!(
+            sandbox.call::<()>("think", ()),
+            Err(AgentError::OutOfMemory {
+                tick: 42,
+                limit: 1048576
+            })
+        ));
+    }
+
+    #[test]
+    fn growing_string_runs_out_of_memory() {
+        let sandbox = Sandbox::new(small_memory_limits()).unwrap();
+        assert!(matches!(
+            sandbox.load("local s = 'x' while true do s = s .. s end"),
+            Err(AgentError::OutOfMemory { tick: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn memory_is_released_after_out_of_memory() {
+        let sandbox = Sandbox::new(small_memory_limits()).unwrap();
+        sandbox
+            .load(
+                r#"
+                function hog() local t = {} for i = 1, 1e9 do t[i] = i end end
+                function think() return #string.rep('a', 100) end
+                "#,
+            )
+            .unwrap();
+        assert!(sandbox.call::<()>("hog", ()).is_err());
+        assert_eq!(sandbox.call::<i64>("think", ()).unwrap(), 100);
+        assert!(sandbox.used_memory() <= 1024 * 1024);
+    }
+}
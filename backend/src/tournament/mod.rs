@@ -0,0 +1,226 @@
+//! Tournaments between agents of one game.
+//!
+//! An admin creates a tournament, enrolls agents at a fixed version and
+//! starts it. Entrants are seeded by the rating of their version, and every
+//! round is played as head-to-head matches on the job queue. Once the last
+//! match of a round is played, [`advance`] pairs the next one as described in
+//! [`pairing`], until the format says the tournament is over.
+//!
+//! A match that fails counts as a draw, so one broken agent can't stall a
+//! tournament.
+
+pub mod pairing;
+
+use crate::models::{
+    JobStatus, Tournament, TournamentDetails, TournamentEntrant, TournamentError,
+    TournamentPairing, TournamentRound, TournamentStanding, TournamentStatus,
+};
+use crate::prelude::*;
+use crate::rating::DEFAULT_RATING;
+use crate::repositories::{NewPairing, RatingRepository, TournamentRepository};
+use pairing::{Outcome, Pairing};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+
+/// Seed the entrants of a pending tournament by rating and schedule the
+/// first round.
+pub async fn start(state: &AppState, tournament: &Tournament) -> Result<()> {
+    let repo = TournamentRepository::new(&state.db);
+    let entrants = repo.find_entrants(tournament.id).await?;
+    if entrants.len() < 2 {
+        return Err(TournamentError::TooFewEntrants.into());
+    }
+
+    let ratings = RatingRepository::new(&state.db);
+    let mut seeded = Vec::with_capacity(entrants.len());
+    for entrant in &entrants {
+        let rating = match entrant.agent_id {
+            Some(agent_id) => ratings.find(agent_id, entrant.agent_version).await?,
+            None => None,
+        };
+        let rating = rating.map_or(DEFAULT_RATING, |rating| rating.rating);
+        seeded.push((entrant.id, rating));
+    }
+    // Stable, so equal ratings keep the enrollment order
+    seeded.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let entrant_ids: Vec<i64> = seeded.iter().map(|(id, _)| *id).collect();
+    if !repo.start(tournament.id, &entrant_ids).await? {
+        return Err(TournamentError::AlreadyStarted.into());
+    }
+    advance(state, tournament.id).await
+}
+
+/// Schedule the next round of a running tournament once every match of the
+/// current one has been played, or finish it after the last round. Safe to
+/// call at any time, and from several workers at once.
+pub async fn advance(state: &AppState, tournament_id: i64) -> Result<()> {
+    let repo = TournamentRepository::new(&state.db);
+    let Some(tournament) = repo.find_by_id(tournament_id).await? else {
+        return Ok(());
+    };
+    if tournament.status != TournamentStatus::Running {
+        return Ok(());
+    }
+
+    let entrants = repo.find_entrants(tournament_id).await?;
+    let pairings = repo.find_pairings(tournament_id).await?;
+    let played = played_rounds(&entrants, &pairings);
+    if played.iter().flatten().any(|pairing| !pairing.is_done()) {
+        return Ok(());
+    }
+
+    let rounds = tournament.rounds.map(|rounds| rounds as usize);
+    let Some(next) = pairing::next_round(tournament.format, entrants.len(), rounds, &played) else {
+        return repo.finish(tournament_id).await;
+    };
+
+    let hasher = RandomState::new();
+    let next: Vec<NewPairing> = next
+        .iter()
+        .enumerate()
+        .map(|(i, pairing)| NewPairing {
+            first: &entrants[pairing.first],
+            second: pairing.second.map(|second| &entrants[second]),
+            bracket: pairing.bracket,
+            // Only the lower 63 bits, so the seed fits in an SQLite integer
+            seed: (hasher.hash_one((tournament_id, tournament.current_round, i)) >> 1) as i64,
+        })
+        .collect();
+    if repo.schedule_round(&tournament, &next).await? {
+        state.queue.notify();
+    }
+    Ok(())
+}
+
+/// Advance the tournament a finished or failed match was played in, if any.
+pub async fn match_done(state: &AppState, match_id: i64) -> Result<()> {
+    let tournament_id = TournamentRepository::new(&state.db)
+        .find_by_match(match_id)
+        .await?;
+    match tournament_id {
+        Some(tournament_id) => advance(state, tournament_id).await,
+        None => Ok(()),
+    }
+}
+
+/// Advance every running tournament, for rounds that ended while the server
+/// was down.
+pub async fn resume(state: &AppState) -> Result<()> {
+    let ids = TournamentRepository::new(&state.db).find_running().await?;
+    for id in ids {
+        advance(state, id).await?;
+    }
+    Ok(())
+}
+
+/// A tournament with its entrants, standings and progress.
+pub async fn details(db: &SqlitePool, tournament: Tournament) -> Result<TournamentDetails> {
+    let repo = TournamentRepository::new(db);
+    let entrants = repo.find_entrants(tournament.id).await?;
+    let pairings = repo.find_pairings(tournament.id).await?;
+
+    let played = played_rounds(&entrants, &pairings);
+    let standings = pairing::standings(tournament.format, entrants.len(), &played)
+        .into_iter()
+        .enumerate()
+        .map(|(rank, standing)| {
+            let entrant = &entrants[standing.entrant];
+            TournamentStanding {
+                rank: rank as i64 + 1,
+                entrant_id: entrant.id,
+                agent_id: entrant.agent_id,
+                agent_name: entrant.agent_name.clone(),
+                points: standing.points,
+                wins: standing.wins as i64,
+                draws: standing.draws as i64,
+                losses: standing.losses as i64,
+                byes: standing.byes as i64,
+                buchholz: standing.buchholz,
+                eliminated_in: standing.eliminated_in.map(|round| round as i64),
+            }
+        })
+        .collect();
+
+    let matches = pairings.iter().filter(|pairing| pairing.match_id.is_some());
+    let matches_total = matches.clone().count() as i64;
+    let matches_played = matches
+        .filter(|pairing| {
+            matches!(
+                pairing.status,
+                Some(JobStatus::Finished | JobStatus::Failed)
+            )
+        })
+        .count() as i64;
+
+    Ok(TournamentDetails {
+        tournament,
+        entrants,
+        standings,
+        matches_total,
+        matches_played,
+    })
+}
+
+/// The pairings of a tournament grouped by round.
+pub async fn bracket(db: &SqlitePool, tournament_id: i64) -> Result<Vec<TournamentRound>> {
+    let pairings = TournamentRepository::new(db)
+        .find_pairings(tournament_id)
+        .await?;
+
+    let mut rounds: Vec<TournamentRound> = Vec::new();
+    for pairing in pairings {
+        match rounds.last_mut() {
+            Some(round) if round.round == pairing.round => round.pairings.push(pairing),
+            _ => rounds.push(TournamentRound {
+                round: pairing.round,
+                pairings: vec![pairing],
+            }),
+        }
+    }
+    Ok(rounds)
+}
+
+/// The stored pairings as rounds for [`pairing`], with entrants by their
+/// position in `entrants`.
+fn played_rounds(
+    entrants: &[TournamentEntrant],
+    pairings: &[TournamentPairing],
+) -> Vec<Vec<Pairing>> {
+    let positions: HashMap<i64, usize> = entrants
+        .iter()
+        .enumerate()
+        .map(|(position, entrant)| (entrant.id, position))
+        .collect();
+
+    let mut rounds: Vec<Vec<Pairing>> = Vec::new();
+    for stored in pairings {
+        let round = stored.round as usize;
+        if rounds.len() < round {
+            rounds.resize(round, Vec::new());
+        }
+        rounds[round - 1].push(Pairing {
+            first: positions[&stored.first_entrant_id],
+            second: stored.second_entrant_id.map(|id| positions[&id]),
+            bracket: stored.bracket,
+            outcome: outcome(stored),
+        });
+    }
+    rounds
+}
+
+/// How a stored pairing ended, `None` for a bye or while its match is played.
+fn outcome(pairing: &TournamentPairing) -> Option<Outcome> {
+    let second = pairing.second_entrant_id?;
+    match pairing.status {
+        Some(JobStatus::Queued | JobStatus::Running) => None,
+        Some(JobStatus::Finished) => Some(match pairing.winner_entrant_id {
+            Some(winner) if winner == pairing.first_entrant_id => Outcome::FirstWon,
+            Some(winner) if winner == second => Outcome::SecondWon,
+            _ => Outcome::Draw,
+        }),
+        // Failed matches, and pairings without a match, are draws
+        Some(JobStatus::Failed) | None => Some(Outcome::Draw),
+    }
+}
@@ -0,0 +1,548 @@
+//! Pairings and standings of the tournament formats.
+//!
+//! Entrants are identified by their position in the seeding, 0 being the top
+//! seed. Nothing here touches the database: given the rounds played so far,
+//! [`next_round`] pairs the next one and [`standings`] ranks the entrants.
+
+use crate::models::{TournamentBracket, TournamentFormat};
+use std::collections::HashSet;
+
+/// How a pairing ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    FirstWon,
+    SecondWon,
+    Draw,
+}
+
+/// Two entrants of a round, or one entrant sitting it out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pairing {
+    pub first: usize,
+    /// `None` for a bye
+    pub second: Option<usize>,
+    pub bracket: Option<TournamentBracket>,
+    /// `None` for a bye or while the match is being played
+    pub outcome: Option<Outcome>,
+}
+
+impl Pairing {
+    fn new(first: usize, second: usize, bracket: Option<TournamentBracket>) -> Self {
+        Self {
+            first,
+            second: Some(second),
+            bracket,
+            outcome: None,
+        }
+    }
+
+    fn bye(entrant: usize, bracket: Option<TournamentBracket>) -> Self {
+        Self {
+            first: entrant,
+            second: None,
+            bracket,
+            outcome: None,
+        }
+    }
+
+    /// Whether the pairing needs no more play.
+    pub fn is_done(&self) -> bool {
+        self.second.is_none() || self.outcome.is_some()
+    }
+
+    /// The entrant that lost, for knocking entrants out. A draw counts as a
+    /// loss for the lower seed.
+    fn loser(&self) -> Option<usize> {
+        let second = self.second?;
+        match self.outcome? {
+            Outcome::FirstWon => Some(second),
+            Outcome::SecondWon => Some(self.first),
+            Outcome::Draw => Some(self.first.max(second)),
+        }
+    }
+}
+
+/// Losses that knock an entrant out, for elimination formats.
+fn max_losses(format: TournamentFormat) -> Option<usize> {
+    match format {
+        TournamentFormat::SingleElimination => Some(1),
+        TournamentFormat::DoubleElimination => Some(2),
+        TournamentFormat::RoundRobin | TournamentFormat::Swiss => None,
+    }
+}
+
+/// Rounds of a Swiss tournament when not set: enough for one entrant to
+/// be the only one winning every match.
+pub fn default_swiss_rounds(entrants: usize) -> usize {
+    (usize::BITS - entrants.saturating_sub(1).leading_zeros()).max(1) as usize
+}
+
+/// Pair the next round after `played`, or `None` once the tournament is over.
+/// Every pairing of `played` must be done. `rounds` is the number of rounds
+/// of a Swiss tournament.
+pub fn next_round(
+    format: TournamentFormat,
+    entrants: usize,
+    rounds: Option<usize>,
+    played: &[Vec<Pairing>],
+) -> Option<Vec<Pairing>> {
+    if entrants < 2 {
+        return None;
+    }
+    match format {
+        TournamentFormat::RoundRobin => round_robin(entrants, played.len()),
+        TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => {
+            let double = format == TournamentFormat::DoubleElimination;
+            elimination(entrants, double, played)
+        }
+        TournamentFormat::Swiss => {
+            let rounds = rounds.unwrap_or_else(|| default_swiss_rounds(entrants));
+            if played.len() >= rounds {
+                None
+            } else {
+                Some(swiss(entrants, played))
+            }
+        }
+    }
+}
+
+/// Round `round` of the circle method: the first entrant stays put while the
+/// others rotate one step every round.
+fn round_robin(entrants: usize, round: usize) -> Option<Vec<Pairing>> {
+    // An odd number of entrants gets an empty slot, and a bye against it
+    let slots = entrants + entrants % 2;
+    if round >= slots - 1 {
+        return None;
+    }
+
+    let mut order: Vec<Option<usize>> = (0..slots).map(|e| (e < entrants).then_some(e)).collect();
+    order[1..].rotate_right(round);
+
+    let pairings = (0..slots / 2)
+        .filter_map(|i| match (order[i], order[slots - 1 - i]) {
+            // Alternate who takes the first seat
+            (Some(a), Some(b)) if (round + i) % 2 == 1 => Some(Pairing::new(b, a, None)),
+            (Some(a), Some(b)) => Some(Pairing::new(a, b, None)),
+            (Some(e), None) | (None, Some(e)) => Some(Pairing::bye(e, None)),
+            (None, None) => None,
+        })
+        .collect();
+    Some(pairings)
+}
+
+/// Pair the entrants that are still in, those with the same number of losses
+/// against each other. The bracket is reseeded every round: the best seed
+/// left plays the worst, and the best seed gets the bye when the count is
+/// odd. The last two entrants play each other whatever their losses, so a
+/// double elimination final is replayed when the winners bracket loses it.
+fn elimination(entrants: usize, double: bool, played: &[Vec<Pairing>]) -> Option<Vec<Pairing>> {
+    let max_losses = if double { 2 } else { 1 };
+    let mut losses = vec![0; entrants];
+    for pairing in played.iter().flatten() {
+        if let Some(loser) = pairing.loser() {
+            losses[loser] += 1;
+        }
+    }
+
+    let mut groups = vec![Vec::new(); max_losses];
+    for (entrant, &lost) in losses.iter().enumerate() {
+        if lost < max_losses {
+            groups[lost].push(entrant);
+        }
+    }
+
+    let remaining: Vec<usize> = groups.concat();
+    match remaining.len() {
+        0 | 1 => None,
+        2 => {
+            let bracket = double.then_some(TournamentBracket::Final);
+            Some(vec![Pairing::new(remaining[0], remaining[1], bracket)])
+        }
+        _ => {
+            let mut pairings = Vec::new();
+            for (lost, group) in groups.iter().enumerate() {
+                let bracket = double.then_some(if lost == 0 {
+                    TournamentBracket::Winners
+                } else {
+                    TournamentBracket::Losers
+                });
+                let mut group = group.as_slice();
+                if group.len() % 2 == 1 {
+                    pairings.push(Pairing::bye(group[0], bracket));
+                    group = &group[1..];
+                }
+                for i in 0..group.len() / 2 {
+                    pairings.push(Pairing::new(group[i], group[group.len() - 1 - i], bracket));
+                }
+            }
+            Some(pairings)
+        }
+    }
+}
+
+/// Tries at pairing a Swiss round without rematches before giving up on it.
+const SWISS_PAIRING_BUDGET: usize = 10_000;
+
+/// Pair entrants with similar points, top down, avoiding rematches where
+/// possible. The lowest ranked entrant without a bye sits out an odd round.
+fn swiss(entrants: usize, played: &[Vec<Pairing>]) -> Vec<Pairing> {
+    let ranked = standings(TournamentFormat::Swiss, entrants, played);
+    let mut order: Vec<usize> = ranked.iter().map(|standing| standing.entrant).collect();
+
+    let mut bye = None;
+    if order.len() % 2 == 1 {
+        let position = ranked
+            .iter()
+            .rposition(|standing| standing.byes == 0)
+            .unwrap_or(order.len() - 1);
+        bye = Some(order.remove(position));
+    }
+
+    let met: HashSet<(usize, usize)> = played
+        .iter()
+        .flatten()
+        .filter_map(|pairing| {
+            let second = pairing.second?;
+            Some((pairing.first.min(second), pairing.first.max(second)))
+        })
+        .collect();
+
+    // Neighbours in the ranking play each other if everyone has met
+    let mut budget = SWISS_PAIRING_BUDGET;
+    let pairs = pair_without_rematches(&order, &met, &mut budget)
+        .unwrap_or_else(|| order.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+
+    let mut pairings: Vec<Pairing> = pairs
+        .into_iter()
+        .map(|(first, second)| Pairing::new(first, second, None))
+        .collect();
+    pairings.extend(bye.map(|entrant| Pairing::bye(entrant, None)));
+    pairings
+}
+
+/// Pair `order` top down, each entrant with the highest ranked one it hasn't
+/// met, backtracking when that leaves entrants who have all met. `None` if
+/// there is no such pairing, or `budget` tries weren't enough to find it.
+fn pair_without_rematches(
+    order: &[usize],
+    met: &HashSet<(usize, usize)>,
+    budget: &mut usize,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&first, rest)) = order.split_first() else {
+        return Some(Vec::new());
+    };
+    for (i, &second) in rest.iter().enumerate() {
+        if met.contains(&(first.min(second), first.max(second))) {
+            continue;
+        }
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+        if let Some(mut pairs) = pair_without_rematches(&remaining, met, budget) {
+            pairs.insert(0, (first, second));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// How an entrant has done so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub entrant: usize,
+    /// 1 for a win and 0.5 for a draw. Byes count as wins in Swiss tournaments.
+    pub points: f64,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub byes: usize,
+    /// Sum of the points of every opponent played
+    pub buchholz: f64,
+    /// Round the entrant was knocked out in, starting at 1
+    pub eliminated_in: Option<usize>,
+}
+
+/// Rank the entrants, best first. Elimination formats rank entrants still in
+/// first, then by how long they lasted. Other formats rank by points. Ties
+/// are broken by Buchholz score, then wins, then seed.
+pub fn standings(
+    format: TournamentFormat,
+    entrants: usize,
+    played: &[Vec<Pairing>],
+) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = (0..entrants)
+        .map(|entrant| Standing {
+            entrant,
+            points: 0.0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            byes: 0,
+            buchholz: 0.0,
+            eliminated_in: None,
+        })
+        .collect();
+    let max_losses = max_losses(format);
+    let mut knockouts = vec![0; entrants];
+
+    for (round, pairings) in played.iter().enumerate() {
+        for pairing in pairings {
+            let Some(second) = pairing.second else {
+                let standing = &mut standings[pairing.first];
+                standing.byes += 1;
+                if format == TournamentFormat::Swiss {
+                    standing.points += 1.0;
+                }
+                continue;
+            };
+            let (winner, loser) = match pairing.outcome {
+                Some(Outcome::FirstWon) => (pairing.first, second),
+                Some(Outcome::SecondWon) => (second, pairing.first),
+                Some(Outcome::Draw) => {
+                    for entrant in [pairing.first, second] {
+                        standings[entrant].draws += 1;
+                        standings[entrant].points += 0.5;
+                    }
+                    (pairing.first, second)
+                }
+                None => continue,
+            };
+            if pairing.outcome != Some(Outcome::Draw) {
+                standings[winner].wins += 1;
+                standings[winner].points += 1.0;
+                standings[loser].losses += 1;
+            }
+
+            if let (Some(max_losses), Some(knocked)) = (max_losses, pairing.loser()) {
+                knockouts[knocked] += 1;
+                if knockouts[knocked] == max_losses {
+                    standings[knocked].eliminated_in = Some(round + 1);
+                }
+            }
+        }
+    }
+
+    for pairing in played.iter().flatten() {
+        if let (Some(second), Some(_)) = (pairing.second, pairing.outcome) {
+            let (first_points, second_points) =
+                (standings[pairing.first].points, standings[second].points);
+            standings[pairing.first].buchholz += second_points;
+            standings[second].buchholz += first_points;
+        }
+    }
+
+    standings.sort_by(|a, b| {
+        let lasted = |standing: &Standing| {
+            (
+                standing.eliminated_in.is_none(),
+                standing.eliminated_in.unwrap_or(0),
+            )
+        };
+        let by_lasting = if max_losses.is_some() {
+            lasted(b).cmp(&lasted(a))
+        } else {
+            std::cmp::Ordering::Equal
+        };
+        by_lasting
+            .then(b.points.total_cmp(&a.points))
+            .then(b.buchholz.total_cmp(&a.buchholz))
+            .then(b.wins.cmp(&a.wins))
+            .then(a.entrant.cmp(&b.entrant))
+    });
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play a round, the lower seed of every pairing winning.
+    fn favourites_win(mut round: Vec<Pairing>) -> Vec<Pairing> {
+        for pairing in &mut round {
+            if let Some(second) = pairing.second {
+                pairing.outcome = Some(if pairing.first < second {
+                    Outcome::FirstWon
+                } else {
+                    Outcome::SecondWon
+                });
+            }
+        }
+        round
+    }
+
+    /// Play a tournament to the end, returning its rounds.
+    fn play_out(format: TournamentFormat, entrants: usize) -> Vec<Vec<Pairing>> {
+        let mut played = Vec::new();
+        while let Some(round) = next_round(format, entrants, None, &played) {
+            played.push(favourites_win(round));
+            assert!(played.len() < 100, "tournament never ends");
+        }
+        played
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for entrants in [2, 5, 6] {
+            let played = play_out(TournamentFormat::RoundRobin, entrants);
+
+            let mut met = HashSet::new();
+            for pairing in played.iter().flatten() {
+                if let Some(second) = pairing.second {
+                    assert!(met.insert((pairing.first.min(second), pairing.first.max(second))));
+                }
+            }
+            assert_eq!(met.len(), entrants * (entrants - 1) / 2);
+        }
+    }
+
+    #[test]
+    fn round_robin_with_odd_entrants_has_byes() {
+        let played = play_out(TournamentFormat::RoundRobin, 3);
+
+        assert_eq!(played.len(), 3);
+        assert!(played.iter().all(|round| round.len() == 2));
+        let ranked = standings(TournamentFormat::RoundRobin, 3, &played);
+        assert!(ranked.iter().all(|standing| standing.byes == 1));
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[0].points, 2.0);
+    }
+
+    #[test]
+    fn single_elimination_knocks_out_losers() {
+        let played = play_out(TournamentFormat::SingleElimination, 5);
+
+        // 5 entrants: 1 bye and 2 matches, 1 bye and 1 match, the final
+        assert_eq!(played.len(), 3);
+        assert_eq!(played[0][0], Pairing::bye(0, None));
+        assert_eq!(played[0][1].first, 1);
+        assert_eq!(played[0][1].second, Some(4));
+
+        let ranked = standings(TournamentFormat::SingleElimination, 5, &played);
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[0].eliminated_in, None);
+        assert_eq!(ranked[1].eliminated_in, Some(3));
+        assert_eq!(ranked[4].eliminated_in, Some(1));
+    }
+
+    #[test]
+    fn elimination_draw_knocks_out_lower_seed() {
+        let mut round = next_round(TournamentFormat::SingleElimination, 2, None, &[]).unwrap();
+        round[0].outcome = Some(Outcome::Draw);
+
+        assert!(
+            next_round(
+                TournamentFormat::SingleElimination,
+                2,
+                None,
+                &[round.clone()]
+            )
+            .is_none()
+        );
+        let ranked = standings(TournamentFormat::SingleElimination, 2, &[round]);
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[1].eliminated_in, Some(1));
+    }
+
+    #[test]
+    fn double_elimination_needs_two_losses() {
+        let played = play_out(TournamentFormat::DoubleElimination, 4);
+
+        let ranked = standings(TournamentFormat::DoubleElimination, 4, &played);
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[0].losses, 0);
+        assert!(ranked[1..].iter().all(|standing| standing.losses == 2));
+        assert!(
+            played[1]
+                .iter()
+                .any(|p| p.bracket == Some(TournamentBracket::Losers))
+        );
+        let last = played.last().unwrap();
+        assert_eq!(last[0].bracket, Some(TournamentBracket::Final));
+    }
+
+    #[test]
+    fn double_elimination_final_is_replayed_after_an_upset() {
+        let mut played =
+            vec![next_round(TournamentFormat::DoubleElimination, 2, None, &[]).unwrap()];
+        played[0][0].outcome = Some(Outcome::SecondWon);
+
+        // The unbeaten entrant loses the final, so it is played again
+        for _ in 0..2 {
+            let last = next_round(TournamentFormat::DoubleElimination, 2, None, &played).unwrap();
+            assert_eq!(last.len(), 1);
+            assert_eq!(last[0].bracket, Some(TournamentBracket::Final));
+            played.push(favourites_win(last));
+        }
+
+        assert!(next_round(TournamentFormat::DoubleElimination, 2, None, &played).is_none());
+        let ranked = standings(TournamentFormat::DoubleElimination, 2, &played);
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[1].eliminated_in, Some(3));
+    }
+
+    #[test]
+    fn swiss_plays_set_rounds_without_rematches() {
+        let mut played = Vec::new();
+        while let Some(round) = next_round(TournamentFormat::Swiss, 6, Some(3), &played) {
+            played.push(favourites_win(round));
+        }
+
+        assert_eq!(played.len(), 3);
+        let mut met = HashSet::new();
+        for pairing in played.iter().flatten() {
+            let second = pairing.second.unwrap();
+            assert!(met.insert((pairing.first.min(second), pairing.first.max(second))));
+        }
+        let ranked = standings(TournamentFormat::Swiss, 6, &played);
+        assert_eq!(ranked[0].entrant, 0);
+        assert_eq!(ranked[0].points, 3.0);
+    }
+
+    #[test]
+    fn swiss_bye_goes_to_lowest_ranked_once() {
+        let played = play_out(TournamentFormat::Swiss, 5);
+
+        assert_eq!(played.len(), default_swiss_rounds(5));
+        assert_eq!(played[0].last().unwrap(), &Pairing::bye(4, None));
+        let ranked = standings(TournamentFormat::Swiss, 5, &played);
+        assert!(ranked.iter().all(|standing| standing.byes <= 1));
+    }
+
+    #[test]
+    fn default_swiss_rounds_grow_with_entrants() {
+        assert_eq!(default_swiss_rounds(2), 1);
+        assert_eq!(default_swiss_rounds(4), 2);
+        assert_eq!(default_swiss_rounds(5), 3);
+        assert_eq!(default_swiss_rounds(16), 4);
+    }
+
+    #[test]
+    fn ties_are_broken_by_buchholz() {
+        // 0, 1 and 2 end with a point each, but 2 only beat the pointless 3
+        let played = vec![
+            vec![Pairing {
+                outcome: Some(Outcome::FirstWon),
+                ..Pairing::new(0, 1, None)
+            }],
+            vec![Pairing {
+                outcome: Some(Outcome::FirstWon),
+                ..Pairing::new(2, 3, None)
+            }],
+            vec![Pairing {
+                outcome: Some(Outcome::FirstWon),
+                ..Pairing::new(1, 3, None)
+            }],
+        ];
+
+        let ranked = standings(TournamentFormat::RoundRobin, 4, &played);
+
+        let order: Vec<usize> = ranked.iter().map(|standing| standing.entrant).collect();
+        assert_eq!(order, vec![0, 1, 2, 3]);
+        assert_eq!(ranked[1].points, ranked[2].points);
+        assert!(ranked[1].buchholz > ranked[2].buchholz);
+    }
+}
@@ -0,0 +1,107 @@
+//! Checks that finished matches play out the same way again.
+//!
+//! Matches are deterministic: the same seed, game version, Lua runtime and
+//! agent versions give the same replay, byte for byte. All of them are
+//! recorded with every match, and [`verify`] plays a finished match again
+//! from them and compares the new replay with the stored one. When a result
+//! is disputed, a replay that is still identical shows that nothing but the
+//! agents' code decided it.
+//!
+//! A match only replays identically on the versions it was played with.
+//! Agents that ran out of their time budget may also play differently, as
+//! that budget is measured in wall-clock time.
+
+use crate::models::{JobStatus, MatchError, MatchVerification};
+use crate::prelude::*;
+use crate::queue;
+use crate::repositories::{GameRepository, MatchRepository};
+use tracing::warn;
+
+/// Play a finished match again and compare its replay with the stored one.
+pub async fn verify(state: &AppState, match_id: i64) -> Result<MatchVerification> {
+    let matches = MatchRepository::new(&state.db);
+    let found = matches.find_by_id(match_id).await?.ok_or(Error::NotFound)?;
+    if found.status != JobStatus::Finished {
+        return Err(MatchError::NotPlayed.into());
+    }
+    let stored = matches
+        .find_replay(match_id)
+        .await?
+        .ok_or(MatchError::NotPlayed)?;
+
+    let game = GameRepository::new(&state.db)
+        .find_by_id(found.game_id)
+        .await?
+        .ok_or(MatchError::UnknownGame)?;
+    let mut entrants = queue::load_entrants(state, &found).await?;
+
+    let limits = state.config.sandbox_limits(&game);
+    let games = state.games.clone();
+    let seed = found.seed as u64;
+    let (outcome, stored) = tokio::task::spawn_blocking(move || {
+        let rules = games.get(&game.name).ok_or(MatchError::UnknownGame)?;
+        // Agents may have been renamed since, play under the recorded names
+        if let Some(recorded) = rules.replay_agents(&stored)
+            && recorded.len() == entrants.len()
+        {
+            for (entrant, agent) in entrants.iter_mut().zip(recorded) {
+                entrant.name = agent.name;
+            }
+        }
+        let outcome = rules.run(seed, &entrants, limits, None, None);
+        Ok::<_, MatchError>((outcome, stored))
+    })
+    .await??;
+
+    let first_difference = first_difference(&stored, &outcome.replay);
+    if let Some(offset) = first_difference {
+        warn!(
+            "Match {} played differently when replayed, from byte {} of its replay",
+            match_id, offset
+        );
+    }
+
+    Ok(MatchVerification {
+        match_id,
+        identical: first_difference.is_none(),
+        first_difference: first_difference.map(|offset| offset as i64),
+        stored_size: stored.len() as i64,
+        replayed_size: outcome.replay.len() as i64,
+        game_version: found.game_version,
+        lua_version: found.lua_version,
+        replayed_game_version: outcome.game_version.to_string(),
+        replayed_lua_version: outcome.lua_version.to_string(),
+    })
+}
+
+/// Offset of the first byte where two replays differ, `None` if they are
+/// the same. A replay that is cut short differs where it ends.
+fn first_difference(stored: &[u8], replayed: &[u8]) -> Option<usize> {
+    stored
+        .iter()
+        .zip(replayed)
+        .position(|(a, b)| a != b)
+        .or_else(|| (stored.len() != replayed.len()).then(|| stored.len().min(replayed.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_replays_have_no_difference() {
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(first_difference(&[], &[]), None);
+    }
+
+    #[test]
+    fn first_differing_byte_is_found() {
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 4, 5]), Some(1));
+    }
+
+    #[test]
+    fn shorter_replay_differs_where_it_ends() {
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 2]), Some(2));
+        assert_eq!(first_difference(&[1], &[1, 2]), Some(1));
+    }
+}
@@ -4,7 +4,7 @@ mod common;
 
 use axum_extra::extract::cookie::Cookie;
 use axum_test::TestServer;
-use backend::models::Agent;
+use backend::models::{Agent, AgentDiff, AgentDraft, AgentVersion};
 use backend::prelude::AppState;
 use backend::repositories::{GameRepository, UserRepository};
 use backend::routes;
@@ -252,6 +252,26 @@ async fn create_agent_with_invalid_lua_syntax_fails() {
     response.assert_status_bad_request();
 }
 
+#[tokio::test]
+async fn create_agent_with_infinite_loop_does_not_run_code() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+
+    // Saving only compiles the code, so this returns instead of hanging
+    let response = server
+        .post("/agents")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({
+            "game_id": game_id,
+            "name": "Looping Agent",
+            "code": "while true do end"
+        }))
+        .await;
+
+    response.assert_status_ok();
+}
+
 // ============================================================================
 // List Agents Tests
 // ============================================================================
@@ -579,6 +599,396 @@ async fn update_agent_with_empty_code_fails() {
     response.assert_status_bad_request();
 }
 
+// ============================================================================
+// Draft Tests
+// ============================================================================
+
+/// Helper to create an agent through the API.
+async fn create_agent(server: &TestServer, token: &str, game_id: i64, code: &str) -> Agent {
+    server
+        .post("/agents")
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&json!({
+            "game_id": game_id,
+            "name": "My Agent",
+            "code": code
+        }))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn save_draft_with_broken_code_succeeds() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token, game_id, "-- working").await;
+
+    let response = server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": "function think(" }))
+        .await;
+
+    response.assert_status_ok();
+    let draft: AgentDraft = response.json();
+    assert_eq!(draft.code, "function think(");
+
+    let response = server
+        .get(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+    let draft: AgentDraft = response.json();
+    assert_eq!(draft.code, "function think(");
+
+    // The agent keeps its published code and shows it has a draft
+    let agents: Vec<Agent> = server
+        .get(&format!("/agents?game_id={}", game_id))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert_eq!(agents[0].code, "-- working");
+    assert!(agents[0].has_draft);
+}
+
+#[tokio::test]
+async fn save_too_large_draft_fails() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token, game_id, "-- working").await;
+
+    let response = server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": "-".repeat(100_000) }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn publish_draft_creates_version() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token, game_id, "-- working").await;
+    server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": "-- better" }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/agents/{}/draft/publish", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let published: Agent = response.json();
+    assert_eq!(published.code, "-- better");
+    assert_eq!(published.version, 2);
+    assert!(!published.has_draft);
+
+    let response = server
+        .get(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn publish_broken_draft_fails() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token, game_id, "-- working").await;
+    server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": "function think(" }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/agents/{}/draft/publish", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_bad_request();
+
+    let current: Agent = server
+        .get(&format!("/agents/{}", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert_eq!(current.code, "-- working");
+    assert_eq!(current.version, 1);
+    assert!(current.has_draft);
+}
+
+#[tokio::test]
+async fn discard_draft_succeeds() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token, game_id, "-- working").await;
+    server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": "-- unfinished" }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .delete(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+
+    let response = server
+        .delete(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn other_users_draft_is_not_found() {
+    let (server, state) = setup_server().await;
+    let (_user1_id, token1) = create_user_with_token(&state, "user1").await;
+    let (_user2_id, token2) = create_user_with_token(&state, "user2").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent(&server, &token1, game_id, "-- working").await;
+    server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token1))
+        .json(&json!({ "code": "-- secret" }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token2.clone()))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server
+        .put(&format!("/agents/{}/draft", agent.id))
+        .add_cookie(Cookie::new("token", token2.clone()))
+        .json(&json!({ "code": "-- hacked" }))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server
+        .post(&format!("/agents/{}/draft/publish", agent.id))
+        .add_cookie(Cookie::new("token", token2))
+        .await;
+    response.assert_status_not_found();
+}
+
+// ============================================================================
+// Agent Version Tests
+// ============================================================================
+
+/// Helper to create an agent and save new code for it, returning the agent.
+async fn create_agent_with_two_versions(server: &TestServer, token: &str, game_id: i64) -> Agent {
+    let created: Agent = server
+        .post("/agents")
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&json!({
+            "game_id": game_id,
+            "name": "My Agent",
+            "code": "-- first"
+        }))
+        .await
+        .json();
+
+    server
+        .put(&format!("/agents/{}", created.id))
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&json!({ "code": "-- second" }))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn update_agent_code_creates_version() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+    assert_eq!(agent.version, 2);
+
+    let response = server
+        .get(&format!("/agents/{}/versions", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_ok();
+    let versions: Vec<AgentVersion> = response.json();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0].version, 2);
+    assert_eq!(versions[0].code, "-- second");
+    assert_eq!(versions[1].version, 1);
+    assert_eq!(versions[1].code, "-- first");
+}
+
+#[tokio::test]
+async fn get_agent_version_returns_old_code() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let version: AgentVersion = response.json();
+    assert_eq!(version.code, "-- first");
+
+    let response = server
+        .get(&format!("/agents/{}/versions/3", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn restore_agent_version_saves_new_version() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+
+    let response = server
+        .post(&format!("/agents/{}/versions/1/restore", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let restored: Agent = response.json();
+    assert_eq!(restored.code, "-- first");
+    assert_eq!(restored.version, 3);
+
+    let versions: Vec<AgentVersion> = server
+        .get(&format!("/agents/{}/versions", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert_eq!(versions.len(), 3);
+}
+
+#[tokio::test]
+async fn diff_agent_versions_returns_changes() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1/diff/2", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let diff: AgentDiff = response.json();
+    assert_eq!((diff.from, diff.to), (1, 2));
+    assert_eq!(
+        diff.unified,
+        "--- version 1\n+++ version 2\n@@ -1,1 +1,1 @@\n--- first\n+-- second\n"
+    );
+    assert_eq!(diff.hunks.len(), 1);
+
+    let response = server
+        .get(&format!("/agents/{}/versions/2/diff/2", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    let diff: AgentDiff = response.json();
+    assert!(diff.hunks.is_empty());
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1/diff/5", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn diff_of_very_long_versions_fails() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+
+    server
+        .put(&format!("/agents/{}", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": "x = 1\n".repeat(3000) }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1/diff/3", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn update_agent_with_too_large_code_fails() {
+    let (server, state) = setup_server().await;
+    let (_user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token, game_id).await;
+
+    let response = server
+        .put(&format!("/agents/{}", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": "-".repeat(64 * 1024 + 1) }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn other_users_agent_versions_are_not_found() {
+    let (server, state) = setup_server().await;
+    let (_user1_id, token1) = create_user_with_token(&state, "user1").await;
+    let (_user2_id, token2) = create_user_with_token(&state, "user2").await;
+    let game_id = get_robotsumo_game_id(&state).await;
+    let agent = create_agent_with_two_versions(&server, &token1, game_id).await;
+
+    let response = server
+        .get(&format!("/agents/{}/versions", agent.id))
+        .add_cookie(Cookie::new("token", token2.clone()))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1", agent.id))
+        .add_cookie(Cookie::new("token", token2.clone()))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server
+        .get(&format!("/agents/{}/versions/1/diff/2", agent.id))
+        .add_cookie(Cookie::new("token", token2.clone()))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server
+        .post(&format!("/agents/{}/versions/1/restore", agent.id))
+        .add_cookie(Cookie::new("token", token2))
+        .await;
+    response.assert_status_not_found();
+}
+
 // ============================================================================
 // Delete Agent Tests
 // ============================================================================
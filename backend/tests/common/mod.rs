@@ -1,6 +1,8 @@
 //! Common test utilities for integration tests.
 
 use backend::prelude::{Claims, Config};
+use backend::sandbox;
+use backend::{ladder, playground};
 use chrono::Duration;
 use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
 
@@ -10,6 +12,14 @@ pub fn test_config() -> Config {
         database_url: ":memory:".to_string(),
         server_port: 0,
         jwt_secret: "test-secret-key-for-testing-only".to_string(),
+        agent_instruction_limit: sandbox::DEFAULT_INSTRUCTION_LIMIT,
+        agent_time_limit_ms: sandbox::DEFAULT_TIME_LIMIT_MS,
+        job_workers: 1,
+        ladder_interval_secs: 0,
+        ladder_rating_window: ladder::DEFAULT_RATING_WINDOW,
+        ladder_daily_match_cap: ladder::DEFAULT_DAILY_MATCH_CAP,
+        playground_runs_per_minute: playground::DEFAULT_RUNS_PER_MINUTE,
+        playground_max_running: playground::DEFAULT_MAX_RUNNING,
     }
 }
 
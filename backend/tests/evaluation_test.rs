@@ -0,0 +1,355 @@
+//! Integration tests for evaluation endpoints.
+
+mod common;
+
+use axum_extra::extract::cookie::Cookie;
+use axum_test::TestServer;
+use backend::models::{Evaluation, JobStatus, Match};
+use backend::prelude::AppState;
+use backend::repositories::{AgentRepository, GameRepository, UserRepository};
+use backend::{evaluation, queue, routes};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Steers towards the opponent, using the last move to know its heading.
+const PUSHER: &str = r#"
+    local last_x, last_y
+    function think()
+        local x, y = get_position()
+        local ox, oy = get_opponent_position()
+        if last_x then
+            local cross = (x - last_x) * (oy - y) - (y - last_y) * (ox - x)
+            if cross > 0.01 then turn_left() elseif cross < -0.01 then turn_right() end
+        end
+        last_x, last_y = x, y
+        move_forward()
+    end
+"#;
+const IDLE: &str = "function think() end";
+
+/// Helper to create a test server with the job queue running.
+async fn setup_server() -> (TestServer, AppState) {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    queue::start(&state)
+        .await
+        .expect("Failed to start job queue");
+    let app = routes::routes().with_state(state.clone());
+    let server = TestServer::new(app).unwrap();
+    (server, state)
+}
+
+/// Helper to create a test user and return their ID and token.
+async fn create_user_with_token(state: &AppState, username: &str) -> (i64, String) {
+    let repo = UserRepository::new(&state.db);
+    let user = repo
+        .create(username, "Password123!", false)
+        .await
+        .expect("Failed to create user");
+    let token = common::create_test_token(user.id, false, username, &state.config.jwt_secret);
+    (user.id, token)
+}
+
+/// Helper to create an agent of a seeded game directly in the database.
+async fn create_agent(state: &AppState, user_id: i64, game: &str, name: &str, code: &str) -> i64 {
+    let game = GameRepository::new(&state.db)
+        .find_by_name(game)
+        .await
+        .expect("Failed to query game")
+        .expect("game should exist");
+    let agent = AgentRepository::new(&state.db)
+        .create(user_id, game.id, name, code)
+        .await
+        .expect("Failed to create agent");
+    agent.id
+}
+
+/// Helper to queue an evaluation and wait until the workers have played it.
+async fn evaluate(server: &TestServer, token: &str, body: Value) -> Evaluation {
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    let created: Evaluation = response.json();
+    assert_eq!(created.status, JobStatus::Queued);
+
+    for _ in 0..1000 {
+        let found: Evaluation = server
+            .get(&format!("/evaluations/{}", created.id))
+            .add_cookie(Cookie::new("token", token.to_string()))
+            .await
+            .json();
+        if matches!(found.status, JobStatus::Finished | JobStatus::Failed) {
+            return found;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("evaluation {} was not played in time", created.id);
+}
+
+// ============================================================================
+// Create Evaluation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn evaluation_reports_better_agent() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let pusher = create_agent(&state, user_id, "robotsumo", "Pusher", PUSHER).await;
+    let idle = create_agent(&state, user_id, "robotsumo", "Idle", IDLE).await;
+
+    let evaluation = evaluate(
+        &server,
+        &token,
+        json!({ "agent_a_id": pusher, "agent_b_id": idle, "seeds": 3, "seed": 11 }),
+    )
+    .await;
+
+    assert_eq!(evaluation.status, JobStatus::Finished);
+    assert_eq!(evaluation.seed, 11);
+    assert_eq!(evaluation.agent_a_version, 1);
+    let wins = evaluation.wins.unwrap();
+    let draws = evaluation.draws.unwrap();
+    let losses = evaluation.losses.unwrap();
+    assert_eq!(wins + draws + losses, 6);
+    assert!(wins > losses);
+    let mean = evaluation.mean_score_diff.unwrap();
+    assert!(mean > 0.0);
+    assert!(evaluation.ci_low.unwrap() <= mean);
+    assert!(evaluation.ci_high.unwrap() >= mean);
+
+    // The matches of an evaluation stay out of the match history
+    let matches: Vec<Match> = server
+        .get(&format!("/matches?agent_id={}", pusher))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert!(matches.is_empty());
+}
+
+#[tokio::test]
+async fn evaluation_compares_versions_of_one_agent() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let agent = create_agent(&state, user_id, "robotsumo", "Robot", IDLE).await;
+    server
+        .put(&format!("/agents/{}", agent))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({ "code": PUSHER }))
+        .await
+        .assert_status_ok();
+
+    let evaluation = evaluate(
+        &server,
+        &token,
+        json!({ "agent_a_id": agent, "agent_b_id": agent, "agent_b_version": 1, "seeds": 2 }),
+    )
+    .await;
+
+    assert_eq!(evaluation.status, JobStatus::Finished);
+    assert_eq!(evaluation.agent_a_version, 2);
+    assert_eq!(evaluation.agent_b_version, 1);
+    assert!(evaluation.wins.unwrap() > evaluation.losses.unwrap());
+}
+
+#[tokio::test]
+async fn evaluation_of_other_users_agent_fails() {
+    let (server, state) = setup_server().await;
+    let (alice_id, _) = create_user_with_token(&state, "alice").await;
+    let (bob_id, bob_token) = create_user_with_token(&state, "bob").await;
+    let alices = create_agent(&state, alice_id, "robotsumo", "Alices", PUSHER).await;
+    let bobs = create_agent(&state, bob_id, "robotsumo", "Bobs", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", bob_token))
+        .json(&json!({ "agent_a_id": alices, "agent_b_id": bobs, "seeds": 2 }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn evaluation_against_old_version_of_other_users_agent_fails() {
+    let (server, state) = setup_server().await;
+    let (alice_id, alice_token) = create_user_with_token(&state, "alice").await;
+    let (bob_id, bob_token) = create_user_with_token(&state, "bob").await;
+    let alices = create_agent(&state, alice_id, "robotsumo", "Alices", IDLE).await;
+    server
+        .put(&format!("/agents/{}", alices))
+        .add_cookie(Cookie::new("token", alice_token))
+        .json(&json!({ "code": PUSHER }))
+        .await
+        .assert_status_ok();
+    let bobs = create_agent(&state, bob_id, "robotsumo", "Bobs", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", bob_token.clone()))
+        .json(&json!({
+            "agent_a_id": bobs,
+            "agent_b_id": alices,
+            "agent_b_version": 1,
+            "seeds": 2
+        }))
+        .await;
+    response.assert_status_bad_request();
+
+    // The current version is fair game
+    let evaluation = evaluate(
+        &server,
+        &bob_token,
+        json!({ "agent_a_id": bobs, "agent_b_id": alices, "agent_b_version": 2, "seeds": 2 }),
+    )
+    .await;
+    assert_eq!(evaluation.status, JobStatus::Finished);
+}
+
+#[tokio::test]
+async fn evaluation_with_too_few_seeds_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let agent = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_a_id": agent, "agent_b_id": agent, "seeds": 1 }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn evaluation_with_overflowing_seed_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let agent = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({
+            "agent_a_id": agent,
+            "agent_b_id": agent,
+            "seeds": 10,
+            "seed": i64::MAX - 1
+        }))
+        .await;
+    response.assert_status_bad_request();
+
+    // Nothing was queued that could never be played
+    let response = server
+        .get("/evaluations")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    assert!(response.json::<Vec<Evaluation>>().is_empty());
+}
+
+#[tokio::test]
+async fn evaluation_of_different_games_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let snake = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+    let robot = create_agent(&state, user_id, "robotsumo", "Robot", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_a_id": snake, "agent_b_id": robot }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn evaluation_with_unknown_version_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let agent = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_a_id": agent, "agent_a_version": 5, "agent_b_id": agent }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn too_many_pending_evaluations_fail() {
+    // Without workers, so the evaluations stay queued
+    let state = AppState::new(common::test_config(), common::test_db().await);
+    let server = TestServer::new(routes::routes().with_state(state.clone())).unwrap();
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let agent = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+    let body = json!({ "agent_a_id": agent, "agent_b_id": agent, "seeds": 2 });
+
+    for _ in 0..evaluation::MAX_PENDING {
+        server
+            .post("/evaluations")
+            .add_cookie(Cookie::new("token", token.clone()))
+            .json(&body)
+            .await
+            .assert_status_ok();
+    }
+    let response = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", token))
+        .json(&body)
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+// ============================================================================
+// Get Evaluation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn evaluations_are_private() {
+    let (server, state) = setup_server().await;
+    let (user_id, alice_token) = create_user_with_token(&state, "alice").await;
+    let (_, bob_token) = create_user_with_token(&state, "bob").await;
+    let agent = create_agent(&state, user_id, "snake", "Slither", IDLE).await;
+
+    let created: Evaluation = server
+        .post("/evaluations")
+        .add_cookie(Cookie::new("token", alice_token.clone()))
+        .json(&json!({ "agent_a_id": agent, "agent_b_id": agent, "seeds": 2 }))
+        .await
+        .json();
+
+    server
+        .get(&format!("/evaluations/{}", created.id))
+        .add_cookie(Cookie::new("token", bob_token.clone()))
+        .await
+        .assert_status_not_found();
+
+    let mine: Vec<Evaluation> = server
+        .get("/evaluations")
+        .add_cookie(Cookie::new("token", alice_token))
+        .await
+        .json();
+    assert_eq!(mine.len(), 1);
+    let theirs: Vec<Evaluation> = server
+        .get("/evaluations")
+        .add_cookie(Cookie::new("token", bob_token))
+        .await
+        .json();
+    assert!(theirs.is_empty());
+}
+
+#[tokio::test]
+async fn evaluation_without_auth_fails() {
+    let (server, _) = setup_server().await;
+
+    server
+        .get("/evaluations")
+        .await
+        .assert_status_unauthorized();
+}
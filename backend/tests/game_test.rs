@@ -1,9 +1,11 @@
 mod common;
 
+use axum_extra::extract::cookie::Cookie;
 use axum_test::TestServer;
-use backend::models::Game;
+use backend::models::{Difficulty, Game, HouseBot, Leaderboard, MatchOutcome, Termination};
 use backend::prelude::AppState;
-use backend::routes;
+use backend::repositories::{AgentRepository, GameRepository, MatchRepository, UserRepository};
+use backend::{bots, routes};
 
 #[tokio::test]
 async fn list_games_returns_seeded_games() {
@@ -49,3 +51,251 @@ async fn get_game_by_invalid_name_returns_not_found() {
     let response = server.get("/games/nonexistent").await;
     response.assert_status_not_found();
 }
+
+// ============================================================================
+// Leaderboard Tests
+// ============================================================================
+
+/// Create a user with two robot sumo agents and store rated matches between
+/// them, the first agent winning `first_wins` of them.
+async fn play_rated_matches(state: &AppState, first_wins: usize, matches: usize) -> [i64; 2] {
+    let user = UserRepository::new(&state.db)
+        .create("sumoist", "TestPass123!", false)
+        .await
+        .unwrap();
+    let game = GameRepository::new(&state.db)
+        .find_by_name("robotsumo")
+        .await
+        .unwrap()
+        .unwrap();
+    let agent_repo = AgentRepository::new(&state.db);
+    let first = agent_repo
+        .create(user.id, game.id, "First", "-- first")
+        .await
+        .unwrap();
+    let second = agent_repo
+        .create(user.id, game.id, "Second", "-- second")
+        .await
+        .unwrap();
+
+    let match_repo = MatchRepository::new(&state.db);
+    for i in 0..matches {
+        let created = match_repo
+            .create(game.id, user.id, 1, &[first.id, second.id], true)
+            .await
+            .unwrap();
+        let winner = if i < first_wins { 0 } else { 1 };
+        let outcome = MatchOutcome {
+            winner: Some(winner),
+            scores: vec![u32::from(winner == 0), u32::from(winner == 1)],
+            ticks: 10,
+            termination: Termination::Finished(robotsumo_core::RING_OUT.to_string()),
+            errors: vec![None, None],
+            replay: Vec::new(),
+            logs: Vec::new(),
+            memory: Vec::new(),
+            game_version: "1.0.0",
+            lua_version: "Lua 5.4",
+        };
+        match_repo.finish(created.id, &outcome).await.unwrap();
+    }
+    [first.id, second.id]
+}
+
+#[tokio::test]
+async fn get_leaderboard_ranks_agents() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let agents = play_rated_matches(&state, 3, 4).await;
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/games/robotsumo/leaderboard").await;
+    response.assert_status_ok();
+
+    let leaderboard: Leaderboard = response.json();
+    assert_eq!(leaderboard.game, "robotsumo");
+    assert_eq!(leaderboard.total, 2);
+    let first = &leaderboard.entries[0];
+    assert_eq!(first.rank, 1);
+    assert_eq!(first.agent_id, agents[0]);
+    assert_eq!(first.agent_name, "First");
+    assert_eq!(first.owner, "sumoist");
+    assert_eq!((first.wins, first.losses, first.draws), (3, 1, 0));
+    assert_eq!(first.games, 4);
+}
+
+#[tokio::test]
+async fn get_leaderboard_is_paginated() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let agents = play_rated_matches(&state, 0, 2).await;
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .get("/games/robotsumo/leaderboard?page=2&per_page=1")
+        .await;
+    response.assert_status_ok();
+
+    let leaderboard: Leaderboard = response.json();
+    assert_eq!((leaderboard.page, leaderboard.per_page), (2, 1));
+    assert_eq!(leaderboard.total, 2);
+    assert_eq!(leaderboard.entries.len(), 1);
+    assert_eq!(leaderboard.entries[0].rank, 2);
+    assert_eq!(leaderboard.entries[0].agent_id, agents[0]);
+}
+
+#[tokio::test]
+async fn get_leaderboard_filters_by_min_games() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    play_rated_matches(&state, 1, 2).await;
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .get("/games/robotsumo/leaderboard?min_games=3&days=7")
+        .await;
+    response.assert_status_ok();
+
+    let leaderboard: Leaderboard = response.json();
+    assert_eq!(leaderboard.total, 0);
+    assert!(leaderboard.entries.is_empty());
+}
+
+#[tokio::test]
+async fn get_leaderboard_counts_a_match_once_per_agent() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let [first, second] = play_rated_matches(&state, 1, 2).await;
+
+    // A match with the first agent in two seats, one of which wins
+    let game = GameRepository::new(&state.db)
+        .find_by_name("robotsumo")
+        .await
+        .unwrap()
+        .unwrap();
+    let match_repo = MatchRepository::new(&state.db);
+    let created = match_repo
+        .create(game.id, 1, 1, &[first, second, first], true)
+        .await
+        .unwrap();
+    let outcome = MatchOutcome {
+        winner: Some(2),
+        scores: vec![0, 0, 1],
+        ticks: 10,
+        termination: Termination::Finished(robotsumo_core::RING_OUT.to_string()),
+        errors: vec![None, None, None],
+        replay: Vec::new(),
+        logs: Vec::new(),
+        memory: Vec::new(),
+        game_version: "1.0.0",
+        lua_version: "Lua 5.4",
+    };
+    match_repo.finish(created.id, &outcome).await.unwrap();
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/games/robotsumo/leaderboard?min_games=3").await;
+    response.assert_status_ok();
+
+    let leaderboard: Leaderboard = response.json();
+    assert_eq!(leaderboard.total, 2);
+    assert_eq!(leaderboard.entries.len(), 2);
+    let entry = |agent_id| {
+        leaderboard
+            .entries
+            .iter()
+            .find(|entry| entry.agent_id == agent_id)
+            .unwrap()
+    };
+    assert_eq!(
+        (entry(first).wins, entry(first).losses, entry(first).games),
+        (2, 1, 3)
+    );
+    assert_eq!(
+        (
+            entry(second).wins,
+            entry(second).losses,
+            entry(second).games
+        ),
+        (1, 2, 3)
+    );
+}
+
+#[tokio::test]
+async fn get_leaderboard_of_unknown_game_returns_not_found() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/games/nonexistent/leaderboard").await;
+    response.assert_status_not_found();
+}
+
+// ============================================================================
+// House Bot Tests
+// ============================================================================
+
+#[tokio::test]
+async fn list_bots_returns_every_difficulty() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    bots::seed(&db).await.expect("Failed to seed house bots");
+    let state = AppState::new(config, db);
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/games/snake/bots").await;
+    response.assert_status_ok();
+
+    let bots: Vec<HouseBot> = response.json();
+    let difficulties: Vec<Difficulty> = bots.iter().map(|bot| bot.difficulty).collect();
+    assert_eq!(
+        difficulties,
+        vec![Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+    );
+    assert!(bots.iter().all(|bot| !bot.code.is_empty()));
+}
+
+#[tokio::test]
+async fn list_bots_of_unknown_game_returns_not_found() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/games/nonexistent/bots").await;
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn update_house_bot_fails() {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    bots::seed(&db).await.expect("Failed to seed house bots");
+    let state = AppState::new(config, db);
+    let user = UserRepository::new(&state.db)
+        .create("alice", "Password123!", false)
+        .await
+        .unwrap();
+    let token = common::create_test_token(user.id, false, "alice", &state.config.jwt_secret);
+    let app = routes::routes().with_state(state);
+    let server = TestServer::new(app).unwrap();
+
+    let bots: Vec<HouseBot> = server.get("/games/snake/bots").await.json();
+    let response = server
+        .put(&format!("/agents/{}", bots[0].agent_id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&serde_json::json!({ "code": "function think() end" }))
+        .await;
+    response.assert_status_not_found();
+}
@@ -0,0 +1,206 @@
+//! Integration tests for the ladder scheduler and its endpoints.
+
+mod common;
+
+use axum_extra::extract::cookie::Cookie;
+use axum_test::TestServer;
+use backend::models::{Agent, LadderStatus};
+use backend::prelude::{AppState, Config};
+use backend::repositories::{AgentRepository, GameRepository, MatchRepository, UserRepository};
+use backend::{ladder, routes};
+
+const IDLE: &str = "function think() end";
+
+/// Helper to create a test server. The job queue is not started, so ladder
+/// matches stay queued.
+async fn setup_server() -> (TestServer, AppState) {
+    setup_server_with_config(common::test_config()).await
+}
+
+/// Helper to create a test server with the given configuration.
+async fn setup_server_with_config(config: Config) -> (TestServer, AppState) {
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    let app = routes::routes().with_state(state.clone());
+    let server = TestServer::new(app).unwrap();
+    (server, state)
+}
+
+/// Helper to create a test user and return their ID and token.
+async fn create_user_with_token(state: &AppState, username: &str, admin: bool) -> (i64, String) {
+    let repo = UserRepository::new(&state.db);
+    let user = repo
+        .create(username, "Password123!", admin)
+        .await
+        .expect("Failed to create user");
+    let token = common::create_test_token(user.id, admin, username, &state.config.jwt_secret);
+    (user.id, token)
+}
+
+/// Helper to create a snake agent on the ladder.
+async fn create_ladder_agent(state: &AppState, user_id: i64, name: &str) -> i64 {
+    let game = GameRepository::new(&state.db)
+        .find_by_name("snake")
+        .await
+        .expect("Failed to query game")
+        .expect("game should exist");
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .create(user_id, game.id, name, IDLE)
+        .await
+        .expect("Failed to create agent");
+    repo.set_ladder(agent.id, user_id, true)
+        .await
+        .expect("Failed to join ladder");
+    agent.id
+}
+
+// ============================================================================
+// Join Ladder Tests
+// ============================================================================
+
+#[tokio::test]
+async fn join_and_leave_ladder() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice", false).await;
+    let game = GameRepository::new(&state.db)
+        .find_by_name("snake")
+        .await
+        .unwrap()
+        .unwrap();
+    let agent = AgentRepository::new(&state.db)
+        .create(user_id, game.id, "Slither", IDLE)
+        .await
+        .unwrap();
+    assert!(!agent.ladder);
+
+    let response = server
+        .put(&format!("/agents/{}/ladder", agent.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<Agent>().ladder);
+
+    let response = server
+        .delete(&format!("/agents/{}/ladder", agent.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_ok();
+    assert!(!response.json::<Agent>().ladder);
+}
+
+#[tokio::test]
+async fn join_ladder_with_other_users_agent_fails() {
+    let (server, state) = setup_server().await;
+    let (alice_id, _) = create_user_with_token(&state, "alice", false).await;
+    let (_, bob_token) = create_user_with_token(&state, "bob", false).await;
+    let agent_id = create_ladder_agent(&state, alice_id, "Slither").await;
+
+    let response = server
+        .put(&format!("/agents/{}/ladder", agent_id))
+        .add_cookie(Cookie::new("token", bob_token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+// ============================================================================
+// Scheduler Tests
+// ============================================================================
+
+#[tokio::test]
+async fn run_queues_rated_ladder_match() {
+    let (_, state) = setup_server().await;
+    let (alice_id, _) = create_user_with_token(&state, "alice", false).await;
+    let (bob_id, _) = create_user_with_token(&state, "bob", false).await;
+    let a = create_ladder_agent(&state, alice_id, "Slither").await;
+    let b = create_ladder_agent(&state, bob_id, "Hiss").await;
+
+    let queued = ladder::run(&state).await.expect("Ladder run failed");
+    assert_eq!(queued, 1);
+
+    let repo = MatchRepository::new(&state.db);
+    let matches = repo.find_by_agent(a).await.unwrap();
+    assert_eq!(matches.len(), 1);
+    let m = &matches[0];
+    assert!(m.ladder);
+    assert!(m.rated);
+    let mut agents: Vec<i64> = m.agents.iter().filter_map(|a| a.agent_id).collect();
+    agents.sort();
+    assert_eq!(agents, vec![a, b]);
+}
+
+#[tokio::test]
+async fn run_skips_agents_with_queued_ladder_match() {
+    let (_, state) = setup_server().await;
+    let (alice_id, _) = create_user_with_token(&state, "alice", false).await;
+    let (bob_id, _) = create_user_with_token(&state, "bob", false).await;
+    create_ladder_agent(&state, alice_id, "Slither").await;
+    create_ladder_agent(&state, bob_id, "Hiss").await;
+
+    assert_eq!(ladder::run(&state).await.unwrap(), 1);
+    // The first match has not been played yet
+    assert_eq!(ladder::run(&state).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn run_respects_daily_match_cap() {
+    let mut config = common::test_config();
+    config.ladder_daily_match_cap = 0;
+    let (_, state) = setup_server_with_config(config).await;
+    let (alice_id, _) = create_user_with_token(&state, "alice", false).await;
+    let (bob_id, _) = create_user_with_token(&state, "bob", false).await;
+    create_ladder_agent(&state, alice_id, "Slither").await;
+    create_ladder_agent(&state, bob_id, "Hiss").await;
+
+    assert_eq!(ladder::run(&state).await.unwrap(), 0);
+}
+
+// ============================================================================
+// Pause Tests
+// ============================================================================
+
+#[tokio::test]
+async fn pause_ladder_as_admin_stops_pairing() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "teacher", true).await;
+    let (alice_id, _) = create_user_with_token(&state, "alice", false).await;
+    let (bob_id, _) = create_user_with_token(&state, "bob", false).await;
+    create_ladder_agent(&state, alice_id, "Slither").await;
+    create_ladder_agent(&state, bob_id, "Hiss").await;
+
+    let response = server
+        .post("/ladder/pause")
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<LadderStatus>().paused);
+    assert_eq!(ladder::run(&state).await.unwrap(), 0);
+
+    let response = server
+        .post("/ladder/resume")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_ok();
+    assert!(!response.json::<LadderStatus>().paused);
+    assert_eq!(ladder::run(&state).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn pause_ladder_as_non_admin_fails() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "pupil", false).await;
+
+    let response = server
+        .post("/ladder/pause")
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_not_found();
+
+    let status: LadderStatus = server
+        .get("/ladder")
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert!(!status.paused);
+}
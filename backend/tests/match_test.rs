@@ -0,0 +1,1001 @@
+//! Integration tests for match endpoints.
+
+mod common;
+
+use axum_extra::extract::cookie::Cookie;
+use axum_test::{TestServer, WsMessage};
+use backend::models::{
+    Agent, Difficulty, JobStatus, LogLine, Match, MatchStatus, MatchVerification, MemorySnapshot,
+    Termination,
+};
+use backend::prelude::AppState;
+use backend::repositories::{AgentRepository, BotRepository, GameRepository, UserRepository};
+use backend::{bots, queue, routes};
+use game_core::{Frame, GameRules, LiveView, Replay};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Steers towards the opponent, using the last move to know its heading.
+const PUSHER: &str = r#"
+    local last_x, last_y
+    function think()
+        local x, y = get_position()
+        local ox, oy = get_opponent_position()
+        if last_x then
+            local cross = (x - last_x) * (oy - y) - (y - last_y) * (ox - x)
+            if cross > 0.01 then turn_left() elseif cross < -0.01 then turn_right() end
+        end
+        last_x, last_y = x, y
+        move_forward()
+    end
+"#;
+/// Like `PUSHER`, but remembers its last position in `memory`.
+const REMEMBERING_PUSHER: &str = r#"
+    function think()
+        local x, y = get_position()
+        local ox, oy = get_opponent_position()
+        if memory.x then
+            local cross = (x - memory.x) * (oy - y) - (y - memory.y) * (ox - x)
+            if cross > 0.01 then turn_left() elseif cross < -0.01 then turn_right() end
+        end
+        memory.x, memory.y = x, y
+        move_forward()
+    end
+"#;
+const IDLE: &str = "function think() end";
+/// Does nothing, slowly, so matches run long enough to watch.
+const SLOW: &str = "function think() for i = 1, 2000 do end end";
+
+/// Helper to create a test server with a pre-configured database.
+async fn setup_server() -> (TestServer, AppState) {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    queue::start(&state)
+        .await
+        .expect("Failed to start job queue");
+    let app = routes::routes().with_state(state.clone());
+    let server = TestServer::builder().http_transport().build(app).unwrap();
+    (server, state)
+}
+
+/// Helper to create a test user and return their ID and token.
+async fn create_user_with_token(state: &AppState, username: &str) -> (i64, String) {
+    let repo = UserRepository::new(&state.db);
+    let user = repo
+        .create(username, "Password123!", false)
+        .await
+        .expect("Failed to create user");
+    let token = common::create_test_token(user.id, false, username, &state.config.jwt_secret);
+    (user.id, token)
+}
+
+/// Helper to get the ID of a seeded game.
+async fn get_game_id(state: &AppState, name: &str) -> i64 {
+    let repo = GameRepository::new(&state.db);
+    let game = repo
+        .find_by_name(name)
+        .await
+        .expect("Failed to query game")
+        .expect("game should exist");
+    game.id
+}
+
+/// Helper to create an agent directly in the database.
+async fn create_agent(state: &AppState, user_id: i64, game_id: i64, name: &str, code: &str) -> i64 {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .create(user_id, game_id, name, code)
+        .await
+        .expect("Failed to create agent");
+    agent.id
+}
+
+/// Helper to queue a match and wait until the workers have played it.
+async fn play_match(server: &TestServer, token: &str, body: Value) -> Match {
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    let created: Match = response.json();
+
+    for _ in 0..500 {
+        let status: MatchStatus = server
+            .get(&format!("/matches/{}/status", created.id))
+            .add_cookie(Cookie::new("token", token.to_string()))
+            .await
+            .json();
+        if matches!(status.status, JobStatus::Finished | JobStatus::Failed) {
+            return server
+                .get(&format!("/matches/{}", created.id))
+                .add_cookie(Cookie::new("token", token.to_string()))
+                .await
+                .json();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("match {} was not played in time", created.id);
+}
+
+// ============================================================================
+// Create Match Tests
+// ============================================================================
+
+#[tokio::test]
+async fn create_match_plays_game() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({
+            "game_id": game_id,
+            "agent_ids": [idle, pusher],
+            "seed": 7
+        }),
+    )
+    .await;
+
+    assert_eq!(played.status, JobStatus::Finished);
+    assert_eq!(played.seed, 7);
+    assert_eq!(
+        played.termination,
+        Some(Termination::Finished(robotsumo_core::RING_OUT.to_string()))
+    );
+    assert_eq!(played.winner, Some(1));
+    assert!(played.ticks.unwrap() > 0);
+    assert_eq!(played.agents[0].agent_id, Some(idle));
+    assert_eq!(played.agents[0].score, Some(0));
+    assert_eq!(played.agents[1].score, Some(1));
+    assert_eq!(
+        played.game_version.as_deref(),
+        Some(<robotsumo_core::Game as GameRules>::VERSION)
+    );
+    assert_eq!(played.lua_version.as_deref(), Some("Lua 5.4"));
+}
+
+#[tokio::test]
+async fn create_match_against_other_users_agent() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "user1").await;
+    let (other_id, _) = create_user_with_token(&state, "user2").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let mine = create_agent(&state, user_id, game_id, "Mine", PUSHER).await;
+    let theirs = create_agent(&state, other_id, game_id, "Theirs", IDLE).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [mine, theirs] }),
+    )
+    .await;
+
+    assert!(played.seed >= 0);
+    assert!(played.finished_at.is_some());
+    assert_eq!(played.agents[1].agent_id, Some(theirs));
+}
+
+#[tokio::test]
+async fn create_match_records_agent_error() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "snake").await;
+    let broken = create_agent(
+        &state,
+        user_id,
+        game_id,
+        "Broken",
+        "function think() error('boom') end",
+    )
+    .await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [broken, idle], "seed": 1 }),
+    )
+    .await;
+
+    // An agent error is part of the game, not a failure to play it
+    assert_eq!(played.status, JobStatus::Finished);
+    assert_eq!(played.termination, Some(Termination::AgentError));
+    assert_eq!(played.winner, Some(1));
+    assert!(played.agents[0].error.as_ref().unwrap().contains("boom"));
+    assert_eq!(played.agents[1].error, None);
+}
+
+#[tokio::test]
+async fn create_match_is_queued() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, game_id, "Agent", IDLE).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [agent, agent], "seed": 1 }))
+        .await;
+
+    response.assert_status_ok();
+    let created: Match = response.json();
+    assert_eq!(created.status, JobStatus::Queued);
+    assert_eq!(created.winner, None);
+    assert_eq!(created.finished_at, None);
+}
+
+#[tokio::test]
+async fn create_match_with_too_few_agents_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, game_id, "Alone", IDLE).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [agent] }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn create_match_with_single_agent_fails() {
+    // Snake can be played alone, but a match is between two or more agents
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agent = create_agent(&state, user_id, game_id, "Alone", IDLE).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [agent], "rated": true }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn create_match_with_agent_of_other_game_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let sumo_id = get_game_id(&state, "robotsumo").await;
+    let snake_id = get_game_id(&state, "snake").await;
+    let robot = create_agent(&state, user_id, sumo_id, "Robot", IDLE).await;
+    let snake = create_agent(&state, user_id, snake_id, "Snake", IDLE).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": sumo_id, "agent_ids": [robot, snake] }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn create_match_against_house_bot() {
+    let (server, state) = setup_server().await;
+    bots::seed(&state.db)
+        .await
+        .expect("Failed to seed house bots");
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({
+            "game_id": game_id,
+            "agent_ids": [idle],
+            "bots": ["hard"],
+            "seed": 7
+        }),
+    )
+    .await;
+
+    assert_eq!(played.status, JobStatus::Finished);
+    assert_eq!(played.agents.len(), 2);
+    assert_eq!(played.agents[0].agent_id, Some(idle));
+    let bot = BotRepository::new(&state.db)
+        .find_by_difficulty(game_id, Difficulty::Hard)
+        .await
+        .unwrap()
+        .expect("bot should exist");
+    assert_eq!(played.agents[1].agent_id, Some(bot.agent_id));
+    assert_eq!(played.winner, Some(1));
+}
+
+#[tokio::test]
+async fn create_match_against_missing_house_bot_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, game_id, "Agent", IDLE).await;
+
+    // Bots are only seeded when the server starts
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [agent], "bots": ["easy"] }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn create_match_with_negative_seed_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, game_id, "Agent", IDLE).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [agent, agent], "seed": -1 }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn create_match_without_auth_fails() {
+    let (server, state) = setup_server().await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+
+    let response = server
+        .post("/matches")
+        .json(&json!({ "game_id": game_id, "agent_ids": [1, 2] }))
+        .await;
+
+    response.assert_status_unauthorized();
+}
+
+// ============================================================================
+// Get / List Match Tests
+// ============================================================================
+
+#[tokio::test]
+async fn get_match_returns_result() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, game_id, "Agent", PUSHER).await;
+
+    let created = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [agent, agent], "seed": 3 }),
+    )
+    .await;
+
+    let response = server
+        .get(&format!("/matches/{}", created.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_ok();
+    let found: Match = response.json();
+    assert_eq!(found.id, created.id);
+    assert_eq!(found.ticks, created.ticks);
+    assert!(found.ticks.is_some());
+    assert_eq!(found.agents.len(), 2);
+}
+
+#[tokio::test]
+async fn get_nonexistent_match_status_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get("/matches/99999/status")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn get_nonexistent_match_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get("/matches/99999")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn get_match_replay_returns_recording() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher], "seed": 7 }),
+    )
+    .await;
+
+    let response = server
+        .get(&format!("/matches/{}/replay", played.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_ok();
+    response.assert_header("content-type", "application/octet-stream");
+    let replay = Replay::<robotsumo_core::Game>::decode(&response.into_bytes()).unwrap();
+    assert_eq!(replay.header.seed, 7);
+    assert_eq!(replay.header.agents[0].id, Some(idle));
+    assert_eq!(replay.header.agents[1].name, "Pusher");
+    assert_eq!(i64::from(replay.ticks()), played.ticks.unwrap());
+}
+
+#[tokio::test]
+async fn get_nonexistent_match_replay_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get("/matches/99999/replay")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn get_match_logs_returns_printed_lines() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let chatty = create_agent(
+        &state,
+        user_id,
+        game_id,
+        "Chatty",
+        "print('ready') function think() print('tick', observation.tick) end",
+    )
+    .await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [chatty, pusher], "seed": 7 }),
+    )
+    .await;
+
+    let response = server
+        .get(&format!("/matches/{}/logs", played.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let logs: Vec<LogLine> = response.json();
+    assert_eq!(logs.len() as i64, played.ticks.unwrap() + 1);
+    assert!(logs.iter().all(|line| line.seat == 0));
+    assert_eq!(logs[0].message, "ready");
+    assert_eq!((logs[1].tick, logs[1].message.as_str()), (0, "tick\t0"));
+    assert_eq!((logs[3].tick, logs[3].message.as_str()), (2, "tick\t2"));
+
+    let response = server
+        .get(&format!("/matches/{}/logs?agent_id={}", played.id, pusher))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_ok();
+    assert!(response.json::<Vec<LogLine>>().is_empty());
+}
+
+#[tokio::test]
+async fn get_match_logs_only_returns_own_agents() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "user1").await;
+    let (other_id, other_token) = create_user_with_token(&state, "user2").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let chatty = "function think() print(observation.tick) end";
+    let mine = create_agent(&state, user_id, game_id, "Mine", chatty).await;
+    let theirs = create_agent(&state, other_id, game_id, "Theirs", chatty).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [mine, theirs], "seed": 7 }),
+    )
+    .await;
+
+    for (token, seats) in [
+        (token, vec![0]),
+        (other_token, vec![1]),
+        (admin_token, vec![0, 1]),
+    ] {
+        let logs: Vec<LogLine> = server
+            .get(&format!("/matches/{}/logs", played.id))
+            .add_cookie(Cookie::new("token", token))
+            .await
+            .json();
+        let mut seen: Vec<i64> = logs.iter().map(|line| line.seat).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, seats);
+    }
+}
+
+#[tokio::test]
+async fn get_nonexistent_match_logs_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get("/matches/99999/logs")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn get_match_memory_returns_snapshots() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", REMEMBERING_PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher], "seed": 7 }),
+    )
+    .await;
+    assert_eq!(played.winner, Some(1));
+
+    let response = server
+        .get(&format!("/matches/{}/memory", played.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+
+    response.assert_status_ok();
+    let memory: Vec<MemorySnapshot> = response.json();
+    assert!(!memory.is_empty());
+    assert!(memory.iter().all(|snapshot| snapshot.seat == 1));
+    assert_eq!(memory[0].tick, 0);
+    assert!(memory[0].memory["x"].is_number());
+    assert!(memory.windows(2).all(|pair| pair[0].tick < pair[1].tick));
+
+    let response = server
+        .get(&format!("/matches/{}/memory?agent_id={}", played.id, idle))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_ok();
+    assert!(response.json::<Vec<MemorySnapshot>>().is_empty());
+}
+
+#[tokio::test]
+async fn get_match_memory_only_returns_own_agents() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "user1").await;
+    let (other_id, other_token) = create_user_with_token(&state, "user2").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let mine = create_agent(&state, user_id, game_id, "Mine", REMEMBERING_PUSHER).await;
+    let theirs = create_agent(&state, other_id, game_id, "Theirs", REMEMBERING_PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [mine, theirs], "seed": 7 }),
+    )
+    .await;
+
+    for (token, seats) in [
+        (token.clone(), vec![0]),
+        (other_token, vec![1]),
+        (admin_token, vec![0, 1]),
+    ] {
+        let memory: Vec<MemorySnapshot> = server
+            .get(&format!("/matches/{}/memory", played.id))
+            .add_cookie(Cookie::new("token", token))
+            .await
+            .json();
+        let mut seen: Vec<i64> = memory.iter().map(|snapshot| snapshot.seat).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, seats);
+    }
+
+    let memory: Vec<MemorySnapshot> = server
+        .get(&format!(
+            "/matches/{}/memory?agent_id={}",
+            played.id, theirs
+        ))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert!(memory.is_empty());
+}
+
+#[tokio::test]
+async fn get_nonexistent_match_memory_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get("/matches/99999/memory")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+// ============================================================================
+// Rating Tests
+// ============================================================================
+
+#[tokio::test]
+async fn rated_match_updates_agent_ratings() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher], "rated": true }),
+    )
+    .await;
+    assert!(played.rated);
+
+    let winner: Agent = server
+        .get(&format!("/agents/{}", pusher))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await
+        .json();
+    let loser: Agent = server
+        .get(&format!("/agents/{}", idle))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert!(winner.rating.unwrap() > loser.rating.unwrap());
+    assert!(winner.rating_deviation.unwrap() < 350.0);
+}
+
+#[tokio::test]
+async fn unrated_match_keeps_agent_ratings() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher] }),
+    )
+    .await;
+    assert!(!played.rated);
+
+    let agent: Agent = server
+        .get(&format!("/agents/{}", pusher))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert_eq!(agent.rating, None);
+}
+
+#[tokio::test]
+async fn rated_match_with_other_users_agent_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "user1").await;
+    let (other_id, _) = create_user_with_token(&state, "user2").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let mine = create_agent(&state, user_id, game_id, "Mine", PUSHER).await;
+    let theirs = create_agent(&state, other_id, game_id, "Theirs", IDLE).await;
+
+    for body in [
+        json!({ "game_id": game_id, "agent_ids": [mine, theirs], "rated": true }),
+        json!({ "game_id": game_id, "agent_ids": [mine], "bots": ["easy"], "rated": true }),
+    ] {
+        let response = server
+            .post("/matches")
+            .add_cookie(Cookie::new("token", token.clone()))
+            .json(&body)
+            .await;
+        response.assert_status_bad_request();
+        assert!(response.text().contains("can be rated"));
+    }
+}
+
+#[tokio::test]
+async fn admin_can_rate_any_match() {
+    let (server, state) = setup_server().await;
+    let (user_id, _) = create_user_with_token(&state, "user1").await;
+    let (other_id, _) = create_user_with_token(&state, "user2").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let first = create_agent(&state, user_id, game_id, "First", PUSHER).await;
+    let second = create_agent(&state, other_id, game_id, "Second", IDLE).await;
+
+    let played = play_match(
+        &server,
+        &admin_token,
+        json!({ "game_id": game_id, "agent_ids": [first, second], "rated": true }),
+    )
+    .await;
+    assert!(played.rated);
+}
+
+// ============================================================================
+// Live Match Tests
+// ============================================================================
+
+#[tokio::test]
+async fn watch_match_streams_frames() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let slow = create_agent(&state, user_id, game_id, "Slow", SLOW).await;
+
+    let response = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&json!({
+            "game_id": game_id,
+            "agent_ids": [slow, slow],
+            "seed": 3
+        }))
+        .await;
+    let created: Match = response.json();
+
+    for _ in 0..500 {
+        if state.live.get(created.id).is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let mut websocket = server
+        .get_websocket(&format!("/matches/{}/live", created.id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await
+        .into_websocket()
+        .await;
+
+    let mut view = LiveView::<robotsumo_core::Game>::new();
+    while let WsMessage::Binary(bytes) = websocket.receive_message().await {
+        view.receive(Frame::decode(&bytes).unwrap());
+    }
+    assert!(view.is_ended());
+    assert_eq!(view.header().unwrap().seed, 3);
+
+    let response = server
+        .get(&format!("/matches/{}/replay", created.id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_ok();
+    let replay = Replay::<robotsumo_core::Game>::decode(&response.into_bytes()).unwrap();
+    assert_eq!(view.game(), replay.state_at(replay.ticks()).as_ref());
+}
+
+#[tokio::test]
+async fn watch_finished_match_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher] }),
+    )
+    .await;
+
+    let response = server
+        .get_websocket(&format!("/matches/{}/live", played.id))
+        .add_cookie(Cookie::new("token", token))
+        .expect_failure()
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn watch_nonexistent_match_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "testuser").await;
+
+    let response = server
+        .get_websocket("/matches/99999/live")
+        .add_cookie(Cookie::new("token", token))
+        .expect_failure()
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn list_matches_by_agent() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let first = create_agent(&state, user_id, game_id, "First", IDLE).await;
+    let second = create_agent(&state, user_id, game_id, "Second", PUSHER).await;
+    let third = create_agent(&state, user_id, game_id, "Third", PUSHER).await;
+
+    for agent_ids in [[first, second], [second, third]] {
+        server
+            .post("/matches")
+            .add_cookie(Cookie::new("token", token.clone()))
+            .json(&json!({ "game_id": game_id, "agent_ids": agent_ids, "seed": 1 }))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/matches")
+        .add_query_param("agent_id", second)
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+    let matches: Vec<Match> = response.json();
+    assert_eq!(matches.len(), 2);
+    assert!(matches[0].id > matches[1].id);
+
+    let matches: Vec<Match> = server
+        .get("/matches")
+        .add_query_param("agent_id", first)
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .json();
+    assert_eq!(matches.len(), 1);
+}
+
+// ============================================================================
+// Verify Match Tests
+// ============================================================================
+
+/// Helper to create an admin and return their token.
+async fn create_admin_token(state: &AppState) -> String {
+    let user = UserRepository::new(&state.db)
+        .create("admin", "Password123!", true)
+        .await
+        .expect("Failed to create admin");
+    common::create_test_token(user.id, true, "admin", &state.config.jwt_secret)
+}
+
+#[tokio::test]
+async fn verify_match_replays_identically() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher], "seed": 7 }),
+    )
+    .await;
+
+    // Neither a new name nor new code changes how the match replays
+    server
+        .put(&format!("/agents/{}", pusher))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "name": "Renamed", "code": IDLE }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post(&format!("/matches/{}/verify", played.id))
+        .add_cookie(Cookie::new("token", admin_token))
+        .await;
+
+    response.assert_status_ok();
+    let verification: MatchVerification = response.json();
+    assert!(verification.identical);
+    assert_eq!(verification.first_difference, None);
+    assert_eq!(verification.stored_size, verification.replayed_size);
+    assert_eq!(verification.game_version, played.game_version);
+    assert_eq!(Some(verification.replayed_lua_version), played.lua_version);
+}
+
+#[tokio::test]
+async fn verify_match_flags_changed_replay() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let pusher = create_agent(&state, user_id, game_id, "Pusher", PUSHER).await;
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, pusher], "seed": 7 }),
+    )
+    .await;
+
+    // Cut the stored replay short, as if it had been recorded differently
+    sqlx::query("UPDATE match_replays SET data = substr(data, 1, 20) WHERE match_id = ?")
+        .bind(played.id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let verification: MatchVerification = server
+        .post(&format!("/matches/{}/verify", played.id))
+        .add_cookie(Cookie::new("token", admin_token))
+        .await
+        .json();
+
+    assert!(!verification.identical);
+    assert_eq!(verification.first_difference, Some(20));
+    assert_eq!(verification.stored_size, 20);
+    assert!(verification.replayed_size > 20);
+}
+
+#[tokio::test]
+async fn verify_unplayed_match_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let admin_token = create_admin_token(&state).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let slow = create_agent(&state, user_id, game_id, "Slow", SLOW).await;
+
+    let created: Match = server
+        .post("/matches")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "agent_ids": [slow, slow] }))
+        .await
+        .json();
+
+    server
+        .post(&format!("/matches/{}/verify", created.id))
+        .add_cookie(Cookie::new("token", admin_token))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn verify_match_as_non_admin_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "testuser").await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let played = play_match(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "agent_ids": [idle, idle], "seed": 1 }),
+    )
+    .await;
+
+    server
+        .post(&format!("/matches/{}/verify", played.id))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .assert_status_not_found();
+}
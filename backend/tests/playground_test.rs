@@ -0,0 +1,270 @@
+//! Integration tests for the playground endpoint.
+
+mod common;
+
+use axum_extra::extract::cookie::Cookie;
+use axum_test::TestServer;
+use backend::models::{PlaygroundResult, Termination};
+use backend::prelude::{AppState, Config};
+use backend::repositories::{AgentRepository, GameRepository, UserRepository};
+use backend::{bots, playground, routes};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use game_core::Replay;
+use serde_json::json;
+
+const IDLE: &str = "function think() end";
+/// Prints the tick, then does nothing.
+const CHATTY: &str = "function think() print('tick', observation.tick) end";
+
+/// Helper to create a test server with the house bots seeded.
+async fn setup_server() -> (TestServer, AppState) {
+    setup_server_with_config(common::test_config()).await
+}
+
+/// Helper to create a test server with the given configuration.
+async fn setup_server_with_config(config: Config) -> (TestServer, AppState) {
+    let db = common::test_db().await;
+    bots::seed(&db).await.expect("Failed to seed house bots");
+    let state = AppState::new(config, db);
+    let app = routes::routes().with_state(state.clone());
+    let server = TestServer::new(app).unwrap();
+    (server, state)
+}
+
+/// Helper to create a test user and return their ID and token.
+async fn create_user_with_token(state: &AppState, username: &str) -> (i64, String) {
+    let repo = UserRepository::new(&state.db);
+    let user = repo
+        .create(username, "Password123!", false)
+        .await
+        .expect("Failed to create user");
+    let token = common::create_test_token(user.id, false, username, &state.config.jwt_secret);
+    (user.id, token)
+}
+
+/// Helper to create a snake agent directly in the database.
+async fn create_agent(state: &AppState, user_id: i64, name: &str) -> i64 {
+    let game = GameRepository::new(&state.db)
+        .find_by_name("snake")
+        .await
+        .expect("Failed to query game")
+        .expect("game should exist");
+    let agent = AgentRepository::new(&state.db)
+        .create(user_id, game.id, name, IDLE)
+        .await
+        .expect("Failed to create agent");
+    agent.id
+}
+
+/// Helper to count the agents of every user.
+async fn count_agents(state: &AppState) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM agents")
+        .fetch_one(&state.db)
+        .await
+        .unwrap()
+}
+
+// ============================================================================
+// Run Tests
+// ============================================================================
+
+#[tokio::test]
+async fn playground_against_bot_returns_result_replay_and_logs() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "alice").await;
+    let agents = count_agents(&state).await;
+
+    let response = server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": CHATTY, "opponent": { "bot": "easy" }, "seed": 3 }))
+        .await;
+    response.assert_status_ok();
+
+    let result: PlaygroundResult = response.json();
+    assert_eq!(result.seed, 3);
+    assert!(result.ticks > 0);
+    assert!(result.ticks <= i64::from(playground::MAX_TICKS));
+    assert_eq!(result.scores.len(), 2);
+    assert!(result.errors.iter().all(Option::is_none));
+
+    let replay = STANDARD.decode(&result.replay).unwrap();
+    let replay = Replay::<snake_core::Game>::decode(&replay).unwrap();
+    assert_eq!(replay.header.seed, 3);
+    assert_eq!(i64::from(replay.ticks()), result.ticks);
+
+    assert_eq!(result.logs.len() as i64, result.ticks);
+    assert_eq!(result.logs[0].seat, 0);
+    assert_eq!(result.logs[0].message, "tick\t0");
+
+    assert_eq!(count_agents(&state).await, agents);
+}
+
+#[tokio::test]
+async fn playground_is_cut_short() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "alice").await;
+    let game_id = GameRepository::new(&state.db)
+        .find_by_name("robotsumo")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    let idle = AgentRepository::new(&state.db)
+        .create(user_id, game_id, "Idle", IDLE)
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/games/robotsumo/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": IDLE, "opponent": { "agent": idle.id } }))
+        .await;
+    response.assert_status_ok();
+
+    let result: PlaygroundResult = response.json();
+    assert_eq!(result.ticks, i64::from(playground::MAX_TICKS));
+    assert_eq!(result.termination, Termination::Timeout);
+    assert_eq!(result.winner, None);
+}
+
+#[tokio::test]
+async fn playground_reports_runtime_errors() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "alice").await;
+
+    let response = server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({
+            "code": "function think() error('oops') end",
+            "opponent": { "bot": "hard" }
+        }))
+        .await;
+    response.assert_status_ok();
+
+    let result: PlaygroundResult = response.json();
+    assert_eq!(result.termination, Termination::AgentError);
+    assert_eq!(result.winner, Some(1));
+    assert!(result.errors[0].as_ref().unwrap().contains("oops"));
+}
+
+#[tokio::test]
+async fn playground_with_invalid_code_fails() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "alice").await;
+
+    let response = server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": "function think(", "opponent": { "bot": "easy" } }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn playground_against_other_users_agent_fails() {
+    let (server, state) = setup_server().await;
+    let (alice_id, _) = create_user_with_token(&state, "alice").await;
+    let (_, bob_token) = create_user_with_token(&state, "bob").await;
+    let agent_id = create_agent(&state, alice_id, "Slither").await;
+
+    let response = server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", bob_token))
+        .json(&json!({ "code": IDLE, "opponent": { "agent": agent_id } }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn playground_of_unknown_game_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "alice").await;
+
+    let response = server
+        .post("/games/nonexistent/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "code": IDLE, "opponent": { "bot": "easy" } }))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn playground_without_auth_fails() {
+    let (server, _) = setup_server().await;
+
+    let response = server
+        .post("/games/snake/playground")
+        .json(&json!({ "code": IDLE, "opponent": { "bot": "easy" } }))
+        .await;
+
+    response.assert_status_unauthorized();
+}
+
+// ============================================================================
+// Rate Limit Tests
+// ============================================================================
+
+#[tokio::test]
+async fn playground_is_rate_limited_per_user() {
+    let mut config = common::test_config();
+    config.playground_runs_per_minute = 1;
+    let (server, state) = setup_server_with_config(config).await;
+    let (_, alice_token) = create_user_with_token(&state, "alice").await;
+    let (_, bob_token) = create_user_with_token(&state, "bob").await;
+    let body = json!({ "code": IDLE, "opponent": { "bot": "easy" }, "seed": 1 });
+
+    server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", alice_token.clone()))
+        .json(&body)
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", alice_token))
+        .json(&body)
+        .await
+        .assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+
+    server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", bob_token))
+        .json(&body)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn playground_is_limited_to_running_matches() {
+    let mut config = common::test_config();
+    config.playground_max_running = 1;
+    let (server, state) = setup_server_with_config(config).await;
+    let (_, token) = create_user_with_token(&state, "alice").await;
+    let body = json!({ "code": IDLE, "opponent": { "bot": "easy" }, "seed": 1 });
+
+    // As if another user's match was being played
+    let running = state.playground_runs.clone().try_acquire_owned().unwrap();
+    server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", token.clone()))
+        .json(&body)
+        .await
+        .assert_status(axum::http::StatusCode::TOO_MANY_REQUESTS);
+
+    // Turning the user away didn't count as one of their runs
+    drop(running);
+    server
+        .post("/games/snake/playground")
+        .add_cookie(Cookie::new("token", token))
+        .json(&body)
+        .await
+        .assert_status_ok();
+    assert_eq!(state.playground_runs.available_permits(), 1);
+}
@@ -0,0 +1,97 @@
+//! Regression tests replaying committed fixture matches.
+//!
+//! Every fixture in `tests/fixtures` is a match with the code of its agents,
+//! the outcome it had and its replay. Playing it again must give the same
+//! outcome and the same replay byte for byte. If it doesn't, matches no
+//! longer play out the way they did, and finished matches can't be verified.
+//!
+//! When a change to a game is meant to change how matches play, bump the
+//! game's `GameRules::VERSION` and regenerate the fixtures with
+//! `UPDATE_FIXTURES=1 cargo test --test replay_test`.
+
+use backend::games::GameRegistry;
+use backend::runner::Entrant;
+use backend::sandbox::Limits;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A recorded match, stored as `<name>.json` next to its `<name>.replay`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    game: String,
+    seed: u64,
+    /// Agents in seat order
+    agents: Vec<FixtureAgent>,
+    winner: Option<usize>,
+    ticks: u32,
+    scores: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureAgent {
+    name: String,
+    code: String,
+}
+
+/// Helper to get the path of a fixture file.
+fn fixture_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(file)
+}
+
+/// Helper to play a fixture match again and compare it with how it was
+/// recorded, or to record it anew when `UPDATE_FIXTURES` is set.
+fn replay_fixture(name: &str) {
+    let json_path = fixture_path(&format!("{name}.json"));
+    let replay_path = fixture_path(&format!("{name}.replay"));
+    let mut fixture: Fixture =
+        serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+
+    let registry = GameRegistry::new();
+    let rules = registry.get(&fixture.game).expect("game should exist");
+    let entrants: Vec<Entrant> = fixture
+        .agents
+        .iter()
+        .map(|agent| Entrant {
+            id: None,
+            name: agent.name.clone(),
+            code: agent.code.clone(),
+        })
+        .collect();
+    let outcome = rules.run(fixture.seed, &entrants, Limits::default(), None, None);
+    assert!(
+        outcome.errors.iter().all(Option::is_none),
+        "fixture agents failed: {:?}",
+        outcome.errors
+    );
+
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        fixture.winner = outcome.winner;
+        fixture.ticks = outcome.ticks;
+        fixture.scores = outcome.scores;
+        let json = serde_json::to_string_pretty(&fixture).unwrap() + "\n";
+        std::fs::write(&json_path, json).unwrap();
+        std::fs::write(&replay_path, &outcome.replay).unwrap();
+        return;
+    }
+
+    let recorded = std::fs::read(&replay_path).unwrap();
+    assert_eq!(outcome.winner, fixture.winner);
+    assert_eq!(outcome.ticks, fixture.ticks);
+    assert_eq!(outcome.scores, fixture.scores);
+    assert!(
+        outcome.replay == recorded,
+        "replay of {name} differs from the recorded one"
+    );
+}
+
+#[test]
+fn snake_fixture_replays_identically() {
+    replay_fixture("snake_match");
+}
+
+#[test]
+fn robotsumo_fixture_replays_identically() {
+    replay_fixture("robotsumo_match");
+}
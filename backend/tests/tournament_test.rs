@@ -0,0 +1,381 @@
+//! Integration tests for tournament endpoints.
+
+mod common;
+
+use axum_extra::extract::cookie::Cookie;
+use axum_test::TestServer;
+use backend::models::{
+    Tournament, TournamentDetails, TournamentEntrant, TournamentRound, TournamentStatus,
+};
+use backend::prelude::AppState;
+use backend::repositories::{AgentRepository, GameRepository, UserRepository};
+use backend::{queue, routes};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const IDLE: &str = "function think() end";
+const BROKEN: &str = "function think() error('boom') end";
+
+/// Helper to create a test server with a running job queue.
+async fn setup_server() -> (TestServer, AppState) {
+    let config = common::test_config();
+    let db = common::test_db().await;
+    let state = AppState::new(config, db);
+    queue::start(&state)
+        .await
+        .expect("Failed to start job queue");
+    let app = routes::routes().with_state(state.clone());
+    let server = TestServer::new(app).unwrap();
+    (server, state)
+}
+
+/// Helper to create a test user and return their ID and token.
+async fn create_user_with_token(state: &AppState, username: &str, admin: bool) -> (i64, String) {
+    let repo = UserRepository::new(&state.db);
+    let user = repo
+        .create(username, "Password123!", admin)
+        .await
+        .expect("Failed to create user");
+    let token = common::create_test_token(user.id, admin, username, &state.config.jwt_secret);
+    (user.id, token)
+}
+
+/// Helper to get the ID of a seeded game.
+async fn get_game_id(state: &AppState, name: &str) -> i64 {
+    let repo = GameRepository::new(&state.db);
+    let game = repo
+        .find_by_name(name)
+        .await
+        .expect("Failed to query game")
+        .expect("game should exist");
+    game.id
+}
+
+/// Helper to create an agent directly in the database.
+async fn create_agent(state: &AppState, user_id: i64, game_id: i64, name: &str, code: &str) -> i64 {
+    let repo = AgentRepository::new(&state.db);
+    let agent = repo
+        .create(user_id, game_id, name, code)
+        .await
+        .expect("Failed to create agent");
+    agent.id
+}
+
+/// Helper to create a snake tournament with the given agents enrolled.
+async fn create_tournament(server: &TestServer, token: &str, body: Value, agents: &[i64]) -> i64 {
+    let response = server
+        .post("/tournaments")
+        .add_cookie(Cookie::new("token", token.to_string()))
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    let tournament: Tournament = response.json();
+
+    for agent_id in agents {
+        server
+            .post(&format!("/tournaments/{}/entrants", tournament.id))
+            .add_cookie(Cookie::new("token", token.to_string()))
+            .json(&json!({ "agent_id": agent_id }))
+            .await
+            .assert_status_ok();
+    }
+    tournament.id
+}
+
+/// Helper to wait until the workers have played every round of a tournament.
+async fn wait_for_tournament(server: &TestServer, token: &str, id: i64) -> TournamentDetails {
+    for _ in 0..500 {
+        let details: TournamentDetails = server
+            .get(&format!("/tournaments/{}", id))
+            .add_cookie(Cookie::new("token", token.to_string()))
+            .await
+            .json();
+        if details.tournament.status == TournamentStatus::Finished {
+            return details;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("tournament {} was not played in time", id);
+}
+
+// ============================================================================
+// Create Tournament Tests
+// ============================================================================
+
+#[tokio::test]
+async fn create_tournament_as_admin() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+
+    let response = server
+        .post("/tournaments")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "name": "Swiss cup", "format": "swiss", "rounds": 3 }))
+        .await;
+
+    response.assert_status_ok();
+    let tournament: Tournament = response.json();
+    assert_eq!(tournament.name, "Swiss cup");
+    assert_eq!(tournament.rounds, Some(3));
+    assert_eq!(tournament.status, TournamentStatus::Pending);
+}
+
+#[tokio::test]
+async fn create_tournament_as_non_admin_fails() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "pupil", false).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+
+    let response = server
+        .post("/tournaments")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn create_tournament_with_rounds_for_elimination_fails() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "robotsumo").await;
+
+    let response = server
+        .post("/tournaments")
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({
+            "game_id": game_id,
+            "name": "Cup",
+            "format": "single_elimination",
+            "rounds": 2
+        }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+// ============================================================================
+// Enroll Agent Tests
+// ============================================================================
+
+#[tokio::test]
+async fn enroll_agent_pins_version() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agent = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    AgentRepository::new(&state.db)
+        .update(agent, user_id, None, Some(BROKEN))
+        .await
+        .unwrap();
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }),
+        &[],
+    )
+    .await;
+
+    let response = server
+        .post(&format!("/tournaments/{}/entrants", id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_id": agent, "version": 1 }))
+        .await;
+
+    response.assert_status_ok();
+    let entrant: TournamentEntrant = response.json();
+    assert_eq!(entrant.agent_id, Some(agent));
+    assert_eq!(entrant.agent_version, 1);
+    assert_eq!(entrant.seed, None);
+}
+
+#[tokio::test]
+async fn enroll_agent_of_other_game_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let snake = get_game_id(&state, "snake").await;
+    let robotsumo = get_game_id(&state, "robotsumo").await;
+    let agent = create_agent(&state, user_id, robotsumo, "Idle", IDLE).await;
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": snake, "name": "Cup", "format": "round_robin" }),
+        &[],
+    )
+    .await;
+
+    let response = server
+        .post(&format!("/tournaments/{}/entrants", id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_id": agent }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn enroll_missing_version_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agent = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }),
+        &[],
+    )
+    .await;
+
+    let response = server
+        .post(&format!("/tournaments/{}/entrants", id))
+        .add_cookie(Cookie::new("token", token))
+        .json(&json!({ "agent_id": agent, "version": 2 }))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn enrolled_agent_cannot_be_deleted() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agent = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }),
+        &[agent],
+    )
+    .await;
+
+    let response = server
+        .delete(&format!("/agents/{}", agent))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+// ============================================================================
+// Play Tournament Tests
+// ============================================================================
+
+#[tokio::test]
+async fn start_tournament_with_one_entrant_fails() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agent = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }),
+        &[agent],
+    )
+    .await;
+
+    let response = server
+        .post(&format!("/tournaments/{}/start", id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn round_robin_tournament_is_played_to_the_end() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let agents = [
+        create_agent(&state, user_id, game_id, "Broken", BROKEN).await,
+        create_agent(&state, user_id, game_id, "Idle", IDLE).await,
+        create_agent(&state, user_id, game_id, "Also broken", BROKEN).await,
+    ];
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Cup", "format": "round_robin" }),
+        &agents,
+    )
+    .await;
+
+    let response = server
+        .post(&format!("/tournaments/{}/start", id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await;
+    response.assert_status_ok();
+    let started: TournamentDetails = response.json();
+    assert_eq!(started.tournament.status, TournamentStatus::Running);
+    assert_eq!(started.tournament.current_round, 1);
+    assert_eq!(started.entrants[0].seed, Some(1));
+    assert_eq!(started.matches_total, 1);
+
+    let finished = wait_for_tournament(&server, &token, id).await;
+    assert_eq!(finished.matches_total, 3);
+    assert_eq!(finished.matches_played, 3);
+    assert_eq!(finished.standings[0].agent_id, Some(agents[1]));
+    assert_eq!(finished.standings[0].wins, 2);
+    assert_eq!(finished.standings[0].points, 2.0);
+
+    let response = server
+        .get(&format!("/tournaments/{}/bracket", id))
+        .add_cookie(Cookie::new("token", token))
+        .await;
+    response.assert_status_ok();
+    let rounds: Vec<TournamentRound> = response.json();
+    assert_eq!(rounds.len(), 3);
+    assert!(rounds.iter().all(|round| round.pairings.len() == 2));
+}
+
+#[tokio::test]
+async fn single_elimination_tournament_has_one_winner() {
+    let (server, state) = setup_server().await;
+    let (user_id, token) = create_user_with_token(&state, "teacher", true).await;
+    let game_id = get_game_id(&state, "snake").await;
+    let broken = create_agent(&state, user_id, game_id, "Broken", BROKEN).await;
+    let idle = create_agent(&state, user_id, game_id, "Idle", IDLE).await;
+    let id = create_tournament(
+        &server,
+        &token,
+        json!({ "game_id": game_id, "name": "Knockout", "format": "single_elimination" }),
+        &[broken, idle],
+    )
+    .await;
+
+    server
+        .post(&format!("/tournaments/{}/start", id))
+        .add_cookie(Cookie::new("token", token.clone()))
+        .await
+        .assert_status_ok();
+
+    let finished = wait_for_tournament(&server, &token, id).await;
+    assert_eq!(finished.tournament.current_round, 1);
+    assert_eq!(finished.standings[0].agent_id, Some(idle));
+    assert_eq!(finished.standings[0].eliminated_in, None);
+    assert_eq!(finished.standings[1].eliminated_in, Some(1));
+
+    // Once finished, its agents can be deleted again
+    server
+        .delete(&format!("/agents/{}", broken))
+        .add_cookie(Cookie::new("token", token))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn get_nonexistent_tournament_returns_not_found() {
+    let (server, state) = setup_server().await;
+    let (_, token) = create_user_with_token(&state, "pupil", false).await;
+
+    let response = server
+        .get("/tournaments/999/bracket")
+        .add_cookie(Cookie::new("token", token))
+        .await;
+
+    response.assert_status_not_found();
+}
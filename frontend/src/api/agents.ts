@@ -4,10 +4,28 @@ export interface Agent {
     game_id: number
     name: string
     code: string
+    version: number
+    has_draft: boolean
+    ladder: boolean
+    rating: number | null
+    rating_deviation: number | null
     created_at: string
     updated_at: string
 }
 
+export interface AgentDraft {
+    agent_id: number
+    code: string
+    updated_at: string
+}
+
+export interface AgentVersion {
+    agent_id: number
+    version: number
+    code: string
+    created_at: string
+}
+
 export interface CreateAgentRequest {
     game_id: number
     name: string
@@ -93,3 +111,76 @@ export async function deleteAgent(id: number): Promise<void> {
         throw new Error('Failed to delete agent')
     }
 }
+
+export async function fetchAgentVersions(id: number): Promise<AgentVersion[]> {
+    const response = await fetch(`/api/agents/${id}/versions`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch agent versions')
+    }
+    return response.json()
+}
+
+export async function restoreAgentVersion(id: number, version: number): Promise<Agent> {
+    const response = await fetch(`/api/agents/${id}/versions/${version}/restore`, {
+        method: 'POST',
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to restore agent version')
+        throw new Error(message)
+    }
+    return response.json()
+}
+
+export async function fetchAgentDraft(id: number): Promise<AgentDraft | null> {
+    const response = await fetch(`/api/agents/${id}/draft`, {
+        credentials: 'include',
+    })
+    if (response.status === 404) {
+        return null
+    }
+    if (!response.ok) {
+        throw new Error('Failed to fetch draft')
+    }
+    return response.json()
+}
+
+export async function saveAgentDraft(id: number, code: string): Promise<AgentDraft> {
+    const response = await fetch(`/api/agents/${id}/draft`, {
+        method: 'PUT',
+        headers: { 'Content-Type': 'application/json' },
+        credentials: 'include',
+        body: JSON.stringify({ code }),
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to save draft')
+        throw new Error(message)
+    }
+    return response.json()
+}
+
+export async function publishAgentDraft(id: number): Promise<Agent> {
+    const response = await fetch(`/api/agents/${id}/draft/publish`, {
+        method: 'POST',
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to publish draft')
+        throw new Error(message)
+    }
+    return response.json()
+}
+
+export async function setAgentLadder(id: number, ladder: boolean): Promise<Agent> {
+    const response = await fetch(`/api/agents/${id}/ladder`, {
+        method: ladder ? 'PUT' : 'DELETE',
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to update ladder')
+        throw new Error(message)
+    }
+    return response.json()
+}
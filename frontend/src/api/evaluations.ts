@@ -0,0 +1,80 @@
+export type EvaluationStatus = 'queued' | 'running' | 'finished' | 'failed'
+
+export interface Evaluation {
+    id: number
+    game_id: number
+    user_id: number
+    agent_a_id: number | null
+    agent_a_version: number
+    agent_b_id: number | null
+    agent_b_version: number
+    seed: number
+    seeds: number
+    status: EvaluationStatus
+    error: string | null
+    wins: number | null
+    draws: number | null
+    losses: number | null
+    mean_score_diff: number | null
+    ci_low: number | null
+    ci_high: number | null
+    created_at: string
+    finished_at: string | null
+}
+
+export interface CreateEvaluationRequest {
+    agent_a_id: number
+    agent_a_version?: number
+    agent_b_id: number
+    agent_b_version?: number
+    seeds?: number
+    seed?: number
+}
+
+interface ApiError {
+    status: number
+    error: string
+}
+
+async function parseErrorResponse(response: Response, fallback: string): Promise<string> {
+    try {
+        const data: ApiError = await response.json()
+        return data.error || fallback
+    } catch {
+        return fallback
+    }
+}
+
+export async function fetchEvaluations(): Promise<Evaluation[]> {
+    const response = await fetch('/api/evaluations', {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch evaluations')
+    }
+    return response.json()
+}
+
+export async function fetchEvaluation(id: number): Promise<Evaluation> {
+    const response = await fetch(`/api/evaluations/${id}`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch evaluation')
+    }
+    return response.json()
+}
+
+export async function createEvaluation(request: CreateEvaluationRequest): Promise<Evaluation> {
+    const response = await fetch('/api/evaluations', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        credentials: 'include',
+        body: JSON.stringify(request),
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to create evaluation')
+        throw new Error(message)
+    }
+    return response.json()
+}
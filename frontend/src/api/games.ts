@@ -4,6 +4,20 @@ export interface Game {
   display_name: string
 }
 
+interface ApiError {
+  status: number
+  error: string
+}
+
+async function parseErrorResponse(response: Response, fallback: string): Promise<string> {
+  try {
+    const data: ApiError = await response.json()
+    return data.error || fallback
+  } catch {
+    return fallback
+  }
+}
+
 export async function fetchGames(): Promise<Game[]> {
   const response = await fetch('/api/games')
   if (!response.ok) {
@@ -19,3 +33,117 @@ export async function fetchGame(name: string): Promise<Game> {
   }
   return response.json()
 }
+
+export type Difficulty = 'easy' | 'medium' | 'hard'
+
+export interface HouseBot {
+  agent_id: number
+  game_id: number
+  name: string
+  difficulty: Difficulty
+  code: string
+  version: number
+  rating: number | null
+  rating_deviation: number | null
+}
+
+export async function fetchHouseBots(name: string): Promise<HouseBot[]> {
+  const response = await fetch(`/api/games/${name}/bots`)
+  if (!response.ok) {
+    throw new Error('Failed to fetch house bots')
+  }
+  return response.json()
+}
+
+export type Opponent = { bot: Difficulty } | { agent: number }
+
+export interface LogLine {
+  seat: number
+  tick: number
+  message: string
+}
+
+/** What an agent had in its `memory` table after a tick on which it changed */
+export interface MemorySnapshot {
+  seat: number
+  tick: number
+  memory: unknown
+}
+
+export interface PlaygroundResult {
+  seed: number
+  winner: number | null
+  ticks: number
+  /** 'timeout', 'agent_error' or the game's own reason, like 'ring_out' */
+  termination: string
+  scores: number[]
+  errors: (string | null)[]
+  /** Base64 encoded replay, the code under test plays in seat 0 */
+  replay: string
+  logs: LogLine[]
+  memory: MemorySnapshot[]
+}
+
+export async function runPlayground(
+  name: string,
+  code: string,
+  opponent: Opponent,
+  seed?: number,
+): Promise<PlaygroundResult> {
+  const response = await fetch(`/api/games/${name}/playground`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    credentials: 'include',
+    body: JSON.stringify({ code, opponent, seed }),
+  })
+  if (!response.ok) {
+    const message = await parseErrorResponse(response, 'Failed to run code')
+    throw new Error(message)
+  }
+  return response.json()
+}
+
+export interface LeaderboardEntry {
+  rank: number
+  agent_id: number
+  agent_name: string
+  owner: string
+  agent_version: number
+  rating: number
+  deviation: number
+  wins: number
+  losses: number
+  draws: number
+  games: number
+}
+
+export interface Leaderboard {
+  game: string
+  page: number
+  per_page: number
+  total: number
+  entries: LeaderboardEntry[]
+}
+
+export interface LeaderboardOptions {
+  page?: number
+  perPage?: number
+  days?: number
+  minGames?: number
+}
+
+export async function fetchLeaderboard(
+  name: string,
+  options: LeaderboardOptions = {},
+): Promise<Leaderboard> {
+  const params = new URLSearchParams()
+  if (options.page !== undefined) params.set('page', String(options.page))
+  if (options.perPage !== undefined) params.set('per_page', String(options.perPage))
+  if (options.days !== undefined) params.set('days', String(options.days))
+  if (options.minGames !== undefined) params.set('min_games', String(options.minGames))
+  const response = await fetch(`/api/games/${name}/leaderboard?${params}`)
+  if (!response.ok) {
+    throw new Error('Failed to fetch leaderboard')
+  }
+  return response.json()
+}
@@ -0,0 +1,23 @@
+import type { LogLine, MemorySnapshot } from './games'
+
+export async function fetchMatchMemory(matchId: number, agentId?: number): Promise<MemorySnapshot[]> {
+    const query = agentId === undefined ? '' : `?agent_id=${agentId}`
+    const response = await fetch(`/api/matches/${matchId}/memory${query}`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch match memory')
+    }
+    return response.json()
+}
+
+export async function fetchMatchLogs(matchId: number, agentId?: number): Promise<LogLine[]> {
+    const query = agentId === undefined ? '' : `?agent_id=${agentId}`
+    const response = await fetch(`/api/matches/${matchId}/logs${query}`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch match logs')
+    }
+    return response.json()
+}
@@ -0,0 +1,157 @@
+export type TournamentFormat = 'round_robin' | 'single_elimination' | 'double_elimination' | 'swiss'
+export type TournamentStatus = 'pending' | 'running' | 'finished'
+export type TournamentBracket = 'winners' | 'losers' | 'final'
+export type MatchStatus = 'queued' | 'running' | 'finished' | 'failed'
+
+export interface Tournament {
+    id: number
+    game_id: number
+    user_id: number
+    name: string
+    format: TournamentFormat
+    rounds: number | null
+    status: TournamentStatus
+    current_round: number
+    created_at: string
+    started_at: string | null
+    finished_at: string | null
+}
+
+export interface TournamentEntrant {
+    id: number
+    agent_id: number | null
+    agent_name: string | null
+    agent_version: number
+    seed: number | null
+}
+
+export interface TournamentStanding {
+    rank: number
+    entrant_id: number
+    agent_id: number | null
+    agent_name: string | null
+    points: number
+    wins: number
+    draws: number
+    losses: number
+    byes: number
+    buchholz: number
+    eliminated_in: number | null
+}
+
+export interface TournamentDetails extends Tournament {
+    entrants: TournamentEntrant[]
+    standings: TournamentStanding[]
+    matches_total: number
+    matches_played: number
+}
+
+export interface TournamentPairing {
+    id: number
+    round: number
+    bracket: TournamentBracket | null
+    first_entrant_id: number
+    second_entrant_id: number | null
+    match_id: number | null
+    status: MatchStatus | null
+    winner_entrant_id: number | null
+}
+
+export interface TournamentRound {
+    round: number
+    pairings: TournamentPairing[]
+}
+
+interface ApiError {
+    status: number
+    error: string
+}
+
+async function parseErrorResponse(response: Response, fallback: string): Promise<string> {
+    try {
+        const data: ApiError = await response.json()
+        return data.error || fallback
+    } catch {
+        return fallback
+    }
+}
+
+export async function fetchTournaments(gameId?: number): Promise<Tournament[]> {
+    const query = gameId === undefined ? '' : `?game_id=${gameId}`
+    const response = await fetch(`/api/tournaments${query}`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch tournaments')
+    }
+    return response.json()
+}
+
+export async function fetchTournament(id: number): Promise<TournamentDetails> {
+    const response = await fetch(`/api/tournaments/${id}`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch tournament')
+    }
+    return response.json()
+}
+
+export async function fetchTournamentBracket(id: number): Promise<TournamentRound[]> {
+    const response = await fetch(`/api/tournaments/${id}/bracket`, {
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        throw new Error('Failed to fetch tournament bracket')
+    }
+    return response.json()
+}
+
+export async function createTournament(
+    gameId: number,
+    name: string,
+    format: TournamentFormat,
+    rounds?: number,
+): Promise<Tournament> {
+    const response = await fetch('/api/tournaments', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        credentials: 'include',
+        body: JSON.stringify({ game_id: gameId, name, format, rounds }),
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to create tournament')
+        throw new Error(message)
+    }
+    return response.json()
+}
+
+export async function enrollAgent(
+    id: number,
+    agentId: number,
+    version?: number,
+): Promise<TournamentEntrant> {
+    const response = await fetch(`/api/tournaments/${id}/entrants`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        credentials: 'include',
+        body: JSON.stringify({ agent_id: agentId, version }),
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to enroll agent')
+        throw new Error(message)
+    }
+    return response.json()
+}
+
+export async function startTournament(id: number): Promise<TournamentDetails> {
+    const response = await fetch(`/api/tournaments/${id}/start`, {
+        method: 'POST',
+        credentials: 'include',
+    })
+    if (!response.ok) {
+        const message = await parseErrorResponse(response, 'Failed to start tournament')
+        throw new Error(message)
+    }
+    return response.json()
+}
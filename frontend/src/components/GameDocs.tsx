@@ -94,6 +94,30 @@ local dist = math.sqrt(x*x + y*y)
 local angle = math.atan2(y, x)
 local rounded = math.floor(3.7)  -- 3
 local absolute = math.abs(-5)    -- 5`,
+        },
+        {
+            name: 'Printing',
+            content: `-- print shows up in the match's debug console,
+-- next to the tick it was printed on
+print("score is", score)
+
+-- Only the first 16 KB an agent prints
+-- in a match are kept`,
+        },
+        {
+            name: 'Memory',
+            content: `-- memory is a table that keeps its contents
+-- from one tick to the next (up to 8 KB)
+function think()
+    memory.turns = (memory.turns or 0) + 1
+    if memory.turns % 10 == 0 then
+        turn_left()
+    end
+end
+
+-- It can hold numbers, strings, booleans
+-- and tables, but not functions.
+-- The match shows what it held each tick.`,
         },
         {
             name: 'Comparison',
@@ -1,3 +1,35 @@
 declare module '/wasm/robotsumo.js' {
   export default function init(): Promise<void>
 }
+
+/** Functions every game module exports for showing a recorded match. */
+interface ReplayPlayer {
+  /** Show a replay from `GET /matches/{id}/replay`, paused at the first tick. */
+  load_replay(bytes: Uint8Array): void
+  /** Show the next binary message from `GET /matches/{id}/live`. */
+  watch_frame(bytes: Uint8Array): void
+  play(): void
+  pause(): void
+  seek(tick: number): void
+  set_speed(speed: number): void
+}
+
+declare module '/wasm/robotsumo/robotsumo.js' {
+  export default function init(): Promise<void>
+  export const load_replay: ReplayPlayer['load_replay']
+  export const watch_frame: ReplayPlayer['watch_frame']
+  export const play: ReplayPlayer['play']
+  export const pause: ReplayPlayer['pause']
+  export const seek: ReplayPlayer['seek']
+  export const set_speed: ReplayPlayer['set_speed']
+}
+
+declare module '/wasm/snake/snake.js' {
+  export default function init(): Promise<void>
+  export const load_replay: ReplayPlayer['load_replay']
+  export const watch_frame: ReplayPlayer['watch_frame']
+  export const play: ReplayPlayer['play']
+  export const pause: ReplayPlayer['pause']
+  export const seek: ReplayPlayer['seek']
+  export const set_speed: ReplayPlayer['set_speed']
+}
@@ -0,0 +1,18 @@
+//! Building blocks shared by the headless game cores.
+//!
+//! Game cores must give the same result for the same seed on every platform,
+//! so they only use what this crate provides for randomness. Every core
+//! implements [`GameRules`] so the backend can run it, and games are recorded
+//! in the [`replay`] format and shown with [`Playback`].
+
+pub mod playback;
+pub mod replay;
+mod rng;
+mod rules;
+#[cfg(test)]
+mod test_game;
+
+pub use playback::{Command, CommandQueue, LiveView, Playback, Player};
+pub use replay::{Frame, Recorder, Replay};
+pub use rng::Rng;
+pub use rules::{GameRules, Termination};
@@ -0,0 +1,431 @@
+//! Playing a [`Replay`] back at any speed.
+//!
+//! Playback steps the game core with the recorded actions, so what is shown
+//! is exactly what the server simulated. Seeking jumps to the closest
+//! snapshot and replays the actions from there. [`LiveView`] does the same
+//! for a game streamed while it is played.
+//!
+//! The games are controlled from JavaScript, which can't reach into the app,
+//! so its calls go into a [`CommandQueue`] and the app hands them to its
+//! [`Player`] on the next frame.
+
+use crate::replay::{Frame, Header};
+use crate::{GameRules, Replay};
+use std::sync::Mutex;
+
+/// Fastest supported playback speed.
+pub const MAX_SPEED: f32 = 16.0;
+
+/// A replay being played back.
+pub struct Playback<G: GameRules> {
+    replay: Replay<G>,
+    /// The game at the start of the current tick.
+    game: G,
+    playing: bool,
+    speed: f32,
+    /// Fraction of the next tick already played.
+    progress: f32,
+}
+
+impl<G: GameRules> Playback<G> {
+    /// Start at the first tick, paused, at normal speed.
+    pub fn new(replay: Replay<G>) -> Self {
+        let game = replay.state_at(0).expect("replays start with a snapshot");
+        Self {
+            replay,
+            game,
+            playing: false,
+            speed: 1.0,
+            progress: 0.0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay<G> {
+        &self.replay
+    }
+
+    /// The game as of the current tick.
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.game.tick()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Whether the last recorded tick has been reached.
+    pub fn is_finished(&self) -> bool {
+        self.tick() >= self.replay.ticks()
+    }
+
+    /// Start playing, from the beginning if the end was reached.
+    pub fn play(&mut self) {
+        if self.is_finished() {
+            self.seek(0);
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Jump to the start of `tick`, or to the end if it is past the end.
+    pub fn seek(&mut self, tick: u32) {
+        let tick = tick.min(self.replay.ticks());
+        if let Some(game) = self.replay.state_at(tick) {
+            self.game = game;
+        }
+        self.progress = 0.0;
+    }
+
+    /// Set how many times faster than real time to play, between 0 and
+    /// [`MAX_SPEED`]. Anything that isn't a number is ignored.
+    pub fn set_speed(&mut self, speed: f32) {
+        if !speed.is_nan() {
+            self.speed = speed.clamp(0.0, MAX_SPEED);
+        }
+    }
+
+    /// Move forward by `ticks` ticks of real time, scaled by the speed. Stops
+    /// playing at the end of the replay.
+    pub fn advance(&mut self, ticks: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.progress += ticks * self.speed;
+        while self.progress >= 1.0 && !self.is_finished() {
+            let tick = self.tick() as usize;
+            self.game.apply(&self.replay.actions[tick]);
+            self.progress -= 1.0;
+        }
+
+        if self.is_finished() {
+            self.playing = false;
+            self.progress = 0.0;
+        }
+    }
+}
+
+/// Follows a live stream of [`Frame`]s.
+pub struct LiveView<G: GameRules> {
+    header: Option<Header<G::Settings>>,
+    game: Option<G>,
+    ended: bool,
+}
+
+impl<G: GameRules> Default for LiveView<G> {
+    fn default() -> Self {
+        Self {
+            header: None,
+            game: None,
+            ended: false,
+        }
+    }
+}
+
+impl<G: GameRules> LiveView<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(&self) -> Option<&Header<G::Settings>> {
+        self.header.as_ref()
+    }
+
+    /// The game as of the latest frame, `None` until the first snapshot.
+    pub fn game(&self) -> Option<&G> {
+        self.game.as_ref()
+    }
+
+    /// Whether the stream has ended.
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Update the game with the next frame of the stream. Ticks that don't
+    /// follow on from the current one are skipped until the next snapshot.
+    pub fn receive(&mut self, frame: Frame<G>) {
+        match frame {
+            Frame::Header(header) => self.header = Some(header),
+            Frame::Snapshot(snapshot) => self.game = Some(snapshot.state),
+            Frame::Tick { tick, actions } => {
+                if let Some(game) = &mut self.game
+                    && game.tick() == tick
+                {
+                    game.apply(&actions);
+                }
+            }
+            Frame::End => self.ended = true,
+        }
+    }
+}
+
+/// Something JavaScript asked the player to do.
+pub enum Command<G: GameRules> {
+    Load(Box<Replay<G>>),
+    Watch(Box<Frame<G>>),
+    Play,
+    Pause,
+    Seek(u32),
+    SetSpeed(f32),
+}
+
+/// Commands waiting for the next frame. Meant to be a `static`, so the
+/// functions exported to JavaScript can reach it.
+pub struct CommandQueue<G: GameRules> {
+    commands: Mutex<Vec<Command<G>>>,
+}
+
+impl<G: GameRules> CommandQueue<G> {
+    pub const fn new() -> Self {
+        Self {
+            commands: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn send(&self, command: Command<G>) {
+        self.commands.lock().unwrap().push(command);
+    }
+
+    /// Take every command sent so far, oldest first.
+    pub fn take(&self) -> Vec<Command<G>> {
+        std::mem::take(&mut *self.commands.lock().unwrap())
+    }
+}
+
+impl<G: GameRules> Default for CommandQueue<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What is being shown: the game's own demo, a replay or a live match.
+#[derive(Default)]
+pub enum Player<G: GameRules> {
+    #[default]
+    Demo,
+    Replay(Playback<G>),
+    Live(LiveView<G>),
+}
+
+impl<G: GameRules> Player<G> {
+    pub fn is_demo(&self) -> bool {
+        matches!(self, Player::Demo)
+    }
+
+    /// Apply the commands queued since the last call.
+    pub fn receive(&mut self, queue: &CommandQueue<G>) {
+        for command in queue.take() {
+            self.apply(command);
+        }
+    }
+
+    /// Load a replay or live frame, or control the loaded replay. Only
+    /// replays can be controlled, other commands are ignored.
+    pub fn apply(&mut self, command: Command<G>) {
+        match (command, &mut *self) {
+            (Command::Load(replay), _) => *self = Player::Replay(Playback::new(*replay)),
+            (Command::Watch(frame), Player::Live(live)) => live.receive(*frame),
+            (Command::Watch(frame), _) => {
+                let mut live = LiveView::new();
+                live.receive(*frame);
+                *self = Player::Live(live);
+            }
+            (Command::Play, Player::Replay(playback)) => playback.play(),
+            (Command::Pause, Player::Replay(playback)) => playback.pause(),
+            (Command::Seek(tick), Player::Replay(playback)) => playback.seek(tick),
+            (Command::SetSpeed(speed), Player::Replay(playback)) => playback.set_speed(speed),
+            _ => {}
+        }
+    }
+
+    /// Play a replay forward by `ticks` at its speed. Returns the game to
+    /// show, `None` for the demo and for live matches before their first
+    /// snapshot.
+    pub fn advance(&mut self, ticks: f32) -> Option<&G> {
+        match self {
+            Player::Demo => None,
+            Player::Replay(playback) => {
+                playback.advance(ticks);
+                Some(playback.game())
+            }
+            Player::Live(live) => live.game(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Recorder;
+    use crate::test_game::Counter;
+
+    /// Ten ticks where the only player adds one each tick.
+    fn playback() -> Playback<Counter> {
+        let mut game = Counter::new_game(0, (), 1);
+        let mut recorder = Recorder::with_snapshot_interval(&game, 0, (), Vec::new(), 4);
+        while !game.is_over() {
+            game.apply(&[1]);
+            recorder.record(vec![1], &game);
+        }
+        Playback::new(recorder.finish())
+    }
+
+    #[test]
+    fn starts_paused_at_first_tick() {
+        let mut playback = playback();
+        playback.advance(5.0);
+
+        assert_eq!(playback.tick(), 0);
+        assert!(!playback.is_playing());
+        assert_eq!(playback.speed(), 1.0);
+    }
+
+    #[test]
+    fn advance_steps_whole_ticks() {
+        let mut playback = playback();
+        playback.play();
+
+        playback.advance(0.6);
+        assert_eq!(playback.tick(), 0);
+        playback.advance(0.6);
+        assert_eq!(playback.tick(), 1);
+        playback.advance(2.0);
+        assert_eq!(playback.tick(), 3);
+        assert_eq!(playback.game().total, 3);
+    }
+
+    #[test]
+    fn speed_scales_advance() {
+        let mut playback = playback();
+        playback.play();
+        playback.set_speed(4.0);
+        playback.advance(1.0);
+        assert_eq!(playback.tick(), 4);
+
+        playback.set_speed(0.0);
+        playback.advance(1.0);
+        assert_eq!(playback.tick(), 4);
+    }
+
+    #[test]
+    fn set_speed_is_clamped() {
+        let mut playback = playback();
+        playback.set_speed(100.0);
+        assert_eq!(playback.speed(), MAX_SPEED);
+        playback.set_speed(-1.0);
+        assert_eq!(playback.speed(), 0.0);
+        playback.set_speed(f32::NAN);
+        assert_eq!(playback.speed(), 0.0);
+    }
+
+    #[test]
+    fn stops_at_the_end() {
+        let mut playback = playback();
+        playback.play();
+        playback.advance(100.0);
+
+        assert!(playback.is_finished());
+        assert!(!playback.is_playing());
+        assert_eq!(playback.game().total, 10);
+
+        // Playing again starts over
+        playback.play();
+        assert_eq!(playback.tick(), 0);
+        assert!(playback.is_playing());
+    }
+
+    #[test]
+    fn seek_matches_playing_through() {
+        let mut playback = playback();
+        playback.seek(7);
+        assert_eq!(playback.tick(), 7);
+        assert_eq!(playback.game().total, 7);
+
+        playback.seek(2);
+        assert_eq!(playback.game().total, 2);
+
+        playback.seek(50);
+        assert_eq!(playback.tick(), 10);
+    }
+
+    #[test]
+    fn live_view_follows_frames() {
+        let replay = playback().replay().clone();
+        let mut live = LiveView::<Counter>::new();
+        assert!(live.game().is_none());
+
+        live.receive(Frame::Header(replay.header.clone()));
+        // Joined late, starting from the snapshot on tick 4
+        live.receive(Frame::Snapshot(replay.snapshots[1].clone()));
+        for tick in 4..6 {
+            live.receive(Frame::Tick {
+                tick,
+                actions: replay.actions[tick as usize].clone(),
+            });
+        }
+        assert_eq!(live.game(), replay.state_at(6).as_ref());
+
+        // A gap in the ticks is skipped until the next snapshot
+        live.receive(Frame::Tick {
+            tick: 7,
+            actions: vec![1],
+        });
+        assert_eq!(live.game().unwrap().tick, 6);
+
+        live.receive(Frame::End);
+        assert!(live.is_ended());
+        assert!(live.header().is_some());
+    }
+
+    #[test]
+    fn player_follows_queued_commands() {
+        let queue = CommandQueue::new();
+        let mut player = Player::<Counter>::default();
+        assert!(player.is_demo());
+        assert!(player.advance(1.0).is_none());
+
+        // Controls do nothing until a replay is loaded
+        queue.send(Command::Seek(3));
+        queue.send(Command::Load(Box::new(playback().replay().clone())));
+        queue.send(Command::Play);
+        queue.send(Command::SetSpeed(2.0));
+        player.receive(&queue);
+        assert!(queue.take().is_empty());
+        assert_eq!(player.advance(1.0).unwrap().tick, 2);
+
+        queue.send(Command::Seek(7));
+        queue.send(Command::Pause);
+        player.receive(&queue);
+        assert_eq!(player.advance(1.0).unwrap().tick, 7);
+    }
+
+    #[test]
+    fn player_switches_to_live_frames() {
+        let replay = playback().replay().clone();
+        let mut player = Player::<Counter>::default();
+        player.apply(Command::Load(Box::new(replay.clone())));
+
+        player.apply(Command::Watch(Box::new(Frame::Header(
+            replay.header.clone(),
+        ))));
+        assert!(matches!(player, Player::Live(_)));
+        assert!(player.advance(1.0).is_none());
+
+        player.apply(Command::Watch(Box::new(Frame::Snapshot(
+            replay.snapshots[1].clone(),
+        ))));
+        player.apply(Command::Play);
+        assert_eq!(player.advance(1.0).unwrap().tick, 4);
+    }
+}
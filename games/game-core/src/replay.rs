@@ -0,0 +1,395 @@
+//! Compact, versioned recordings of played games.
+//!
+//! A replay holds everything needed to play a game back: a header describing
+//! the match, the actions of every player on every tick, and periodic full
+//! snapshots of the game state. Since games are deterministic, any tick can be
+//! rebuilt by taking the closest snapshot before it and applying the recorded
+//! actions from there, which is what [`Replay::state_at`] does.
+//!
+//! # Encoding
+//!
+//! | Bytes  | Content                                                      |
+//! |--------|--------------------------------------------------------------|
+//! | `0..4` | Magic bytes `CGRP`                                           |
+//! | `4..6` | Format version, `u16` little-endian, currently [`FORMAT_VERSION`] |
+//! | `6..`  | The [`Replay`] struct encoded with [postcard]                |
+//!
+//! The postcard encoding has no field names, so fields are stored in the
+//! order they are declared in:
+//!
+//! 1. `header`: [`Header`] with the game name, game version, seed, agents,
+//!    settings and snapshot interval
+//! 2. `actions`: one list per tick, holding the action of every seat
+//! 3. `snapshots`: [`Snapshot`]s of the full game state, taken on tick 0 and
+//!    every `snapshot_interval` ticks after that
+//!
+//! The settings, actions and game state are the game's own serde types, see
+//! the game core of each game for their fields.
+//!
+//! # Live streams
+//!
+//! A game being played is streamed as a sequence of [`Frame`]s, each encoded
+//! on its own with postcard and no prefix, using the same types as replays.
+//! A stream starts with [`Frame::Header`], then a [`Frame::Snapshot`] to start
+//! from, then one [`Frame::Tick`] per tick with snapshots in between as in a
+//! replay, and ends with [`Frame::End`]. Frames use the encoding of
+//! [`FORMAT_VERSION`].
+//!
+//! [postcard]: https://postcard.jamesmunns.com/wire-format
+
+use crate::GameRules;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// First bytes of every encoded replay.
+pub const MAGIC: &[u8; 4] = b"CGRP";
+
+/// Version of the encoding written by [`Replay::encode`].
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Number of ticks between snapshots, unless set otherwise.
+pub const DEFAULT_SNAPSHOT_INTERVAL: u32 = 100;
+
+/// Why a replay could not be decoded.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The data does not start with [`MAGIC`].
+    NotAReplay,
+    /// The replay was written in a format version this build can't read.
+    UnsupportedVersion(u16),
+    /// The replay is of another game.
+    WrongGame(String),
+    /// The data is truncated or does not match the game's types.
+    Malformed(postcard::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a replay"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay format version {version}")
+            }
+            ReplayError::WrongGame(game) => write!(f, "replay is of another game: {game}"),
+            ReplayError::Malformed(e) => write!(f, "malformed replay: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// An agent taking part in a recorded game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Agent {
+    /// ID of the agent in the backend, `None` if it has been deleted.
+    pub id: Option<i64>,
+    pub name: String,
+}
+
+/// Describes the recorded game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header<S> {
+    /// [`GameRules::NAME`] of the game.
+    pub game: String,
+    /// [`GameRules::VERSION`] of the game that was recorded.
+    pub game_version: String,
+    pub seed: u64,
+    /// Agents in seat order.
+    pub agents: Vec<Agent>,
+    pub settings: S,
+    /// Number of ticks between snapshots.
+    pub snapshot_interval: u32,
+}
+
+/// The full game state at the start of a tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot<G> {
+    pub tick: u32,
+    pub state: G,
+}
+
+/// A recorded game of `G`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Replay<G: GameRules> {
+    pub header: Header<G::Settings>,
+    /// `actions[tick][seat]` is what the agent in `seat` did on `tick`.
+    pub actions: Vec<Vec<G::Action>>,
+    /// Snapshots ordered by tick, the first one on tick 0.
+    pub snapshots: Vec<Snapshot<G>>,
+}
+
+impl<G: GameRules> Replay<G> {
+    /// Number of recorded ticks.
+    pub fn ticks(&self) -> u32 {
+        self.actions.len() as u32
+    }
+
+    /// The game state at the start of `tick`, `None` past the recorded end.
+    pub fn state_at(&self, tick: u32) -> Option<G> {
+        if tick > self.ticks() {
+            return None;
+        }
+
+        let snapshot = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.tick <= tick)?;
+        let mut game = snapshot.state.clone();
+        for actions in &self.actions[snapshot.tick as usize..tick as usize] {
+            game.apply(actions);
+        }
+        Some(game)
+    }
+
+    /// Encode the replay as described in the [module docs](self).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        postcard::to_extend(self, bytes).expect("writing to a Vec can't fail")
+    }
+
+    /// Decode a replay written by [`Replay::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let body = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or(ReplayError::NotAReplay)?;
+        let (version, body) = body.split_at_checked(2).ok_or(ReplayError::NotAReplay)?;
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        if version != FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let replay: Self = postcard::from_bytes(body).map_err(ReplayError::Malformed)?;
+        if replay.header.game != G::NAME {
+            return Err(ReplayError::WrongGame(replay.header.game));
+        }
+        Ok(replay)
+    }
+}
+
+/// One message of a live stream, see the [module docs](self).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum Frame<G: GameRules> {
+    /// Describes the game, always the first frame.
+    Header(Header<G::Settings>),
+    /// The full state to continue from.
+    Snapshot(Snapshot<G>),
+    /// The actions applied on `tick`.
+    Tick { tick: u32, actions: Vec<G::Action> },
+    /// The game is over, nothing follows.
+    End,
+}
+
+impl<G: GameRules> Frame<G> {
+    pub fn encode(&self) -> Vec<u8> {
+        postcard::to_allocvec(self).expect("writing to a Vec can't fail")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        postcard::from_bytes(bytes).map_err(ReplayError::Malformed)
+    }
+}
+
+/// Builds a [`Replay`] while a game is played.
+pub struct Recorder<G: GameRules> {
+    replay: Replay<G>,
+}
+
+impl<G: GameRules> Recorder<G> {
+    /// Start recording `game`, which must not have been played yet.
+    pub fn new(game: &G, seed: u64, settings: G::Settings, agents: Vec<Agent>) -> Self {
+        Self::with_snapshot_interval(game, seed, settings, agents, DEFAULT_SNAPSHOT_INTERVAL)
+    }
+
+    /// Like [`Recorder::new`], with a snapshot every `snapshot_interval` ticks.
+    pub fn with_snapshot_interval(
+        game: &G,
+        seed: u64,
+        settings: G::Settings,
+        agents: Vec<Agent>,
+        snapshot_interval: u32,
+    ) -> Self {
+        assert!(snapshot_interval > 0, "snapshot interval must be positive");
+        Self {
+            replay: Replay {
+                header: Header {
+                    game: G::NAME.to_string(),
+                    game_version: G::VERSION.to_string(),
+                    seed,
+                    agents,
+                    settings,
+                    snapshot_interval,
+                },
+                actions: Vec::new(),
+                snapshots: vec![Snapshot {
+                    tick: game.tick(),
+                    state: game.clone(),
+                }],
+            },
+        }
+    }
+
+    /// Record the actions just applied to `game`.
+    pub fn record(&mut self, actions: Vec<G::Action>, game: &G) {
+        self.replay.actions.push(actions);
+        if game
+            .tick()
+            .is_multiple_of(self.replay.header.snapshot_interval)
+        {
+            self.replay.snapshots.push(Snapshot {
+                tick: game.tick(),
+                state: game.clone(),
+            });
+        }
+    }
+
+    /// The replay recorded so far.
+    pub fn replay(&self) -> &Replay<G> {
+        &self.replay
+    }
+
+    pub fn finish(self) -> Replay<G> {
+        self.replay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_game::Counter;
+
+    fn agents() -> Vec<Agent> {
+        vec![
+            Agent {
+                id: Some(1),
+                name: "One".to_string(),
+            },
+            Agent {
+                id: None,
+                name: "Deleted".to_string(),
+            },
+        ]
+    }
+
+    /// Play a full game where seat 0 adds the tick and seat 1 adds one.
+    fn record(snapshot_interval: u32) -> (Counter, Replay<Counter>) {
+        let mut game = Counter::new_game(5, (), 2);
+        let mut recorder =
+            Recorder::with_snapshot_interval(&game, 5, (), agents(), snapshot_interval);
+        while !game.is_over() {
+            let actions = vec![game.tick, 1];
+            game.apply(&actions);
+            recorder.record(actions, &game);
+        }
+        (game, recorder.finish())
+    }
+
+    #[test]
+    fn recorder_takes_periodic_snapshots() {
+        let (_, replay) = record(4);
+
+        assert_eq!(replay.ticks(), 10);
+        let ticks: Vec<u32> = replay.snapshots.iter().map(|s| s.tick).collect();
+        assert_eq!(ticks, vec![0, 4, 8]);
+        assert_eq!(replay.header.game, "counter");
+        assert_eq!(replay.header.game_version, "1.0.0");
+        assert_eq!(replay.header.seed, 5);
+    }
+
+    #[test]
+    fn state_at_rebuilds_every_tick() {
+        let (end, replay) = record(4);
+
+        let mut game = Counter::new_game(5, (), 2);
+        for tick in 0..=10 {
+            assert_eq!(replay.state_at(tick), Some(game.clone()), "tick {tick}");
+            game.apply(&[tick, 1]);
+        }
+        assert_eq!(replay.state_at(10), Some(end));
+        assert_eq!(replay.state_at(11), None);
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let (_, replay) = record(DEFAULT_SNAPSHOT_INTERVAL);
+        let bytes = replay.encode();
+
+        assert_eq!(&bytes[0..4], MAGIC);
+        assert_eq!(&bytes[4..6], &FORMAT_VERSION.to_le_bytes());
+
+        let decoded = Replay::<Counter>::decode(&bytes).unwrap();
+        assert_eq!(decoded.header, replay.header);
+        assert_eq!(decoded.actions, replay.actions);
+        assert_eq!(decoded.snapshots, replay.snapshots);
+        assert_eq!(decoded.encode(), bytes);
+    }
+
+    #[test]
+    fn decode_rejects_bad_input() {
+        let (_, replay) = record(DEFAULT_SNAPSHOT_INTERVAL);
+        let bytes = replay.encode();
+
+        assert!(matches!(
+            Replay::<Counter>::decode(b"nope"),
+            Err(ReplayError::NotAReplay)
+        ));
+        assert!(matches!(
+            Replay::<Counter>::decode(b"CGRP"),
+            Err(ReplayError::NotAReplay)
+        ));
+
+        let mut future = bytes.clone();
+        future[4] = 99;
+        assert!(matches!(
+            Replay::<Counter>::decode(&future),
+            Err(ReplayError::UnsupportedVersion(99))
+        ));
+
+        assert!(matches!(
+            Replay::<Counter>::decode(&bytes[..bytes.len() - 3]),
+            Err(ReplayError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let (end, replay) = record(4);
+        let frames = [
+            Frame::<Counter>::Header(replay.header.clone()),
+            Frame::Snapshot(replay.snapshots[1].clone()),
+            Frame::Tick {
+                tick: 4,
+                actions: replay.actions[4].clone(),
+            },
+            Frame::End,
+        ];
+
+        let decoded: Vec<Frame<Counter>> = frames
+            .iter()
+            .map(|frame| Frame::decode(&frame.encode()).unwrap())
+            .collect();
+
+        assert!(matches!(&decoded[0], Frame::Header(header) if *header == replay.header));
+        assert!(
+            matches!(&decoded[1], Frame::Snapshot(s) if s.tick == 4 && s.state.total < end.total)
+        );
+        assert!(matches!(&decoded[2], Frame::Tick { tick: 4, actions } if *actions == vec![4, 1]));
+        assert!(matches!(decoded[3], Frame::End));
+        assert!(Frame::<Counter>::decode(&[200]).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_other_game() {
+        let (_, mut replay) = record(DEFAULT_SNAPSHOT_INTERVAL);
+        replay.header.game = "snake".to_string();
+
+        assert!(matches!(
+            Replay::<Counter>::decode(&replay.encode()),
+            Err(ReplayError::WrongGame(game)) if game == "snake"
+        ));
+    }
+}
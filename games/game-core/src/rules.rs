@@ -0,0 +1,71 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The game's own rules ended it. The reason is up to each game, a short
+    /// `snake_case` name like `"ring_out"`.
+    Finished(&'static str),
+    /// The tick limit was reached.
+    Timeout,
+}
+
+/// The rules of a game, implemented by the game's state type.
+///
+/// The backend runs matches through this trait only, so a new game plugs in
+/// by implementing it and registering it under the same name as its row in
+/// the `games` table.
+///
+/// A game is played in ticks: every player gets an observation of the current
+/// state, decides on an action, and all actions are applied at once. The
+/// state is serializable so replays can store snapshots of it.
+pub trait GameRules: Clone + Serialize + DeserializeOwned {
+    /// Unique name of the game, the same as `Game::name` in the backend.
+    const NAME: &'static str;
+
+    /// Version of the rules, stored in replays. Changes whenever the same
+    /// seed and actions could give a different game.
+    const VERSION: &'static str;
+
+    /// Fewest players a game can be started with.
+    const MIN_PLAYERS: usize;
+
+    /// Most players a game can be started with.
+    const MAX_PLAYERS: usize;
+
+    /// Rules that can be tweaked per match.
+    type Settings: Default + Clone + Serialize + DeserializeOwned;
+
+    /// What a player does on one tick. The default is what players who don't
+    /// decide anything do.
+    type Action: Default + Clone + Serialize + DeserializeOwned;
+
+    /// What a player knows about the game when deciding on an action.
+    type Observation: Serialize;
+
+    /// Start a new game. `players` must be between [`Self::MIN_PLAYERS`] and
+    /// [`Self::MAX_PLAYERS`].
+    fn new_game(seed: u64, settings: Self::Settings, players: usize) -> Self;
+
+    /// The game as seen by player `player`.
+    fn observe(&self, player: usize) -> Self::Observation;
+
+    /// Advance the game by one tick. `actions[i]` is the action of player `i`.
+    fn apply(&mut self, actions: &[Self::Action]);
+
+    /// Number of ticks played so far.
+    fn tick(&self) -> u32;
+
+    /// Whether the game has ended. Applying actions to an ended game does
+    /// nothing.
+    fn is_over(&self) -> bool;
+
+    /// Why the game ended, `None` while it is still running.
+    fn termination(&self) -> Option<Termination>;
+
+    /// Index of the winning player once the game is over, `None` for a draw.
+    fn winner(&self) -> Option<usize>;
+
+    /// Score of every player, higher is better.
+    fn scores(&self) -> Vec<u32>;
+}
@@ -0,0 +1,60 @@
+//! A tiny game for testing code that is generic over [`GameRules`].
+
+use crate::{GameRules, Termination};
+use serde::{Deserialize, Serialize};
+
+/// Every player adds their action to a shared total, for ten ticks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Counter {
+    pub tick: u32,
+    pub total: u32,
+}
+
+impl GameRules for Counter {
+    const NAME: &'static str = "counter";
+    const VERSION: &'static str = "1.0.0";
+    const MIN_PLAYERS: usize = 1;
+    const MAX_PLAYERS: usize = 2;
+
+    type Settings = ();
+    type Action = u32;
+    type Observation = u32;
+
+    fn new_game(seed: u64, _settings: (), _players: usize) -> Self {
+        Counter {
+            tick: 0,
+            total: seed as u32,
+        }
+    }
+
+    fn observe(&self, _player: usize) -> u32 {
+        self.total
+    }
+
+    fn apply(&mut self, actions: &[u32]) {
+        if !self.is_over() {
+            self.total += actions.iter().sum::<u32>();
+            self.tick += 1;
+        }
+    }
+
+    fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    fn is_over(&self) -> bool {
+        self.tick >= 10
+    }
+
+    fn termination(&self) -> Option<Termination> {
+        self.is_over().then_some(Termination::Timeout)
+    }
+
+    fn winner(&self) -> Option<usize> {
+        None
+    }
+
+    fn scores(&self) -> Vec<u32> {
+        vec![self.total]
+    }
+}
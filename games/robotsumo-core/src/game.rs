@@ -0,0 +1,451 @@
+use crate::{TICK_SECONDS, Vec2, heading_vector};
+use game_core::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How much a robot speeds up per second while driving, in units per second.
+pub const ACCELERATION: f64 = 12.0;
+
+/// Fraction of its velocity a robot keeps from one tick to the next.
+pub const FRICTION: f64 = 0.92;
+
+/// How far a robot turns per tick while steering, in degrees.
+pub const TURN_DEGREES: i32 = 6;
+
+/// How bouncy collisions between robots are, from 0 (not at all) to 1.
+pub const RESTITUTION: f64 = 0.5;
+
+/// Largest random change to each robot's starting heading, in degrees.
+pub const MAX_START_JITTER: i32 = 10;
+
+/// Whether a robot drives on this tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Throttle {
+    #[default]
+    Idle,
+    Forward,
+    Backward,
+}
+
+/// Whether a robot turns on this tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Steering {
+    #[default]
+    Straight,
+    Left,
+    Right,
+}
+
+/// What a robot does on one tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    pub throttle: Throttle,
+    pub steering: Steering,
+}
+
+/// Rules that can be tweaked per match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ring_radius: f64,
+    pub robot_radius: f64,
+    /// The game ends in a draw after this many ticks if both robots are
+    /// still in the ring.
+    pub max_ticks: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ring_radius: 10.0,
+            robot_radius: 1.0,
+            max_ticks: 60 * crate::TICKS_PER_SECOND,
+        }
+    }
+}
+
+/// One robot in the ring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Robot {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Direction the robot faces, in degrees counter-clockwise from the
+    /// positive x axis, always in `0..360`.
+    pub heading: i32,
+    /// Set to the tick the robot left the ring on.
+    pub ring_out: Option<u32>,
+}
+
+impl Robot {
+    /// Unit vector pointing the way the robot faces.
+    pub fn facing(&self) -> Vec2 {
+        heading_vector(self.heading)
+    }
+
+    pub fn is_in_ring(&self) -> bool {
+        self.ring_out.is_none()
+    }
+}
+
+/// The complete state of a robot sumo game between two robots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Game {
+    pub settings: Settings,
+    pub tick: u32,
+    pub robots: [Robot; 2],
+}
+
+impl Game {
+    /// Start a new game. The robots start halfway between the centre and the
+    /// edge, facing each other, and the seed turns each of them slightly.
+    pub fn new(seed: u64, settings: Settings) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut jitter = || rng.below(2 * MAX_START_JITTER as u32 + 1) as i32 - MAX_START_JITTER;
+        let start = settings.ring_radius / 2.0;
+
+        let robots = [
+            Robot {
+                position: Vec2::new(-start, 0.0),
+                velocity: Vec2::ZERO,
+                heading: jitter().rem_euclid(360),
+                ring_out: None,
+            },
+            Robot {
+                position: Vec2::new(start, 0.0),
+                velocity: Vec2::ZERO,
+                heading: (180 + jitter()).rem_euclid(360),
+                ring_out: None,
+            },
+        ];
+
+        Self {
+            settings,
+            tick: 0,
+            robots,
+        }
+    }
+
+    /// Advance the game by one fixed timestep. `controls[i]` drives robot `i`.
+    pub fn step(&mut self, controls: [Controls; 2]) {
+        if self.is_over() {
+            return;
+        }
+
+        for (robot, controls) in self.robots.iter_mut().zip(controls) {
+            robot.heading = match controls.steering {
+                Steering::Straight => robot.heading,
+                Steering::Left => (robot.heading + TURN_DEGREES).rem_euclid(360),
+                Steering::Right => (robot.heading - TURN_DEGREES).rem_euclid(360),
+            };
+
+            let thrust = match controls.throttle {
+                Throttle::Idle => 0.0,
+                Throttle::Forward => ACCELERATION,
+                Throttle::Backward => -ACCELERATION,
+            };
+            robot.velocity += robot.facing() * (thrust * TICK_SECONDS);
+            robot.velocity = robot.velocity * FRICTION;
+            robot.position += robot.velocity * TICK_SECONDS;
+        }
+
+        self.collide();
+
+        for robot in &mut self.robots {
+            if robot.is_in_ring() && robot.position.length() > self.settings.ring_radius {
+                robot.ring_out = Some(self.tick);
+            }
+        }
+
+        self.tick += 1;
+    }
+
+    /// The game ends when a robot leaves the ring or the time is up.
+    pub fn is_over(&self) -> bool {
+        self.tick >= self.settings.max_ticks || self.robots.iter().any(|r| !r.is_in_ring())
+    }
+
+    /// Index of the robot still in the ring once the other has been pushed
+    /// out. `None` while the game runs, on a timeout, or if both left the
+    /// ring on the same tick.
+    pub fn winner(&self) -> Option<usize> {
+        match (self.robots[0].is_in_ring(), self.robots[1].is_in_ring()) {
+            (true, false) => Some(0),
+            (false, true) => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Score of every robot: 1 for the winner, 0 otherwise.
+    pub fn scores(&self) -> Vec<u32> {
+        let winner = self.winner();
+        (0..self.robots.len())
+            .map(|i| u32::from(winner == Some(i)))
+            .collect()
+    }
+
+    /// The robot robot `index` is fighting.
+    pub fn opponent(&self, index: usize) -> &Robot {
+        &self.robots[1 - index]
+    }
+
+    /// Distance between the centres of the two robots.
+    pub fn distance_to_opponent(&self, index: usize) -> f64 {
+        self.robots[index]
+            .position
+            .distance(self.opponent(index).position)
+    }
+
+    /// How far robot `index`'s centre can move straight outwards before it
+    /// leaves the ring. Negative once it is out.
+    pub fn distance_to_edge(&self, index: usize) -> f64 {
+        self.settings.ring_radius - self.robots[index].position.length()
+    }
+
+    /// Separate the robots if they overlap and exchange momentum along the
+    /// line between them. Both robots weigh the same.
+    fn collide(&mut self) {
+        let [a, b] = &mut self.robots;
+        let offset = b.position - a.position;
+        let distance = offset.length();
+        let min_distance = 2.0 * self.settings.robot_radius;
+        if distance >= min_distance {
+            return;
+        }
+
+        let normal = if distance > 0.0 {
+            offset * (1.0 / distance)
+        } else {
+            Vec2::new(1.0, 0.0)
+        };
+
+        let correction = normal * ((min_distance - distance) / 2.0);
+        a.position -= correction;
+        b.position += correction;
+
+        let closing_speed = (b.velocity - a.velocity).dot(normal);
+        if closing_speed < 0.0 {
+            let impulse = normal * (-(1.0 + RESTITUTION) * closing_speed / 2.0);
+            a.velocity -= impulse;
+            b.velocity += impulse;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORWARD: Controls = Controls {
+        throttle: Throttle::Forward,
+        steering: Steering::Straight,
+    };
+    const IDLE: Controls = Controls {
+        throttle: Throttle::Idle,
+        steering: Steering::Straight,
+    };
+
+    /// Turn towards the opponent and drive at it.
+    fn chase(game: &Game, index: usize) -> Controls {
+        let robot = &game.robots[index];
+        let to_opponent = game.opponent(index).position - robot.position;
+        let facing = robot.facing();
+        let cross = facing.x * to_opponent.y - facing.y * to_opponent.x;
+        let steering = if cross > 0.1 {
+            Steering::Left
+        } else if cross < -0.1 {
+            Steering::Right
+        } else {
+            Steering::Straight
+        };
+        Controls {
+            throttle: Throttle::Forward,
+            steering,
+        }
+    }
+
+    /// Drive in a tight circle.
+    const CIRCLE: Controls = Controls {
+        throttle: Throttle::Forward,
+        steering: Steering::Left,
+    };
+
+    fn play(seed: u64) -> Game {
+        let mut game = Game::new(seed, Settings::default());
+        while !game.is_over() {
+            let controls = [chase(&game, 0), CIRCLE];
+            game.step(controls);
+        }
+        game
+    }
+
+    fn still_game() -> Game {
+        let mut game = Game::new(0, Settings::default());
+        game.robots[0].heading = 0;
+        game.robots[1].heading = 180;
+        game
+    }
+
+    #[test]
+    fn robots_start_facing_each_other() {
+        let game = Game::new(7, Settings::default());
+
+        assert_eq!(game.robots[0].position, Vec2::new(-5.0, 0.0));
+        assert_eq!(game.robots[1].position, Vec2::new(5.0, 0.0));
+        for (robot, facing) in game.robots.iter().zip([0, 180]) {
+            let off = (robot.heading - facing + 180).rem_euclid(360) - 180;
+            assert!(off.abs() <= MAX_START_JITTER, "heading {}", robot.heading);
+        }
+    }
+
+    #[test]
+    fn seed_changes_starting_headings() {
+        let headings: Vec<_> = (0..10)
+            .map(|seed| Game::new(seed, Settings::default()).robots[0].heading)
+            .collect();
+        assert!(headings.iter().any(|&h| h != headings[0]));
+    }
+
+    #[test]
+    fn driving_forward_moves_along_heading() {
+        let mut game = still_game();
+        game.step([FORWARD, IDLE]);
+
+        let robot = &game.robots[0];
+        assert!(robot.position.x > -5.0);
+        assert_eq!(robot.position.y, 0.0);
+        assert_eq!(game.robots[1].position, Vec2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn driving_backward_moves_against_heading() {
+        let mut game = still_game();
+        let backward = Controls {
+            throttle: Throttle::Backward,
+            ..IDLE
+        };
+        game.step([backward, IDLE]);
+
+        assert!(game.robots[0].position.x < -5.0);
+    }
+
+    #[test]
+    fn steering_turns_by_fixed_steps() {
+        let mut game = still_game();
+        let left = Controls {
+            steering: Steering::Left,
+            ..IDLE
+        };
+        let right = Controls {
+            steering: Steering::Right,
+            ..IDLE
+        };
+        game.step([left, right]);
+
+        assert_eq!(game.robots[0].heading, TURN_DEGREES);
+        assert_eq!(game.robots[1].heading, 180 - TURN_DEGREES);
+
+        game.step([right, IDLE]);
+        game.step([right, IDLE]);
+        assert_eq!(game.robots[0].heading, 360 - TURN_DEGREES);
+    }
+
+    #[test]
+    fn idle_robot_slows_down() {
+        let mut game = still_game();
+        game.robots[0].velocity = Vec2::new(2.0, 0.0);
+        game.step([IDLE, IDLE]);
+
+        assert_eq!(game.robots[0].velocity, Vec2::new(2.0 * FRICTION, 0.0));
+    }
+
+    #[test]
+    fn colliding_robots_do_not_overlap() {
+        let mut game = still_game();
+        game.robots[0].position = Vec2::new(-0.5, 0.0);
+        game.robots[1].position = Vec2::new(0.5, 0.0);
+        game.robots[0].velocity = Vec2::new(3.0, 0.0);
+        game.step([IDLE, IDLE]);
+
+        assert!(game.distance_to_opponent(0) >= 2.0 * game.settings.robot_radius - 1e-12);
+        assert!(game.robots[1].velocity.x > 0.0, "momentum is passed on");
+    }
+
+    #[test]
+    fn leaving_the_ring_loses() {
+        let mut game = still_game();
+        game.robots[1].position = Vec2::new(9.99, 0.0);
+        game.robots[1].velocity = Vec2::new(3.0, 0.0);
+        game.step([IDLE, IDLE]);
+
+        assert!(game.is_over());
+        assert_eq!(game.robots[1].ring_out, Some(0));
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.scores(), vec![1, 0]);
+    }
+
+    #[test]
+    fn timeout_is_a_draw() {
+        let mut game = Game::new(
+            0,
+            Settings {
+                max_ticks: 5,
+                ..Settings::default()
+            },
+        );
+        for _ in 0..10 {
+            game.step([IDLE, IDLE]);
+        }
+
+        assert_eq!(game.tick, 5);
+        assert!(game.is_over());
+        assert_eq!(game.winner(), None);
+        assert_eq!(game.scores(), vec![0, 0]);
+    }
+
+    #[test]
+    fn no_steps_after_game_over() {
+        let mut game = play(3);
+        let finished = game.clone();
+        game.step([FORWARD, FORWARD]);
+        assert_eq!(game, finished);
+    }
+
+    #[test]
+    fn distances_for_agents() {
+        let game = still_game();
+
+        assert_eq!(game.distance_to_opponent(0), 10.0);
+        assert_eq!(game.distance_to_opponent(1), 10.0);
+        assert_eq!(game.distance_to_edge(0), 5.0);
+        assert_eq!(game.opponent(0).position, Vec2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn same_seed_gives_same_game() {
+        for seed in 0..5 {
+            assert_eq!(play(seed), play(seed));
+        }
+    }
+
+    #[test]
+    fn recorded_game_is_bit_for_bit_identical() {
+        // Locks in the simulation; any change to these numbers changes the
+        // outcome of existing replays.
+        let game = play(42);
+        let bits = |v: Vec2| (v.x.to_bits(), v.y.to_bits());
+
+        assert_eq!(game.tick, LOCKED_TICK);
+        assert_eq!(game.winner(), LOCKED_WINNER);
+        assert_eq!(bits(game.robots[0].position), LOCKED_POSITIONS[0]);
+        assert_eq!(bits(game.robots[1].position), LOCKED_POSITIONS[1]);
+    }
+
+    const LOCKED_TICK: u32 = 164;
+    const LOCKED_WINNER: Option<usize> = Some(0);
+    const LOCKED_POSITIONS: [(u64, u64); 2] = [
+        (0x4021_DE19_50A8_5A5B, 0x3FE9_E818_435F_9401),
+        (0x4023_5D23_1948_7848, 0x4005_A2C4_85E2_ABC2),
+    ];
+}
@@ -0,0 +1,35 @@
+//! Headless robot sumo physics shared by the backend and the Bevy game.
+//!
+//! Two round robots start inside a circular ring and try to push each other
+//! out. The simulation runs on a fixed timestep and only uses operations that
+//! IEEE 754 defines exactly (`+`, `-`, `*`, `/` and `sqrt`), with its own sine
+//! and cosine, so the same seed and inputs give bit-for-bit the same game on
+//! every platform, including wasm32.
+//!
+//! The functions agents call map onto the core like this:
+//!
+//! | Agent function               | Core                               |
+//! |------------------------------|------------------------------------|
+//! | `move_forward()`             | [`Controls::throttle`] = `Forward` |
+//! | `move_backward()`            | [`Controls::throttle`] = `Backward`|
+//! | `turn_left()`                | [`Controls::steering`] = `Left`    |
+//! | `turn_right()`               | [`Controls::steering`] = `Right`   |
+//! | `get_position()`             | [`Robot::position`]                |
+//! | `get_opponent_position()`    | [`Game::opponent`]                 |
+//! | `get_distance_to_opponent()` | [`Game::distance_to_opponent`]     |
+//! | `get_distance_to_edge()`     | [`Game::distance_to_edge`]         |
+
+mod game;
+mod math;
+mod rules;
+
+pub use game::*;
+pub use game_core::{GameRules, Termination};
+pub use math::*;
+pub use rules::{Observation, RING_OUT};
+
+/// Number of physics steps per second of game time.
+pub const TICKS_PER_SECOND: u32 = 30;
+
+/// Game time that passes in one tick, in seconds.
+pub const TICK_SECONDS: f64 = 1.0 / TICKS_PER_SECOND as f64;
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A 2D vector. The ring is centred on `(0, 0)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn dot(self, other: Self) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn distance(self, other: Self) -> f64 {
+        (self - other).length()
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Self;
+    fn mul(self, factor: f64) -> Self {
+        Self::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+/// Unit vector pointing along a heading in whole degrees, counter-clockwise
+/// from the positive x axis.
+///
+/// `f64::sin` and `f64::cos` may give slightly different results on different
+/// platforms, so this uses its own series that only needs `+`, `*` and `/`.
+pub fn heading_vector(degrees: i32) -> Vec2 {
+    let degrees = degrees.rem_euclid(360);
+    let quadrant = degrees / 90;
+    let angle = (degrees % 90) as f64 * std::f64::consts::PI / 180.0;
+    let (sin, cos) = (sin_series(angle), cos_series(angle));
+
+    match quadrant {
+        0 => Vec2::new(cos, sin),
+        1 => Vec2::new(-sin, cos),
+        2 => Vec2::new(-cos, -sin),
+        _ => Vec2::new(sin, -cos),
+    }
+}
+
+/// Number of Taylor series terms; enough for about 1e-14 below `pi / 2`.
+const SERIES_TERMS: u32 = 9;
+
+/// `sin(x)` for `0 <= x < pi / 2`, as a Taylor series in Horner form.
+fn sin_series(x: f64) -> f64 {
+    let x2 = x * x;
+    let mut sum = 1.0;
+    for n in (1..SERIES_TERMS).rev() {
+        let n = n as f64;
+        sum = 1.0 - x2 / ((2.0 * n) * (2.0 * n + 1.0)) * sum;
+    }
+    x * sum
+}
+
+/// `cos(x)` for `0 <= x < pi / 2`, as a Taylor series in Horner form.
+fn cos_series(x: f64) -> f64 {
+    let x2 = x * x;
+    let mut sum = 1.0;
+    for n in (1..SERIES_TERMS).rev() {
+        let n = n as f64;
+        sum = 1.0 - x2 / ((2.0 * n - 1.0) * (2.0 * n)) * sum;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn heading_vector_matches_std_trigonometry() {
+        for degrees in -360..720 {
+            let radians = (degrees as f64).to_radians();
+            let v = heading_vector(degrees);
+            assert!(close(v.x, radians.cos()), "cos({degrees})");
+            assert!(close(v.y, radians.sin()), "sin({degrees})");
+        }
+    }
+
+    #[test]
+    fn heading_vector_is_exact_on_axes() {
+        assert_eq!(heading_vector(0), Vec2::new(1.0, 0.0));
+        assert_eq!(heading_vector(90), Vec2::new(-0.0, 1.0));
+        assert_eq!(heading_vector(180), Vec2::new(-1.0, -0.0));
+        assert_eq!(heading_vector(270), Vec2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn vector_length_and_distance() {
+        assert_eq!(Vec2::new(3.0, 4.0).length(), 5.0);
+        assert_eq!(Vec2::new(1.0, 1.0).distance(Vec2::new(4.0, 5.0)), 5.0);
+    }
+}
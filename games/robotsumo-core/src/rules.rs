@@ -0,0 +1,164 @@
+use crate::{Controls, Game, Settings, Vec2};
+use game_core::{GameRules, Termination};
+use serde::Serialize;
+
+/// Reason a game ends when a robot was pushed out of the ring.
+pub const RING_OUT: &str = "ring_out";
+
+/// What one robot knows about the game on a tick.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Observation {
+    pub tick: u32,
+    pub ring_radius: f64,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Degrees counter-clockwise from the positive x axis, in `0..360`.
+    pub heading: i32,
+    pub opponent_position: Vec2,
+    pub opponent_heading: i32,
+    pub distance_to_opponent: f64,
+    pub distance_to_edge: f64,
+}
+
+impl GameRules for Game {
+    const NAME: &'static str = "robotsumo";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const MIN_PLAYERS: usize = 2;
+    const MAX_PLAYERS: usize = 2;
+
+    type Settings = Settings;
+    type Action = Controls;
+    type Observation = Observation;
+
+    fn new_game(seed: u64, settings: Settings, players: usize) -> Self {
+        assert_eq!(players, 2, "robot sumo is played by two robots");
+        Game::new(seed, settings)
+    }
+
+    fn observe(&self, player: usize) -> Observation {
+        let robot = &self.robots[player];
+        let opponent = self.opponent(player);
+
+        Observation {
+            tick: self.tick,
+            ring_radius: self.settings.ring_radius,
+            position: robot.position,
+            velocity: robot.velocity,
+            heading: robot.heading,
+            opponent_position: opponent.position,
+            opponent_heading: opponent.heading,
+            distance_to_opponent: self.distance_to_opponent(player),
+            distance_to_edge: self.distance_to_edge(player),
+        }
+    }
+
+    fn apply(&mut self, actions: &[Controls]) {
+        let controls = [0, 1].map(|i| actions.get(i).copied().unwrap_or_default());
+        self.step(controls);
+    }
+
+    fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    fn is_over(&self) -> bool {
+        Game::is_over(self)
+    }
+
+    fn termination(&self) -> Option<Termination> {
+        if self.robots.iter().any(|robot| !robot.is_in_ring()) {
+            Some(Termination::Finished(RING_OUT))
+        } else if Game::is_over(self) {
+            Some(Termination::Timeout)
+        } else {
+            None
+        }
+    }
+
+    fn winner(&self) -> Option<usize> {
+        Game::winner(self)
+    }
+
+    fn scores(&self) -> Vec<u32> {
+        Game::scores(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Steering, Throttle};
+    use game_core::{Recorder, Replay};
+
+    #[test]
+    fn observation_is_from_the_players_side() {
+        let game = Game::new_game(0, Settings::default(), 2);
+        let observation = game.observe(1);
+
+        assert_eq!(observation.position, game.robots[1].position);
+        assert_eq!(observation.opponent_position, game.robots[0].position);
+        assert_eq!(observation.distance_to_opponent, 10.0);
+        assert_eq!(observation.distance_to_edge, 5.0);
+    }
+
+    #[test]
+    fn missing_actions_leave_robots_idle() {
+        let mut game = Game::new_game(0, Settings::default(), 2);
+        let turn = Controls {
+            throttle: Throttle::Idle,
+            steering: Steering::Left,
+        };
+        let heading = game.robots[1].heading;
+        game.apply(&[turn]);
+
+        assert_eq!(GameRules::tick(&game), 1);
+        assert_eq!(game.robots[1].heading, heading);
+        assert_eq!(game.robots[1].velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn ring_out_and_timeout_end_the_game() {
+        let mut game = Game::new_game(0, Settings::default(), 2);
+        assert_eq!(game.termination(), None);
+
+        game.robots[0].ring_out = Some(0);
+        assert_eq!(game.termination(), Some(Termination::Finished(RING_OUT)));
+
+        game.robots[0].ring_out = None;
+        game.tick = game.settings.max_ticks;
+        assert_eq!(game.termination(), Some(Termination::Timeout));
+    }
+
+    #[test]
+    #[should_panic]
+    fn needs_two_players() {
+        Game::new_game(0, Settings::default(), 3);
+    }
+
+    #[test]
+    fn replay_round_trip() {
+        let mut game = Game::new_game(8, Settings::default(), 2);
+        let mut recorder = Recorder::new(&game, 8, Settings::default(), Vec::new());
+        let chase = Controls {
+            throttle: Throttle::Forward,
+            steering: Steering::Straight,
+        };
+        let circle = Controls {
+            throttle: Throttle::Forward,
+            steering: Steering::Left,
+        };
+        while !game.is_over() {
+            let actions = vec![chase, circle];
+            game.apply(&actions);
+            recorder.record(actions, &game);
+        }
+
+        let bytes = recorder.finish().encode();
+        let replay = Replay::<Game>::decode(&bytes).unwrap();
+
+        assert_eq!(replay.header.game, "robotsumo");
+        assert_eq!(replay.ticks(), game.tick);
+        // Snapshots store floats exactly, so replaying gives the same bits
+        assert_eq!(replay.state_at(game.tick), Some(game));
+    }
+}
@@ -1,6 +1,17 @@
 use bevy::prelude::*;
+use robotsumo_core::{Controls, Game, Settings, Steering, TICKS_PER_SECOND, Throttle};
 use wasm_bindgen::prelude::*;
 
+mod playback;
+
+use playback::{PlaybackPlugin, Player};
+pub use playback::{load_replay, pause, play, seek, set_speed, watch_frame};
+
+/// Size of one game unit on screen, in pixels.
+const UNIT_SIZE: f32 = 24.0;
+
+const ROBOT_COLORS: [Color; 2] = [Color::srgb(0.39, 0.4, 0.95), Color::srgb(0.96, 0.45, 0.2)];
+
 #[wasm_bindgen(start)]
 pub fn run() {
     App::new()
@@ -14,27 +25,115 @@ pub fn run() {
             }),
             ..default()
         }))
+        .add_plugins(PlaybackPlugin)
+        .insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND as f64))
+        .insert_resource(Simulation::new(0))
         .add_systems(Startup, setup)
+        .add_systems(FixedUpdate, advance)
+        .add_systems(Update, draw)
         .run();
 }
 
-fn setup(mut commands: Commands) {
+/// The game being shown, simulated by `robotsumo-core`.
+#[derive(Resource)]
+struct Simulation {
+    game: Game,
+    seed: u64,
+}
+
+impl Simulation {
+    fn new(seed: u64) -> Self {
+        Self {
+            game: Game::new(seed, Settings::default()),
+            seed,
+        }
+    }
+}
+
+/// The sprite showing robot `0` or `1`.
+#[derive(Component)]
+struct RobotSprite(usize);
+
+fn setup(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
     commands.spawn(Camera2d);
 
-    commands
-        .spawn(Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        })
-        .with_child((
-            Text::new("Robot Sumo"),
-            TextFont {
-                font_size: 64.0,
-                ..default()
-            },
-            TextColor(Color::WHITE),
-        ));
+    let settings = &simulation.game.settings;
+    commands.spawn((
+        Mesh2d(meshes.add(Circle::new(settings.ring_radius as f32 * UNIT_SIZE))),
+        MeshMaterial2d(materials.add(Color::srgb(0.06, 0.09, 0.16))),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+    ));
+
+    let radius = settings.robot_radius as f32 * UNIT_SIZE;
+    for (index, color) in ROBOT_COLORS.into_iter().enumerate() {
+        commands
+            .spawn((
+                RobotSprite(index),
+                Mesh2d(meshes.add(Circle::new(radius))),
+                MeshMaterial2d(materials.add(color)),
+                Transform::from_xyz(0.0, 0.0, 1.0),
+            ))
+            .with_child((
+                // Marks the front of the robot
+                Sprite::from_color(Color::WHITE, Vec2::new(radius * 0.6, radius * 0.3)),
+                Transform::from_xyz(radius * 0.6, 0.0, 1.0),
+            ));
+    }
+}
+
+/// Step the demo game at a fixed rate, starting a new one when it ends. Does
+/// nothing while a recorded or live match is shown.
+fn advance(player: Res<Player>, mut simulation: ResMut<Simulation>) {
+    if !player.is_demo() {
+        return;
+    }
+
+    if simulation.game.is_over() {
+        let seed = simulation.seed + 1;
+        *simulation = Simulation::new(seed);
+        return;
+    }
+
+    let game = &simulation.game;
+    let controls = [demo_controls(game, 0), demo_controls(game, 1)];
+    simulation.game.step(controls);
+}
+
+/// Simple built-in player for the demo: turn towards the opponent and push,
+/// backing off when close to the edge.
+fn demo_controls(game: &Game, index: usize) -> Controls {
+    let robot = &game.robots[index];
+    let to_opponent = game.opponent(index).position - robot.position;
+    let facing = robot.facing();
+    let cross = facing.x * to_opponent.y - facing.y * to_opponent.x;
+
+    let steering = if cross > 0.2 {
+        Steering::Left
+    } else if cross < -0.2 {
+        Steering::Right
+    } else {
+        Steering::Straight
+    };
+    let throttle = if game.distance_to_edge(index) < 1.5 && facing.dot(robot.position) > 0.0 {
+        Throttle::Backward
+    } else {
+        Throttle::Forward
+    };
+
+    Controls { throttle, steering }
+}
+
+/// Move the robot sprites to where the robots are.
+fn draw(simulation: Res<Simulation>, mut robots: Query<(&RobotSprite, &mut Transform)>) {
+    for (sprite, mut transform) in &mut robots {
+        let robot = &simulation.game.robots[sprite.0];
+        transform.translation.x = robot.position.x as f32 * UNIT_SIZE;
+        transform.translation.y = robot.position.y as f32 * UNIT_SIZE;
+        transform.rotation = Quat::from_rotation_z((robot.heading as f32).to_radians());
+    }
 }
@@ -0,0 +1,639 @@
+use crate::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A cell on the grid. `(0, 0)` is the bottom-left corner and `y` grows upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// The neighbouring cell in a direction.
+    pub fn step(self, direction: Direction) -> Self {
+        let (dx, dy) = direction.offset();
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+/// The direction a snake is moving in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// Change in `(x, y)` when moving one cell in this direction.
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, 1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+        }
+    }
+
+    /// Lowercase name, as shown to agents.
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Right => "right",
+            Direction::Down => "down",
+            Direction::Left => "left",
+        }
+    }
+}
+
+/// What a snake does on one tick. Snakes can't reverse, only turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Turn {
+    #[default]
+    Straight,
+    Left,
+    Right,
+}
+
+/// Why a snake died.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Death {
+    /// Moved off the grid.
+    Wall,
+    /// Ran into a snake, either itself or another one.
+    Collision,
+}
+
+/// Rules that can be tweaked per match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub width: i32,
+    pub height: i32,
+    /// The game ends after this many ticks even if snakes are still alive.
+    pub max_ticks: u32,
+    /// Length of every snake at the start.
+    pub start_length: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 20,
+            height: 20,
+            max_ticks: 500,
+            start_length: 3,
+        }
+    }
+}
+
+impl Settings {
+    /// The settings made to fit `players` snakes: a column of its own for
+    /// every snake with a free one between them, and snakes of at least one
+    /// cell that fit between the middle row and the edge behind them.
+    pub fn clamped(self, players: usize) -> Self {
+        let width = self.width.max(players as i32 + 1);
+        let height = self.height.max(1);
+        let start_length = self.start_length.clamp(1, (height / 2 + 1) as u32);
+        Self {
+            width,
+            height,
+            start_length,
+            ..self
+        }
+    }
+}
+
+/// One snake on the grid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snake {
+    /// Cells from head to tail.
+    pub body: VecDeque<Position>,
+    pub direction: Direction,
+    /// Set once the snake has died, together with the tick it happened on.
+    pub death: Option<(Death, u32)>,
+}
+
+impl Snake {
+    pub fn head(&self) -> Position {
+        self.body[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.body.is_empty()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.death.is_none()
+    }
+}
+
+/// The complete state of a snake game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Game {
+    pub settings: Settings,
+    pub tick: u32,
+    pub snakes: Vec<Snake>,
+    /// `None` only when the grid is completely full.
+    pub food: Option<Position>,
+    rng: Rng,
+}
+
+impl Game {
+    /// Start a new game. Snakes are spread evenly along the middle row, all
+    /// facing up, and the first food is placed using the seed. Settings that
+    /// don't fit the players are [clamped](Settings::clamped) first.
+    pub fn new(seed: u64, settings: Settings, players: usize) -> Self {
+        let settings = settings.clamped(players);
+        let spacing = settings.width / (players as i32 + 1);
+        let snakes = (0..players)
+            .map(|i| {
+                let x = spacing * (i as i32 + 1);
+                let y = settings.height / 2;
+                let body = (0..settings.start_length as i32)
+                    .map(|offset| Position::new(x, y - offset))
+                    .collect();
+                Snake {
+                    body,
+                    direction: Direction::Up,
+                    death: None,
+                }
+            })
+            .collect();
+
+        let mut game = Self {
+            settings,
+            tick: 0,
+            snakes,
+            food: None,
+            rng: Rng::new(seed),
+        };
+        game.food = game.spawn_food();
+        game
+    }
+
+    /// Advance the game by one tick. `turns[i]` is the move of snake `i`;
+    /// missing entries mean going straight.
+    pub fn step(&mut self, turns: &[Turn]) {
+        if self.is_over() {
+            return;
+        }
+
+        // Move every living snake. Tails move before collisions are checked,
+        // so a snake may follow its own (or another snake's) tail.
+        let mut food_eaten = false;
+        for (i, snake) in self.snakes.iter_mut().enumerate() {
+            if !snake.is_alive() {
+                continue;
+            }
+            snake.direction = match turns.get(i).copied().unwrap_or_default() {
+                Turn::Straight => snake.direction,
+                Turn::Left => snake.direction.turn_left(),
+                Turn::Right => snake.direction.turn_right(),
+            };
+            let head = snake.head().step(snake.direction);
+            snake.body.push_front(head);
+
+            if Some(head) == self.food {
+                food_eaten = true;
+            } else {
+                snake.body.pop_back();
+            }
+        }
+
+        // Decide all deaths before applying them, so collisions are simultaneous
+        let deaths: Vec<Option<Death>> = (0..self.snakes.len())
+            .map(|i| self.check_death(i))
+            .collect();
+        for (snake, death) in self.snakes.iter_mut().zip(deaths) {
+            if let Some(death) = death {
+                snake.death = Some((death, self.tick));
+            }
+        }
+
+        if food_eaten {
+            self.food = self.spawn_food();
+        }
+
+        self.tick += 1;
+    }
+
+    /// The game ends when the time is up, when every snake is dead, or when
+    /// only one snake is left in a game with several players.
+    pub fn is_over(&self) -> bool {
+        let alive = self.snakes.iter().filter(|s| s.is_alive()).count();
+        self.tick >= self.settings.max_ticks || alive == 0 || (self.snakes.len() > 1 && alive == 1)
+    }
+
+    /// Index of the winning snake once the game is over, `None` for a draw or
+    /// a single-player game. The last snake alive wins; otherwise the longest
+    /// of the snakes that survived longest wins.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_over() || self.snakes.len() < 2 {
+            return None;
+        }
+
+        // Snakes that are still alive outlasted everyone else
+        let last_death = |snake: &Snake| snake.death.map_or(u32::MAX, |(_, tick)| tick);
+        let longest_lived = self.snakes.iter().map(last_death).max()?;
+        let candidates: Vec<usize> = (0..self.snakes.len())
+            .filter(|&i| last_death(&self.snakes[i]) == longest_lived)
+            .collect();
+
+        let best = candidates.iter().map(|&i| self.snakes[i].len()).max()?;
+        let mut best_snakes = candidates
+            .into_iter()
+            .filter(|&i| self.snakes[i].len() == best);
+        match (best_snakes.next(), best_snakes.next()) {
+            (Some(winner), None) => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Score of every snake: its length.
+    pub fn scores(&self) -> Vec<u32> {
+        self.snakes.iter().map(|s| s.len() as u32).collect()
+    }
+
+    /// Whether a cell is on the grid.
+    pub fn in_bounds(&self, position: Position) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.settings.width
+            && position.y < self.settings.height
+    }
+
+    /// Whether a cell is taken by a living snake.
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.snakes
+            .iter()
+            .filter(|s| s.is_alive())
+            .any(|s| s.body.contains(&position))
+    }
+
+    /// How snake `index` dies this tick, if it does.
+    fn check_death(&self, index: usize) -> Option<Death> {
+        let snake = &self.snakes[index];
+        if !snake.is_alive() {
+            return None;
+        }
+
+        let head = snake.head();
+        if !self.in_bounds(head) {
+            return Some(Death::Wall);
+        }
+
+        for (other_index, other) in self.snakes.iter().enumerate() {
+            if !other.is_alive() {
+                continue;
+            }
+            // A snake's own head is the only cell it may share
+            let skip = if other_index == index { 1 } else { 0 };
+            if other.body.iter().skip(skip).any(|&cell| cell == head) {
+                return Some(Death::Collision);
+            }
+        }
+
+        None
+    }
+
+    /// Pick a random free cell for the next food.
+    fn spawn_food(&mut self) -> Option<Position> {
+        let mut free = Vec::new();
+        for y in 0..self.settings.height {
+            for x in 0..self.settings.width {
+                let position = Position::new(x, y);
+                if !self.is_occupied(position) {
+                    free.push(position);
+                }
+            }
+        }
+
+        if free.is_empty() {
+            return None;
+        }
+        let index = self.rng.below(free.len() as u32);
+        Some(free[index as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            width: 10,
+            height: 10,
+            max_ticks: 100,
+            start_length: 3,
+        }
+    }
+
+    /// A game with food moved out of the way so it doesn't interfere.
+    fn game(players: usize) -> Game {
+        let mut game = Game::new(1, settings(), players);
+        game.food = Some(Position::new(0, 0));
+        game
+    }
+
+    /// Simple policy used to drive test games: head towards the food.
+    fn chase_food(game: &Game, index: usize) -> Turn {
+        let snake = &game.snakes[index];
+        let Some(food) = game.food else {
+            return Turn::Straight;
+        };
+        let head = snake.head();
+        let options = [Turn::Straight, Turn::Left, Turn::Right];
+        let distance = |turn: &Turn| {
+            let direction = match turn {
+                Turn::Straight => snake.direction,
+                Turn::Left => snake.direction.turn_left(),
+                Turn::Right => snake.direction.turn_right(),
+            };
+            let next = head.step(direction);
+            let blocked = !game.in_bounds(next) || game.is_occupied(next);
+            let distance = (next.x - food.x).abs() + (next.y - food.y).abs();
+            (blocked, distance)
+        };
+        options.into_iter().min_by_key(distance).unwrap()
+    }
+
+    fn play(seed: u64, players: usize) -> Game {
+        let mut game = Game::new(seed, settings(), players);
+        while !game.is_over() {
+            let turns: Vec<Turn> = (0..players).map(|i| chase_food(&game, i)).collect();
+            game.step(&turns);
+        }
+        game
+    }
+
+    #[test]
+    fn snakes_start_spread_out_and_facing_up() {
+        let game = Game::new(1, settings(), 2);
+        assert_eq!(game.snakes[0].head(), Position::new(3, 5));
+        assert_eq!(game.snakes[1].head(), Position::new(6, 5));
+        assert_eq!(game.snakes[0].len(), 3);
+        assert!(game.snakes.iter().all(|s| s.direction == Direction::Up));
+    }
+
+    #[test]
+    fn snakes_start_with_at_least_one_segment() {
+        let settings = Settings {
+            start_length: 0,
+            ..settings()
+        };
+        let mut game = Game::new(1, settings, 2);
+        assert_eq!(game.settings.start_length, 1);
+        assert!(game.snakes.iter().all(|s| s.len() == 1));
+
+        game.step(&[]);
+        assert_eq!(game.snakes[0].head(), Position::new(3, 6));
+    }
+
+    #[test]
+    fn snakes_start_on_the_grid() {
+        let settings = Settings {
+            height: 4,
+            start_length: 10,
+            ..settings()
+        };
+        let game = Game::new(1, settings, 2);
+        assert_eq!(game.settings.start_length, 3);
+        for snake in &game.snakes {
+            assert!(snake.body.iter().all(|&cell| game.in_bounds(cell)));
+        }
+    }
+
+    #[test]
+    fn snakes_start_in_columns_of_their_own() {
+        let settings = Settings {
+            width: 2,
+            ..settings()
+        };
+        let game = Game::new(1, settings, 4);
+        assert_eq!(game.settings.width, 5);
+        let columns: Vec<i32> = game.snakes.iter().map(|s| s.head().x).collect();
+        assert_eq!(columns, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn default_settings_are_not_clamped() {
+        for players in 1..=4 {
+            assert_eq!(Settings::default().clamped(players), Settings::default());
+        }
+    }
+
+    #[test]
+    fn food_starts_on_a_free_cell() {
+        for seed in 0..50 {
+            let game = Game::new(seed, settings(), 2);
+            let food = game.food.unwrap();
+            assert!(game.in_bounds(food));
+            assert!(!game.is_occupied(food));
+        }
+    }
+
+    #[test]
+    fn snake_moves_one_cell_per_tick() {
+        let mut game = game(1);
+        game.step(&[Turn::Straight]);
+        assert_eq!(game.snakes[0].head(), Position::new(5, 6));
+        assert_eq!(game.snakes[0].len(), 3);
+        assert_eq!(game.tick, 1);
+    }
+
+    #[test]
+    fn turns_are_relative_to_current_direction() {
+        let mut game = game(1);
+        game.step(&[Turn::Left]);
+        assert_eq!(game.snakes[0].direction, Direction::Left);
+        assert_eq!(game.snakes[0].head(), Position::new(4, 5));
+
+        game.step(&[Turn::Left]);
+        assert_eq!(game.snakes[0].direction, Direction::Down);
+        assert_eq!(game.snakes[0].head(), Position::new(4, 4));
+    }
+
+    #[test]
+    fn missing_turns_mean_straight() {
+        let mut game = game(2);
+        game.step(&[]);
+        assert_eq!(game.snakes[0].head(), Position::new(3, 6));
+        assert_eq!(game.snakes[1].head(), Position::new(6, 6));
+    }
+
+    #[test]
+    fn eating_food_grows_the_snake_and_moves_food() {
+        let mut game = game(1);
+        game.food = Some(Position::new(5, 6));
+        game.step(&[Turn::Straight]);
+
+        assert_eq!(game.snakes[0].len(), 4);
+        assert_eq!(game.scores(), vec![4]);
+        let food = game.food.unwrap();
+        assert_ne!(food, Position::new(5, 6));
+        assert!(!game.is_occupied(food));
+    }
+
+    #[test]
+    fn hitting_a_wall_kills_the_snake() {
+        let mut game = game(1);
+        for _ in 0..4 {
+            game.step(&[Turn::Straight]);
+        }
+        assert!(game.snakes[0].is_alive());
+
+        game.step(&[Turn::Straight]);
+        assert_eq!(game.snakes[0].death, Some((Death::Wall, 4)));
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn running_into_itself_kills_the_snake() {
+        let mut game = game(1);
+        game.snakes[0].body = VecDeque::from([
+            Position::new(5, 5),
+            Position::new(5, 4),
+            Position::new(4, 4),
+            Position::new(4, 5),
+            Position::new(4, 6),
+        ]);
+        game.step(&[Turn::Left]);
+        assert_eq!(game.snakes[0].death, Some((Death::Collision, 0)));
+    }
+
+    #[test]
+    fn following_own_tail_is_allowed() {
+        let mut game = game(1);
+        // A square loop where the head moves into the cell the tail leaves
+        game.snakes[0].body = VecDeque::from([
+            Position::new(5, 5),
+            Position::new(5, 4),
+            Position::new(4, 4),
+            Position::new(4, 5),
+        ]);
+        game.step(&[Turn::Left]);
+        assert!(game.snakes[0].is_alive());
+    }
+
+    #[test]
+    fn running_into_another_snake_kills_only_the_attacker() {
+        let mut game = game(2);
+        game.snakes[0].direction = Direction::Right;
+        game.snakes[0].body = VecDeque::from([Position::new(5, 5), Position::new(4, 5)]);
+        game.snakes[1].body = VecDeque::from([
+            Position::new(6, 6),
+            Position::new(6, 5),
+            Position::new(6, 4),
+        ]);
+        game.step(&[Turn::Straight, Turn::Straight]);
+
+        assert_eq!(game.snakes[0].death, Some((Death::Collision, 0)));
+        assert!(game.snakes[1].is_alive());
+        assert!(game.is_over());
+        assert_eq!(game.winner(), Some(1));
+    }
+
+    #[test]
+    fn head_on_collision_kills_both() {
+        let mut game = game(2);
+        game.snakes[0].direction = Direction::Right;
+        game.snakes[0].body = VecDeque::from([Position::new(4, 5), Position::new(3, 5)]);
+        game.snakes[1].direction = Direction::Left;
+        game.snakes[1].body = VecDeque::from([Position::new(6, 5), Position::new(7, 5)]);
+        game.step(&[Turn::Straight, Turn::Straight]);
+
+        assert!(!game.snakes[0].is_alive());
+        assert!(!game.snakes[1].is_alive());
+        assert!(game.is_over());
+        assert_eq!(game.winner(), None);
+    }
+
+    #[test]
+    fn dead_snakes_no_longer_block_cells() {
+        let mut game = game(3);
+        game.snakes[2].death = Some((Death::Wall, 0));
+        let dead_cell = game.snakes[2].body[1];
+        assert!(!game.is_occupied(dead_cell));
+    }
+
+    #[test]
+    fn longest_snake_wins_on_timeout() {
+        let mut game = game(2);
+        game.snakes[1].body.push_back(Position::new(6, 2));
+        game.tick = game.settings.max_ticks;
+        assert!(game.is_over());
+        assert_eq!(game.winner(), Some(1));
+    }
+
+    #[test]
+    fn equal_snakes_draw_on_timeout() {
+        let mut game = game(2);
+        game.tick = game.settings.max_ticks;
+        assert_eq!(game.winner(), None);
+    }
+
+    #[test]
+    fn finished_game_does_not_change() {
+        let mut game = play(3, 2);
+        let before = game.clone();
+        game.step(&[Turn::Left, Turn::Left]);
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_game() {
+        assert_eq!(play(42, 2), play(42, 2));
+    }
+
+    #[test]
+    fn different_seeds_place_food_differently() {
+        let foods: Vec<_> = (0..10)
+            .map(|seed| Game::new(seed, settings(), 2).food)
+            .collect();
+        assert!(foods.iter().any(|&food| food != foods[0]));
+    }
+
+    #[test]
+    fn recorded_game_is_locked_in() {
+        // Changing these values would change every recorded game
+        let game = play(42, 2);
+        assert_eq!(game.tick, 16);
+        assert_eq!(game.scores(), vec![4, 5]);
+        assert_eq!(game.winner(), Some(1));
+        assert_eq!(game.food, Some(Position::new(0, 4)));
+        assert_eq!(game.snakes[0].death, Some((Death::Collision, 15)));
+    }
+}
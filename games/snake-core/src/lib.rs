@@ -0,0 +1,17 @@
+//! Headless snake rules shared by the backend and the Bevy game.
+//!
+//! Everything here is plain Rust with no rendering or platform code, so the
+//! server can run matches and the WASM build can show them. The simulation
+//! only uses integer math and the seeded random number generator from
+//! `game-core`, which means the same seed and the same inputs give the same
+//! game everywhere.
+
+mod game;
+mod rules;
+
+pub use game::*;
+pub use game_core::{GameRules, Rng, Termination};
+pub use rules::{COLLISION, Observation};
+
+/// How many game ticks run per second when a game is shown in real time.
+pub const TICKS_PER_SECOND: u32 = 8;
@@ -0,0 +1,176 @@
+use crate::{Direction, Game, Position, Settings, Turn};
+use game_core::{GameRules, Termination};
+use serde::Serialize;
+
+/// Reason a game ends when snakes crashed into walls or each other.
+pub const COLLISION: &str = "collision";
+
+/// What one snake knows about the game on a tick.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Observation {
+    pub tick: u32,
+    pub width: i32,
+    pub height: i32,
+    pub head: Position,
+    pub direction: Direction,
+    pub length: usize,
+    pub food: Option<Position>,
+    /// The snake's own cells, from head to tail.
+    pub body: Vec<Position>,
+    /// Cells of every other living snake, from head to tail.
+    pub opponents: Vec<Vec<Position>>,
+}
+
+impl GameRules for Game {
+    const NAME: &'static str = "snake";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const MIN_PLAYERS: usize = 1;
+    const MAX_PLAYERS: usize = 4;
+
+    type Settings = Settings;
+    type Action = Turn;
+    type Observation = Observation;
+
+    fn new_game(seed: u64, settings: Settings, players: usize) -> Self {
+        Game::new(seed, settings, players)
+    }
+
+    fn observe(&self, player: usize) -> Observation {
+        let snake = &self.snakes[player];
+        let opponents = self
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|&(i, other)| i != player && other.is_alive())
+            .map(|(_, other)| other.body.iter().copied().collect())
+            .collect();
+
+        Observation {
+            tick: self.tick,
+            width: self.settings.width,
+            height: self.settings.height,
+            head: snake.head(),
+            direction: snake.direction,
+            length: snake.len(),
+            food: self.food,
+            body: snake.body.iter().copied().collect(),
+            opponents,
+        }
+    }
+
+    fn apply(&mut self, actions: &[Turn]) {
+        self.step(actions);
+    }
+
+    fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    fn is_over(&self) -> bool {
+        Game::is_over(self)
+    }
+
+    fn termination(&self) -> Option<Termination> {
+        if !Game::is_over(self) {
+            return None;
+        }
+        let alive = self.snakes.iter().filter(|s| s.is_alive()).count();
+        if alive == 0 || (self.snakes.len() > 1 && alive == 1) {
+            Some(Termination::Finished(COLLISION))
+        } else {
+            Some(Termination::Timeout)
+        }
+    }
+
+    fn winner(&self) -> Option<usize> {
+        Game::winner(self)
+    }
+
+    fn scores(&self) -> Vec<u32> {
+        Game::scores(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_core::{Recorder, Replay};
+
+    #[test]
+    fn observation_shows_own_snake_and_opponents() {
+        let game = Game::new_game(1, Settings::default(), 3);
+        let observation = game.observe(1);
+
+        assert_eq!(observation.head, game.snakes[1].head());
+        assert_eq!(observation.direction, Direction::Up);
+        assert_eq!(observation.length, 3);
+        assert_eq!(observation.body.len(), 3);
+        assert_eq!(observation.food, game.food);
+        assert_eq!(observation.opponents.len(), 2);
+        assert_eq!(observation.opponents[0][0], game.snakes[0].head());
+    }
+
+    #[test]
+    fn dead_snakes_are_not_opponents() {
+        let mut game = Game::new_game(1, Settings::default(), 3);
+        game.snakes[2].death = Some((crate::Death::Wall, 0));
+
+        assert_eq!(game.observe(0).opponents.len(), 1);
+    }
+
+    #[test]
+    fn crash_ends_with_collision() {
+        let mut game = Game::new_game(1, Settings::default(), 2);
+        assert_eq!(game.termination(), None);
+
+        game.snakes[0].death = Some((crate::Death::Wall, 0));
+        assert_eq!(game.termination(), Some(Termination::Finished(COLLISION)));
+    }
+
+    #[test]
+    fn surviving_until_the_end_is_a_timeout() {
+        let settings = Settings {
+            max_ticks: 2,
+            ..Settings::default()
+        };
+        let mut game = Game::new_game(1, settings, 2);
+        game.apply(&[]);
+        game.apply(&[]);
+
+        assert_eq!(game.termination(), Some(Termination::Timeout));
+    }
+
+    #[test]
+    fn apply_steps_the_game() {
+        let mut game = Game::new_game(1, Settings::default(), 2);
+        game.apply(&[Turn::Left, Turn::Right]);
+
+        assert_eq!(GameRules::tick(&game), 1);
+        assert_eq!(game.snakes[0].direction, Direction::Left);
+        assert_eq!(game.snakes[1].direction, Direction::Right);
+    }
+
+    #[test]
+    fn replay_round_trip() {
+        let mut game = Game::new_game(3, Settings::default(), 2);
+        let mut recorder = Recorder::new(&game, 3, Settings::default(), Vec::new());
+        while !game.is_over() {
+            // Circle around in small squares until something goes wrong
+            let turn = if game.tick % 3 == 0 {
+                Turn::Left
+            } else {
+                Turn::Straight
+            };
+            let actions = vec![turn, Turn::Right];
+            game.apply(&actions);
+            recorder.record(actions, &game);
+        }
+
+        let bytes = recorder.finish().encode();
+        let replay = Replay::<Game>::decode(&bytes).unwrap();
+
+        assert_eq!(replay.header.game, "snake");
+        assert_eq!(replay.ticks(), game.tick);
+        assert_eq!(replay.state_at(game.tick), Some(game));
+    }
+}
@@ -1,6 +1,22 @@
 use bevy::prelude::*;
+use snake_core::{Game, Position, Settings, TICKS_PER_SECOND, Turn};
 use wasm_bindgen::prelude::*;
 
+mod playback;
+
+use playback::{PlaybackPlugin, Player};
+pub use playback::{load_replay, pause, play, seek, set_speed, watch_frame};
+
+/// Size of one grid cell on screen, in pixels.
+const CELL_SIZE: f32 = 24.0;
+
+const SNAKE_COLORS: [Color; 4] = [
+    Color::srgb(0.39, 0.4, 0.95),
+    Color::srgb(0.96, 0.45, 0.2),
+    Color::srgb(0.2, 0.8, 0.45),
+    Color::srgb(0.9, 0.3, 0.6),
+];
+
 #[wasm_bindgen(start)]
 pub fn run() {
     App::new()
@@ -14,27 +30,122 @@ pub fn run() {
             }),
             ..default()
         }))
+        .add_plugins(PlaybackPlugin)
+        .insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND as f64))
+        .insert_resource(Simulation::new(0))
         .add_systems(Startup, setup)
+        .add_systems(FixedUpdate, advance)
+        .add_systems(Update, draw)
         .run();
 }
 
+/// The game being shown, simulated by `snake-core`.
+#[derive(Resource)]
+struct Simulation {
+    game: Game,
+    seed: u64,
+}
+
+impl Simulation {
+    fn new(seed: u64) -> Self {
+        Self {
+            game: Game::new(seed, Settings::default(), 2),
+            seed,
+        }
+    }
+}
+
+/// Marks sprites that are redrawn every frame.
+#[derive(Component)]
+struct Cell;
+
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2d);
+}
 
-    commands
-        .spawn(Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
+/// Step the demo game at a fixed rate, starting a new one when it ends. Does
+/// nothing while a recorded or live match is shown.
+fn advance(player: Res<Player>, mut simulation: ResMut<Simulation>) {
+    if !player.is_demo() {
+        return;
+    }
+
+    if simulation.game.is_over() {
+        let seed = simulation.seed + 1;
+        *simulation = Simulation::new(seed);
+        return;
+    }
+
+    let game = &simulation.game;
+    let turns: Vec<Turn> = (0..game.snakes.len()).map(|i| demo_turn(game, i)).collect();
+    simulation.game.step(&turns);
+}
+
+/// Simple built-in player for the demo: avoid crashing, then head for the food.
+fn demo_turn(game: &Game, index: usize) -> Turn {
+    let snake = &game.snakes[index];
+    let head = snake.head();
+    let food = game.food.unwrap_or(head);
+
+    [Turn::Straight, Turn::Left, Turn::Right]
+        .into_iter()
+        .min_by_key(|&turn| {
+            let direction = match turn {
+                Turn::Straight => snake.direction,
+                Turn::Left => snake.direction.turn_left(),
+                Turn::Right => snake.direction.turn_right(),
+            };
+            let next = head.step(direction);
+            let blocked = !game.in_bounds(next) || game.is_occupied(next);
+            let distance = (next.x - food.x).abs() + (next.y - food.y).abs();
+            (blocked, distance)
         })
-        .with_child((
-            Text::new("Snake"),
-            TextFont {
-                font_size: 64.0,
-                ..default()
-            },
-            TextColor(Color::WHITE),
+        .unwrap_or_default()
+}
+
+/// Redraw the grid, the snakes and the food.
+fn draw(mut commands: Commands, simulation: Res<Simulation>, cells: Query<Entity, With<Cell>>) {
+    for entity in &cells {
+        commands.entity(entity).despawn();
+    }
+
+    let game = &simulation.game;
+    let to_screen = |position: Position, z: f32| {
+        let x = (position.x as f32 - (game.settings.width - 1) as f32 / 2.0) * CELL_SIZE;
+        let y = (position.y as f32 - (game.settings.height - 1) as f32 / 2.0) * CELL_SIZE;
+        Transform::from_xyz(x, y, z)
+    };
+
+    let board = Vec2::new(
+        game.settings.width as f32 * CELL_SIZE,
+        game.settings.height as f32 * CELL_SIZE,
+    );
+    commands.spawn((
+        Cell,
+        Sprite::from_color(Color::srgb(0.06, 0.09, 0.16), board),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+    ));
+
+    if let Some(food) = game.food {
+        commands.spawn((
+            Cell,
+            Sprite::from_color(Color::srgb(0.94, 0.27, 0.27), Vec2::splat(CELL_SIZE * 0.7)),
+            to_screen(food, 1.0),
         ));
+    }
+
+    for (i, snake) in game.snakes.iter().enumerate() {
+        let mut color = SNAKE_COLORS[i % SNAKE_COLORS.len()];
+        if !snake.is_alive() {
+            color = color.with_alpha(0.3);
+        }
+        for (segment, &position) in snake.body.iter().enumerate() {
+            let size = if segment == 0 { 0.95 } else { 0.8 };
+            commands.spawn((
+                Cell,
+                Sprite::from_color(color, Vec2::splat(CELL_SIZE * size)),
+                to_screen(position, 2.0),
+            ));
+        }
+    }
 }
@@ -0,0 +1,80 @@
+//! Replay playback and live viewing, controlled from JavaScript.
+//!
+//! The exported functions only queue commands, the Bevy app picks them up on
+//! its next frame. While a replay or live match is loaded, it is shown
+//! instead of the demo.
+
+use bevy::prelude::*;
+use game_core::{Command, CommandQueue, Frame, Replay};
+use snake_core::{Game, TICKS_PER_SECOND};
+use wasm_bindgen::prelude::*;
+
+use crate::Simulation;
+
+/// Commands waiting for the next frame.
+static COMMANDS: CommandQueue<Game> = CommandQueue::new();
+
+/// Show a replay downloaded from `GET /matches/{id}/replay`, paused at the
+/// first tick.
+#[wasm_bindgen]
+pub fn load_replay(bytes: &[u8]) -> Result<(), JsError> {
+    let replay = Replay::<Game>::decode(bytes)?;
+    COMMANDS.send(Command::Load(Box::new(replay)));
+    Ok(())
+}
+
+/// Show the next frame of a live match from `GET /matches/{id}/live`.
+#[wasm_bindgen]
+pub fn watch_frame(bytes: &[u8]) -> Result<(), JsError> {
+    let frame = Frame::<Game>::decode(bytes)?;
+    COMMANDS.send(Command::Watch(Box::new(frame)));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn play() {
+    COMMANDS.send(Command::Play);
+}
+
+#[wasm_bindgen]
+pub fn pause() {
+    COMMANDS.send(Command::Pause);
+}
+
+/// Jump to the start of `tick`.
+#[wasm_bindgen]
+pub fn seek(tick: u32) {
+    COMMANDS.send(Command::Seek(tick));
+}
+
+/// Play `speed` times faster than real time.
+#[wasm_bindgen]
+pub fn set_speed(speed: f32) {
+    COMMANDS.send(Command::SetSpeed(speed));
+}
+
+/// What is being shown.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub(crate) struct Player(game_core::Player<Game>);
+
+/// Adds replay playback to the app.
+pub(crate) struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Player>()
+            .add_systems(Update, (receive_commands, advance).chain());
+    }
+}
+
+/// Apply the commands queued since the last frame.
+fn receive_commands(mut player: ResMut<Player>) {
+    player.receive(&COMMANDS);
+}
+
+/// Play the replay forward, and show the game as of the current tick.
+fn advance(time: Res<Time>, mut player: ResMut<Player>, mut simulation: ResMut<Simulation>) {
+    if let Some(game) = player.advance(time.delta_secs() * TICKS_PER_SECOND as f32) {
+        simulation.game = game.clone();
+    }
+}